@@ -129,8 +129,6 @@ SYSTEMD_FILES=(
     "systemd/ddns-updater.timer" 
     "systemd/ddns-updater@.service"
     "systemd/ddns-updater@.timer"
-    "systemd/ddns-backup-cleanup.service"
-    "systemd/ddns-backup-cleanup.timer"
     "systemd/ddns-updater.target"
 )
 
@@ -146,7 +144,6 @@ SCRIPT_FILES=(
     "systemd/install-systemd.sh"
     "systemd/install-systemd-advanced.sh" 
     "systemd/uninstall-systemd.sh"
-    "systemd/ddns-backup-cleanup.sh"
 )
 
 for file in "${SCRIPT_FILES[@]}"; do
@@ -1,8 +1,10 @@
+pub mod scheduler;
 pub mod services;
 pub mod use_cases;
 
 #[cfg(test)]
 mod services_test;
 
+pub use scheduler::*;
 pub use services::*;
 pub use use_cases::*;
@@ -0,0 +1,151 @@
+use crate::application::services::HostConfig;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// Delay before the first retry of a failed check; doubled on every further failure
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How often a host is checked and how failed checks are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckSchedule {
+    pub interval: Duration,
+    pub jitter: Duration,
+    pub max_backoff: Duration,
+}
+
+impl CheckSchedule {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            jitter: Duration::ZERO,
+            max_backoff: Duration::from_secs(3600),
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Delay before the next check, without jitter, after `failures` consecutive failed checks.
+    ///
+    /// Failed checks are retried after 30 seconds (or the interval, if shorter), doubling on
+    /// every further failure up to the maximum backoff.
+    pub fn base_delay(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return self.interval;
+        }
+        let first_retry = RETRY_DELAY.min(self.interval);
+        let backoff = first_retry.saturating_mul(1u32 << (failures - 1).min(16));
+        backoff.min(self.max_backoff.max(first_retry))
+    }
+
+    /// Delay before the next check with a random share of the jitter added
+    pub fn next_delay(&self, failures: u32) -> Duration {
+        self.base_delay(failures) + self.jitter.mul_f64(random_fraction())
+    }
+}
+
+/// A host checked by the daemon
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostJob {
+    pub host: HostConfig,
+    pub schedule: CheckSchedule,
+}
+
+#[derive(Debug)]
+struct ScheduledHost {
+    job: HostJob,
+    failures: u32,
+    next_run: Instant,
+}
+
+/// Keeps track of when each host is checked next
+#[derive(Debug)]
+pub struct Scheduler {
+    hosts: Vec<ScheduledHost>,
+}
+
+impl Scheduler {
+    /// Schedule the given hosts, all of them due right away
+    pub fn new(jobs: Vec<HostJob>, now: Instant) -> Self {
+        let hosts = jobs
+            .into_iter()
+            .map(|job| ScheduledHost {
+                job,
+                failures: 0,
+                next_run: now,
+            })
+            .collect();
+        Self { hosts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// Time the earliest host is due
+    pub fn next_due(&self) -> Option<Instant> {
+        self.hosts.iter().map(|host| host.next_run).min()
+    }
+
+    /// Hosts due at `now`, earliest first
+    pub fn due(&self, now: Instant) -> Vec<HostJob> {
+        let mut due: Vec<&ScheduledHost> = self
+            .hosts
+            .iter()
+            .filter(|host| host.next_run <= now)
+            .collect();
+        due.sort_by_key(|host| host.next_run);
+        due.into_iter().map(|host| host.job.clone()).collect()
+    }
+
+    /// Record the outcome of a host's check and schedule its next one; returns the delay
+    pub fn record(&mut self, hostname: &str, success: bool, now: Instant) -> Option<Duration> {
+        let host = self
+            .hosts
+            .iter_mut()
+            .find(|host| host.job.host.hostname == hostname)?;
+        host.failures = if success { 0 } else { host.failures + 1 };
+        let delay = host.job.schedule.next_delay(host.failures);
+        host.next_run = now + delay;
+        Some(delay)
+    }
+
+    /// Make every host due at `now`, e.g. because the address they follow just changed. Failed
+    /// checks keep counting towards the backoff.
+    pub fn wake_all(&mut self, now: Instant) {
+        for host in &mut self.hosts {
+            host.next_run = host.next_run.min(now);
+        }
+    }
+
+    /// Number of consecutive failed checks of a host
+    pub fn failures(&self, hostname: &str) -> u32 {
+        self.hosts
+            .iter()
+            .find(|host| host.job.host.hostname == hostname)
+            .map_or(0, |host| host.failures)
+    }
+}
+
+/// Random number in `[0, 1)`, seeded by the standard library's per-process hash keys
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos()),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+#[path = "scheduler_tests.rs"]
+mod tests;
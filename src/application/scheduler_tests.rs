@@ -0,0 +1,123 @@
+use super::*;
+use std::path::PathBuf;
+
+fn job(hostname: &str, schedule: CheckSchedule) -> HostJob {
+    HostJob {
+        host: HostConfig::new(
+            hostname,
+            vec![PathBuf::from(format!("/etc/nginx/{}.conf", hostname))],
+        ),
+        schedule,
+    }
+}
+
+#[test]
+fn test_backoff_doubles_up_to_the_maximum() {
+    let schedule =
+        CheckSchedule::new(Duration::from_secs(300)).with_max_backoff(Duration::from_secs(200));
+
+    assert_eq!(schedule.base_delay(0), Duration::from_secs(300));
+    assert_eq!(schedule.base_delay(1), Duration::from_secs(30));
+    assert_eq!(schedule.base_delay(2), Duration::from_secs(60));
+    assert_eq!(schedule.base_delay(3), Duration::from_secs(120));
+    assert_eq!(schedule.base_delay(4), Duration::from_secs(200));
+    assert_eq!(schedule.base_delay(60), Duration::from_secs(200));
+
+    // Hosts checked more often than every 30 seconds retry at their own pace first
+    let fast = CheckSchedule::new(Duration::from_secs(10));
+    assert_eq!(fast.base_delay(1), Duration::from_secs(10));
+    assert_eq!(fast.base_delay(2), Duration::from_secs(20));
+}
+
+#[test]
+fn test_jitter_stays_within_bounds() {
+    let schedule = CheckSchedule::new(Duration::from_secs(60)).with_jitter(Duration::from_secs(15));
+    for _ in 0..100 {
+        let delay = schedule.next_delay(0);
+        assert!(delay >= Duration::from_secs(60));
+        assert!(delay < Duration::from_secs(75));
+    }
+    assert_eq!(
+        CheckSchedule::new(Duration::from_secs(60)).next_delay(0),
+        Duration::from_secs(60)
+    );
+}
+
+#[test]
+fn test_hosts_run_on_their_own_interval() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(
+        vec![
+            job(
+                "fast.example.com",
+                CheckSchedule::new(Duration::from_secs(60)),
+            ),
+            job(
+                "slow.example.com",
+                CheckSchedule::new(Duration::from_secs(600)),
+            ),
+        ],
+        start,
+    );
+
+    // Everything is checked right after startup
+    assert_eq!(scheduler.due(start).len(), 2);
+    scheduler.record("fast.example.com", true, start);
+    scheduler.record("slow.example.com", true, start);
+    assert!(scheduler.due(start).is_empty());
+    assert_eq!(scheduler.next_due(), Some(start + Duration::from_secs(60)));
+
+    let later = start + Duration::from_secs(60);
+    let due = scheduler.due(later);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].host.hostname, "fast.example.com");
+}
+
+#[test]
+fn test_failures_back_off_until_a_check_succeeds() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(
+        vec![job(
+            "home.example.com",
+            CheckSchedule::new(Duration::from_secs(300)),
+        )],
+        start,
+    );
+
+    assert_eq!(
+        scheduler.record("home.example.com", false, start),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+        scheduler.record("home.example.com", false, start),
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(scheduler.failures("home.example.com"), 2);
+
+    assert_eq!(
+        scheduler.record("home.example.com", true, start),
+        Some(Duration::from_secs(300))
+    );
+    assert_eq!(scheduler.failures("home.example.com"), 0);
+
+    assert_eq!(scheduler.record("unknown.example.com", true, start), None);
+}
+
+#[test]
+fn test_wake_all_makes_every_host_due() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(
+        vec![job(
+            "home.example.com",
+            CheckSchedule::new(Duration::from_secs(600)),
+        )],
+        start,
+    );
+    scheduler.record("home.example.com", false, start);
+    assert!(scheduler.due(start).is_empty());
+
+    let later = start + Duration::from_secs(5);
+    scheduler.wake_all(later);
+    assert_eq!(scheduler.due(later).len(), 1);
+    assert_eq!(scheduler.failures("home.example.com"), 1);
+}
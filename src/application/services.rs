@@ -1,12 +1,23 @@
-use crate::domain::entities::WebServerType;
+use crate::domain::entities::{
+    AddressSource, NotificationChannel, PublicIpBackend, PublicIpConfig, ResolverConfig,
+    StorageBackend, WebServerType,
+};
 use crate::domain::ports::{
-    ConfigDiscoveryService, IpRepository, NetworkService, NotificationService, WebServerHandler,
+    ConfigDiscoveryService, DnsResolver, IpRepository, NetworkService, NotificationService,
+    WebServerHandler,
+};
+use crate::domain::value_objects::BackupRetention;
+use crate::infrastructure::webservers::{
+    ApacheHandler, CaddyHandler, NginxHandler, TraefikHandler,
 };
-use crate::infrastructure::webservers::{ApacheHandler, NginxHandler};
 use crate::infrastructure::{
-    ConsoleNotificationService, FileIpRepository, FileSystemConfigDiscovery, HttpNetworkService,
+    CompositeNotificationService, ConsoleNotificationService, DohResolver, FileIpRepository,
+    FileSystemConfigDiscovery, GatewayNetworkService, HttpNetworkService, LogNotificationService,
+    NameserverResolver, SqliteIpRepository, SystemResolver, WebhookNotificationService,
 };
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Application service factory for creating configured services
 pub struct ServiceFactory;
@@ -20,6 +31,20 @@ impl ServiceFactory {
         Ok(Arc::new(repo))
     }
 
+    /// Create an IP repository of the given backend; the SQLite database lives in the storage
+    /// directory and starts out with the JSON entries stored there
+    pub fn create_ip_repository_with(
+        storage_dir: std::path::PathBuf,
+        backend: &StorageBackend,
+    ) -> Result<Arc<dyn IpRepository>, Box<dyn std::error::Error + Send + Sync>> {
+        match backend {
+            StorageBackend::Json => Self::create_ip_repository(storage_dir),
+            StorageBackend::Sqlite => {
+                Ok(Arc::new(SqliteIpRepository::in_storage_dir(&storage_dir)?))
+            }
+        }
+    }
+
     /// Create a web server handler for the given server type
     pub fn create_web_server_handler(
         server_type: WebServerType,
@@ -27,15 +52,9 @@ impl ServiceFactory {
     ) -> Arc<dyn WebServerHandler> {
         match server_type {
             WebServerType::Nginx => Arc::new(NginxHandler::with_backup_dir(backup_dir)),
-            WebServerType::Apache => Arc::new(ApacheHandler::new()), // TODO: Add backup_dir support
-            WebServerType::Caddy => {
-                // TODO: Implement Caddy handler
-                Arc::new(NginxHandler::with_backup_dir(backup_dir)) // Fallback to Nginx for now
-            }
-            WebServerType::Traefik => {
-                // TODO: Implement Traefik handler
-                Arc::new(NginxHandler::with_backup_dir(backup_dir)) // Fallback to Nginx for now
-            }
+            WebServerType::Apache => Arc::new(ApacheHandler::with_backup_dir(backup_dir)),
+            WebServerType::Caddy => Arc::new(CaddyHandler::with_backup_dir(backup_dir)),
+            WebServerType::Traefik => Arc::new(TraefikHandler::with_backup_dir(backup_dir)),
         }
     }
 
@@ -44,37 +63,191 @@ impl ServiceFactory {
         Arc::new(HttpNetworkService::new())
     }
 
+    /// Create a network service with the configured resolver and public IP detection
+    pub fn create_network_service_with(
+        resolver: &ResolverConfig,
+        public_ip: &PublicIpConfig,
+        backend: &PublicIpBackend,
+    ) -> Result<Arc<dyn NetworkService>, Box<dyn std::error::Error + Send + Sync>> {
+        let resolver = Self::create_dns_resolver(resolver);
+        match backend {
+            PublicIpBackend::Http => Ok(Arc::new(
+                HttpNetworkService::new()
+                    .with_resolver(resolver)
+                    .with_public_ip_config(public_ip.clone()),
+            )),
+            #[cfg(target_os = "linux")]
+            PublicIpBackend::Interface { name } => Ok(Arc::new(
+                crate::infrastructure::InterfaceNetworkService::new(name.clone())
+                    .with_resolver(resolver),
+            )),
+            #[cfg(not(target_os = "linux"))]
+            PublicIpBackend::Interface { .. } => {
+                Err("Reading interface addresses is only supported on Linux".into())
+            }
+            PublicIpBackend::Gateway { gateway, protocols } => Ok(Arc::new(
+                GatewayNetworkService::new(*gateway)
+                    .with_protocols(protocols.clone())
+                    .with_resolver(resolver),
+            )),
+        }
+    }
+
+    /// Create a DNS resolver for the given configuration
+    pub fn create_dns_resolver(resolver: &ResolverConfig) -> Arc<dyn DnsResolver> {
+        match resolver {
+            ResolverConfig::System => Arc::new(SystemResolver),
+            ResolverConfig::Nameservers {
+                servers,
+                transport,
+                authoritative,
+            } => Arc::new(
+                NameserverResolver::new(servers.clone())
+                    .with_transport(*transport)
+                    .with_authoritative(*authoritative),
+            ),
+            ResolverConfig::DnsOverHttps { url } => Arc::new(DohResolver::new(url.clone())),
+        }
+    }
+
     /// Create a notification service based on configuration
     pub fn create_notification_service(verbose: bool) -> Arc<dyn NotificationService> {
         Arc::new(ConsoleNotificationService::new(verbose))
     }
 
+    /// Create a notification service sending to all given channels (the console if none)
+    pub fn create_notification_service_for(
+        channels: &[NotificationChannel],
+        verbose: bool,
+    ) -> Arc<dyn NotificationService> {
+        let create = |channel: &NotificationChannel| -> Box<dyn NotificationService> {
+            match channel {
+                NotificationChannel::Console => Box::new(ConsoleNotificationService::new(verbose)),
+                NotificationChannel::Log => Box::new(LogNotificationService::new()),
+                NotificationChannel::Webhook { url } => {
+                    Box::new(WebhookNotificationService::new(url.clone()))
+                }
+            }
+        };
+        match channels {
+            [] => Self::create_notification_service(verbose),
+            [channel] => Arc::from(create(channel)),
+            channels => Arc::new(
+                channels
+                    .iter()
+                    .fold(CompositeNotificationService::new(), |composite, channel| {
+                        composite.add_service(create(channel))
+                    }),
+            ),
+        }
+    }
+
     /// Create a configuration discovery service
     pub fn create_config_discovery_service() -> Arc<dyn ConfigDiscoveryService> {
         Arc::new(FileSystemConfigDiscovery::new())
     }
 }
 
+/// A host and the configuration files holding its allow lists, with per-host overrides of the
+/// application settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostConfig {
+    pub hostname: String,
+    pub config_paths: Vec<PathBuf>,
+    /// Server type of all config files; detected per file if unset
+    pub server_type: Option<WebServerType>,
+    /// Overrides `AppConfig::backup_dir`
+    pub backup_dir: Option<PathBuf>,
+    /// Don't reload the web server; `AppConfig::no_reload` applies to all hosts
+    pub no_reload: bool,
+    /// Overrides `AppConfig::rewrite_prefix`
+    pub rewrite_prefix: Option<bool>,
+    /// Where address changes are reported; the console if empty
+    pub notifications: Vec<NotificationChannel>,
+    /// Time between checks in daemon mode; the daemon's `--interval` if unset
+    pub interval: Option<Duration>,
+}
+
+impl HostConfig {
+    pub fn new(hostname: impl Into<String>, config_paths: Vec<PathBuf>) -> Self {
+        Self {
+            hostname: hostname.into(),
+            config_paths,
+            server_type: None,
+            backup_dir: None,
+            no_reload: false,
+            rewrite_prefix: None,
+            notifications: Vec::new(),
+            interval: None,
+        }
+    }
+
+    pub fn with_server_type(mut self, server_type: Option<WebServerType>) -> Self {
+        self.server_type = server_type;
+        self
+    }
+
+    pub fn with_backup_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.backup_dir = dir;
+        self
+    }
+
+    pub fn with_no_reload(mut self, no_reload: bool) -> Self {
+        self.no_reload = no_reload;
+        self
+    }
+
+    pub fn with_prefix_rewrite(mut self, rewrite_prefix: Option<bool>) -> Self {
+        self.rewrite_prefix = rewrite_prefix;
+        self
+    }
+
+    pub fn with_notifications(mut self, channels: Vec<NotificationChannel>) -> Self {
+        self.notifications = channels;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Option<Duration>) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
 /// Application configuration
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub storage_dir: std::path::PathBuf,
+    pub storage_backend: StorageBackend,
     pub backup_dir: Option<std::path::PathBuf>,
     pub no_reload: bool,
     pub verbose: bool,
     pub backup_retention_days: u16,
     pub max_backups: u16,
+    pub rewrite_prefix: bool,
+    pub resolver: ResolverConfig,
+    pub public_ip: PublicIpConfig,
+    pub address_source: AddressSource,
+    pub public_ip_backend: PublicIpBackend,
+    /// Hosts processed in one run
+    pub hosts: Vec<HostConfig>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             storage_dir: std::path::PathBuf::from("/var/lib/ddns-updater"),
+            storage_backend: StorageBackend::Json,
             backup_dir: None, // Use default backup behavior (same directory as config)
             no_reload: false, // Default: reload server after config changes
             verbose: false,
             backup_retention_days: 30,
             max_backups: 10,
+            rewrite_prefix: false, // Default: only exact address entries are managed
+            resolver: ResolverConfig::System,
+            public_ip: PublicIpConfig::default(), // Default: ipify, ipinfo and icanhazip
+            address_source: AddressSource::Dns,   // Default: resolve the hostname
+            public_ip_backend: PublicIpBackend::Http,
+            hosts: Vec::new(),
         }
     }
 }
@@ -89,6 +262,11 @@ impl AppConfig {
         self
     }
 
+    pub fn with_storage_backend(mut self, backend: StorageBackend) -> Self {
+        self.storage_backend = backend;
+        self
+    }
+
     pub fn with_verbose(mut self, verbose: bool) -> Self {
         self.verbose = verbose;
         self
@@ -104,9 +282,45 @@ impl AppConfig {
         self
     }
 
+    pub fn with_prefix_rewrite(mut self, rewrite_prefix: bool) -> Self {
+        self.rewrite_prefix = rewrite_prefix;
+        self
+    }
+
+    pub fn with_resolver(mut self, resolver: ResolverConfig) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    pub fn with_public_ip(mut self, public_ip: PublicIpConfig) -> Self {
+        self.public_ip = public_ip;
+        self
+    }
+
+    pub fn with_address_source(mut self, address_source: AddressSource) -> Self {
+        self.address_source = address_source;
+        self
+    }
+
+    pub fn with_public_ip_backend(mut self, backend: PublicIpBackend) -> Self {
+        self.public_ip_backend = backend;
+        self
+    }
+
+    pub fn with_hosts(mut self, hosts: Vec<HostConfig>) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
     pub fn with_backup_retention(mut self, days: u16, max_backups: u16) -> Self {
         self.backup_retention_days = days;
         self.max_backups = max_backups;
         self
     }
+
+    /// Retention policy applied to the backups of every config file; zero values fall back to
+    /// the defaults
+    pub fn backup_retention(&self) -> BackupRetention {
+        BackupRetention::new(self.max_backups, self.backup_retention_days).unwrap_or_default()
+    }
 }
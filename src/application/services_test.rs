@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod tests {
     use crate::application::services::{AppConfig, ServiceFactory};
-    use crate::domain::entities::WebServerType;
+    use crate::domain::entities::{
+        AddressSource, PublicIpBackend, PublicIpConfig, ResolverConfig, StorageBackend,
+        WebServerConfig, WebServerType,
+    };
     use std::path::PathBuf;
     use tempfile::tempdir;
 
@@ -11,11 +14,18 @@ mod tests {
 
         let app_config = AppConfig {
             storage_dir: temp_dir.path().to_path_buf(),
+            storage_backend: StorageBackend::Json,
             backup_dir: Some(temp_dir.path().join("backups")),
             no_reload: true,
             verbose: false,
             backup_retention_days: 7,
             max_backups: 10,
+            rewrite_prefix: false,
+            resolver: ResolverConfig::default(),
+            public_ip: PublicIpConfig::default(),
+            address_source: AddressSource::Dns,
+            public_ip_backend: PublicIpBackend::Http,
+            hosts: Vec::new(),
         };
 
         assert_eq!(app_config.storage_dir, temp_dir.path().to_path_buf());
@@ -66,6 +76,26 @@ mod tests {
         assert_eq!(handler.server_type(), WebServerType::Apache);
     }
 
+    #[tokio::test]
+    async fn test_apache_handler_uses_backup_dir() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("site.conf");
+        std::fs::write(&config_path, "Require ip 192.0.2.1\n").unwrap();
+
+        let handler = ServiceFactory::create_web_server_handler(
+            WebServerType::Apache,
+            Some(temp_dir.path().join("backups")),
+        );
+        let config = WebServerConfig::new(config_path, WebServerType::Apache);
+        let backup_path = handler.create_backup(&config).await.unwrap();
+
+        assert_eq!(
+            backup_path.parent(),
+            Some(temp_dir.path().join("backups").as_path())
+        );
+        assert!(backup_path.exists());
+    }
+
     #[test]
     fn test_create_web_server_handler_with_backup_dir() {
         let custom_backup = PathBuf::from("/custom/backup/location");
@@ -83,11 +113,18 @@ mod tests {
 
         let app_config = AppConfig {
             storage_dir: temp_dir.path().to_path_buf(),
+            storage_backend: StorageBackend::Json,
             backup_dir: Some(PathBuf::from("/var/backups/nginx")),
             no_reload: true,
             verbose: true,
             backup_retention_days: 14,
             max_backups: 25,
+            rewrite_prefix: false,
+            resolver: ResolverConfig::default(),
+            public_ip: PublicIpConfig::default(),
+            address_source: AddressSource::Dns,
+            public_ip_backend: PublicIpBackend::Http,
+            hosts: Vec::new(),
         };
 
         // Test that both flags can be set simultaneously
@@ -110,6 +147,18 @@ mod tests {
         // Repository creation succeeded if we reach this point
     }
 
+    #[test]
+    fn test_create_sqlite_ip_repository() {
+        let temp_dir = tempdir().unwrap();
+
+        let result = ServiceFactory::create_ip_repository_with(
+            temp_dir.path().to_path_buf(),
+            &StorageBackend::Sqlite,
+        );
+        assert!(result.is_ok());
+        assert!(temp_dir.path().join("ddns-updater.db").exists());
+    }
+
     #[test]
     fn test_create_network_service() {
         let _service = ServiceFactory::create_network_service();
@@ -133,8 +182,8 @@ mod tests {
         let server_types = vec![
             (WebServerType::Nginx, WebServerType::Nginx),
             (WebServerType::Apache, WebServerType::Apache),
-            (WebServerType::Caddy, WebServerType::Nginx), // Fallback to Nginx
-            (WebServerType::Traefik, WebServerType::Nginx), // Fallback to Nginx
+            (WebServerType::Caddy, WebServerType::Caddy),
+            (WebServerType::Traefik, WebServerType::Traefik),
         ];
 
         for (input_type, expected_type) in server_types {
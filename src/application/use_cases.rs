@@ -1,11 +1,25 @@
-use crate::application::services::{AppConfig, ServiceFactory};
-use crate::domain::entities::{IpEntry, WebServerConfig};
+use crate::application::services::{AppConfig, HostConfig, ServiceFactory};
+use crate::domain::entities::{
+    AddressSource, BackupFile, ChangeOutcome, ChangedFile, EntryMetadata, EntryMigration, IpChange,
+    IpEntry, LegacyFiles, WebServerConfig,
+};
 use crate::domain::ports::{
     ConfigDiscoveryService, IpRepository, NetworkService, NotificationService, WebServerHandler,
 };
-use crate::domain::services::{DdnsUpdateService, UpdateResult, ValidationResult};
+use crate::domain::services::{
+    addresses_in, current_addresses, detect_address_changes, ip_change, normalize_addresses,
+    record_change, DdnsUpdateService, UpdateResult, ValidationResult,
+};
+use crate::domain::value_objects::{AddressDiff, BackupRetention};
+use crate::infrastructure::files::{FileLock, LOCK_TIMEOUT};
 use std::sync::Arc;
 
+/// Lock file of the storage directory
+const STORAGE_LOCK: &str = ".ddns-updater.lock";
+
+/// Directory in the storage directory holding the lock files of the config files
+const CONFIG_LOCK_DIR: &str = "locks";
+
 /// Result of processing multiple configurations
 #[derive(Debug)]
 pub struct MultiConfigResult {
@@ -23,6 +37,39 @@ impl MultiConfigResult {
     }
 }
 
+/// What updating a host would change, worked out by a dry run
+#[derive(Debug, Default)]
+pub struct DryRunResult {
+    /// Address changes that would be applied; empty if the addresses are unchanged
+    pub diff: AddressDiff,
+    /// Config files that would be rewritten
+    pub changes: Vec<FileChange>,
+    pub errors: Vec<(std::path::PathBuf, String)>,
+}
+
+/// Current and new content of a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: std::path::PathBuf,
+    pub original: String,
+    pub updated: String,
+}
+
+/// Backups of one config file, oldest first
+#[derive(Debug, Clone)]
+pub struct ConfigBackups {
+    pub config_path: std::path::PathBuf,
+    pub backups: Vec<BackupInfo>,
+}
+
+/// A backup along with the address change made right after it was taken
+#[derive(Debug, Clone)]
+pub struct BackupInfo {
+    pub file: BackupFile,
+    /// Addresses that differ between the backup and the next version of the file
+    pub change: AddressDiff,
+}
+
 /// Use case for updating DDNS entries
 pub struct UpdateDdnsUseCase {
     service: DdnsUpdateService,
@@ -45,6 +92,18 @@ impl UpdateDdnsUseCase {
         Self { service }
     }
 
+    /// Take the current addresses from the given source instead of resolving the hostname
+    pub fn with_address_source(mut self, address_source: AddressSource) -> Self {
+        self.service = self.service.with_address_source(address_source);
+        self
+    }
+
+    /// Delete backups the policy no longer keeps after every new backup
+    pub fn with_backup_retention(mut self, backup_retention: BackupRetention) -> Self {
+        self.service = self.service.with_backup_retention(backup_retention);
+        self
+    }
+
     /// Execute the DDNS update for a hostname and configuration
     pub async fn execute(
         &self,
@@ -129,8 +188,15 @@ pub struct DdnsApplication {
 impl DdnsApplication {
     /// Create a new application instance with the given configuration
     pub fn new(config: AppConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let ip_repository = ServiceFactory::create_ip_repository(config.storage_dir.clone())?;
-        let network_service = ServiceFactory::create_network_service();
+        let ip_repository = ServiceFactory::create_ip_repository_with(
+            config.storage_dir.clone(),
+            &config.storage_backend,
+        )?;
+        let network_service = ServiceFactory::create_network_service_with(
+            &config.resolver,
+            &config.public_ip,
+            &config.public_ip_backend,
+        )?;
         let notification_service = ServiceFactory::create_notification_service(config.verbose);
         let config_discovery = ServiceFactory::create_config_discovery_service();
 
@@ -160,7 +226,8 @@ impl DdnsApplication {
             .await?;
         eprintln!("DEBUG: Server type detected: {:?}", server_type);
 
-        let config = WebServerConfig::new(config_path, server_type.clone());
+        let config = WebServerConfig::new(config_path, server_type.clone())
+            .with_prefix_rewrite(self.config.rewrite_prefix);
         eprintln!("DEBUG: WebServerConfig created");
 
         // Create appropriate web server handler
@@ -174,9 +241,13 @@ impl DdnsApplication {
             web_server_handler,
             self.network_service.clone(),
             self.notification_service.clone(),
-        );
+        )
+        .with_address_source(self.config.address_source)
+        .with_backup_retention(self.config.backup_retention());
         eprintln!("DEBUG: Use case created, about to execute");
 
+        // Wait for overlapping runs, as `update_host` does
+        let _locks = self.lock(std::slice::from_ref(&config.path)).await?;
         let result = use_case
             .execute_with_options(hostname, &config, self.config.no_reload)
             .await;
@@ -196,19 +267,49 @@ impl DdnsApplication {
         hostname: &str,
         config_paths: Vec<std::path::PathBuf>,
     ) -> Result<MultiConfigResult, Box<dyn std::error::Error + Send + Sync>> {
+        self.update_host(&HostConfig::new(hostname, config_paths))
+            .await
+    }
+
+    /// Hosts from the application configuration
+    pub fn hosts(&self) -> &[HostConfig] {
+        &self.config.hosts
+    }
+
+    /// Update DDNS for all config files of a host with consistent IP storage, using the host's
+    /// overrides of the application settings
+    pub async fn update_host(
+        &self,
+        host: &HostConfig,
+    ) -> Result<MultiConfigResult, Box<dyn std::error::Error + Send + Sync>> {
+        let hostname = host.hostname.as_str();
+        let config_paths = host.config_paths.clone();
+        let host_backup_dir = self.backup_dir_for(host);
+        let no_reload = self.config.no_reload || host.no_reload;
         let mut successes = Vec::new();
         let mut errors = Vec::new();
 
         // Check IP change first - if no change, skip processing all files
-        let current_ip = match self.network_service.resolve_hostname(hostname).await {
-            Ok(ips) if !ips.is_empty() => ips[0],
-            Ok(_) => {
-                let error_msg = format!("Could not resolve hostname: {}", hostname);
+        let resolved_ips = match current_addresses(
+            self.network_service.as_ref(),
+            self.config.address_source,
+            hostname,
+        )
+        .await
+        {
+            Ok(ips) => ips,
+            Err(e) => {
+                let error_msg = e.to_string();
                 for config_path in config_paths {
                     errors.push((config_path, error_msg.clone()));
                 }
                 return Ok(MultiConfigResult { successes, errors });
             }
+        };
+
+        // Overlapping runs wait for each other from here until the stored IPs are updated
+        let _locks = match self.lock(&config_paths).await {
+            Ok(locks) => locks,
             Err(e) => {
                 let error_msg = e.to_string();
                 for config_path in config_paths {
@@ -218,8 +319,15 @@ impl DdnsApplication {
             }
         };
 
-        let stored_ip = match self.ip_repository.load_ip(hostname).await {
-            Ok(ip) => ip,
+        // IPv4 and IPv6 are compared with their stored address independently
+        let changes = match detect_address_changes(
+            self.ip_repository.as_ref(),
+            hostname,
+            &resolved_ips,
+        )
+        .await
+        {
+            Ok(changes) => changes,
             Err(e) => {
                 let error_msg = format!("Failed to load stored IP: {}", e);
                 for config_path in config_paths {
@@ -229,51 +337,158 @@ impl DdnsApplication {
             }
         };
 
-        // If IP hasn't changed, return NoChange for all configs without processing them
-        if let Some(old_ip) = stored_ip {
-            if old_ip == current_ip {
-                if self.config.verbose {
-                    println!(
-                        "ℹ️  No IP change detected ({}), skipping all config file processing",
-                        current_ip
-                    );
-                }
-                for _config_path in &config_paths {
-                    successes.push(UpdateResult::NoChange { ip: current_ip });
-                }
-                return Ok(MultiConfigResult { successes, errors });
+        // Families seen for the first time are recorded without touching any config file
+        if !changes.first_seen.is_empty() {
+            if let Err(e) = self
+                .ip_repository
+                .store_ips(hostname, &changes.first_seen)
+                .await
+            {
+                eprintln!(
+                    "Warning: Failed to store first addresses {}: {}",
+                    format_ips(&changes.first_seen),
+                    e
+                );
             }
         }
 
-        // IP has changed (or no stored IP), process all configs that need actual updates
+        // If no IP has changed, return NoChange for all configs without processing them
+        if changes.diff.is_empty() {
+            if self.config.verbose {
+                println!(
+                    "ℹ️  No IP change detected ({}), skipping all config file processing",
+                    format_ips(&changes.current)
+                );
+            }
+            for _config_path in &config_paths {
+                successes.push(UpdateResult::NoChange {
+                    ips: changes.current.clone(),
+                });
+            }
+            return Ok(MultiConfigResult { successes, errors });
+        }
+
+        // IP has changed, process all configs that need actual updates
         if self.config.verbose {
             println!(
-                "🔄 IP change detected, processing {} config files",
+                "🔄 IP change detected ({}), processing {} config files",
+                changes.diff,
                 config_paths.len()
             );
         }
 
-        // Process all config files without storing IP yet
-        let mut any_updated = false;
+        // Edit all config files without storing IP yet; testing and reloading happen once per
+        // web server after all edits. The batch is all-or-nothing: any failure restores every
+        // edited file from its backup and leaves the stored IPs alone.
+        let mut edited: Vec<(WebServerConfig, UpdateResult)> = Vec::new();
+        let mut failure: Option<String> = None;
         for config_path in config_paths {
             match self
-                .update_ddns_file_only(hostname, config_path.clone(), stored_ip, current_ip)
+                .update_ddns_file_only(host, config_path.clone(), &changes.diff)
                 .await
             {
-                Ok(result) => {
-                    if matches!(result, UpdateResult::Updated { .. }) {
-                        any_updated = true;
-                    }
-                    successes.push(result);
+                Ok((config, result @ UpdateResult::Updated { .. })) => {
+                    edited.push((config, result))
                 }
+                Ok((_, result)) => successes.push(result),
                 Err(e) => {
                     let error_msg = e.to_string();
+                    failure.get_or_insert_with(|| {
+                        format!("{} failed: {}", config_path.display(), error_msg)
+                    });
                     errors.push((config_path, error_msg));
                 }
             }
-        } // Only store the new IP and send notification if at least one file was actually updated
-        if any_updated {
-            if let Err(e) = self.ip_repository.store_ip(hostname, current_ip).await {
+        }
+
+        // Group the edited files by web server
+        let mut batches: Vec<(DdnsUpdateService, Vec<(WebServerConfig, UpdateResult)>)> =
+            Vec::new();
+        for (config, result) in edited {
+            match batches.iter_mut().find(|(_, batch)| {
+                batch
+                    .first()
+                    .is_some_and(|(first, _)| first.server_type == config.server_type)
+            }) {
+                Some((_, batch)) => batch.push((config, result)),
+                None => {
+                    let service = DdnsUpdateService::new(
+                        self.ip_repository.clone(),
+                        ServiceFactory::create_web_server_handler(
+                            config.server_type.clone(),
+                            host_backup_dir.clone(),
+                        ),
+                        self.network_service.clone(),
+                        self.notification_service.clone(),
+                    );
+                    batches.push((service, vec![(config, result)]));
+                }
+            }
+        }
+
+        // Test the combined result of every web server before reloading any of them
+        if failure.is_none() {
+            for (service, batch) in &batches {
+                let configs: Vec<WebServerConfig> =
+                    batch.iter().map(|(config, _)| config.clone()).collect();
+                if let Err(e) = service.test_configs(&configs).await {
+                    failure = Some(format!("{} {}", configs[0].server_type, e));
+                    break;
+                }
+            }
+        }
+
+        let mut reloaded = 0;
+        if failure.is_none() {
+            for (service, batch) in &batches {
+                if let Err(e) = service.reload(no_reload).await {
+                    failure = Some(format!("{} reload failed: {}", batch[0].0.server_type, e));
+                    break;
+                }
+                if !no_reload {
+                    reloaded += 1;
+                }
+            }
+        }
+
+        let files: Vec<ChangedFile> = batches
+            .iter()
+            .flat_map(|(_, batch)| batch)
+            .map(|(config, result)| ChangedFile {
+                path: config.path.clone(),
+                backup_path: match result {
+                    UpdateResult::Updated { backup_path, .. } => Some(backup_path.clone()),
+                    _ => None,
+                },
+            })
+            .collect();
+
+        if let Some(reason) = failure {
+            let mut change = ip_change(hostname, &changes.diff, files, ChangeOutcome::RolledBack);
+            change.error = Some(reason.clone());
+            record_change(self.ip_repository.as_ref(), &change).await;
+            self.roll_back(&batches, reloaded, &reason, &mut errors)
+                .await;
+            return Ok(MultiConfigResult { successes, errors });
+        }
+
+        let applied = !batches.is_empty();
+        if applied {
+            let mut change = ip_change(hostname, &changes.diff, files, ChangeOutcome::Applied);
+            change.reloaded = !no_reload;
+            record_change(self.ip_repository.as_ref(), &change).await;
+        }
+        for (_, batch) in batches {
+            successes.extend(batch.into_iter().map(|(_, result)| result));
+        }
+
+        // Only store the new IPs and send notifications if a file was actually written
+        if applied {
+            if let Err(e) = self
+                .ip_repository
+                .store_ips(hostname, &changes.current)
+                .await
+            {
                 // If we can't store the IP, treat it as an error but don't fail the whole operation
                 eprintln!(
                     "Warning: Failed to store IP after successful updates: {}",
@@ -281,57 +496,412 @@ impl DdnsApplication {
                 );
             }
 
-            // Send notification for the IP change (once for all files)
-            if let Err(e) = self
-                .notification_service
-                .notify_ip_change(hostname, stored_ip, current_ip)
-                .await
-            {
-                eprintln!("Warning: Failed to send notification: {}", e);
+            // Send notifications for the IP changes (once for all files)
+            let notification_service = if host.notifications.is_empty() {
+                self.notification_service.clone()
+            } else {
+                ServiceFactory::create_notification_service_for(
+                    &host.notifications,
+                    self.config.verbose,
+                )
+            };
+            let notifications = changes
+                .diff
+                .replacements()
+                .into_iter()
+                .map(|(old_ip, new_ip)| (Some(old_ip), new_ip))
+                .chain(changes.diff.additions().into_iter().map(|ip| (None, ip)));
+            for (old_ip, new_ip) in notifications {
+                if let Err(e) = notification_service
+                    .notify_ip_change(hostname, old_ip, new_ip)
+                    .await
+                {
+                    eprintln!("Warning: Failed to send notification: {}", e);
+                }
             }
         }
 
         Ok(MultiConfigResult { successes, errors })
     }
 
-    /// Update DDNS for a specific config file without storing IP (used by multi-config processing)
-    async fn update_ddns_file_only(
+    /// Work out what `update_host` would change without writing, backing up, reloading,
+    /// storing or notifying anything
+    pub async fn preview_host(
+        &self,
+        host: &HostConfig,
+    ) -> Result<DryRunResult, Box<dyn std::error::Error + Send + Sync>> {
+        let resolved_ips = current_addresses(
+            self.network_service.as_ref(),
+            self.config.address_source,
+            &host.hostname,
+        )
+        .await?;
+        let changes =
+            detect_address_changes(self.ip_repository.as_ref(), &host.hostname, &resolved_ips)
+                .await
+                .map_err(|e| format!("Failed to load stored IP: {}", e))?;
+
+        let mut result = DryRunResult {
+            diff: changes.diff,
+            ..Default::default()
+        };
+        if result.diff.is_empty() {
+            return Ok(result);
+        }
+
+        for config_path in &host.config_paths {
+            let preview = match self.web_server_config(host, config_path.clone()).await {
+                Ok(config) => {
+                    self.file_service(host, &config)
+                        .preview_file(&config, &host.hostname, &result.diff)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match preview {
+                Ok(Some(updated)) => match std::fs::read_to_string(config_path) {
+                    Ok(original) => result.changes.push(FileChange {
+                        path: config_path.clone(),
+                        original,
+                        updated,
+                    }),
+                    Err(e) => result.errors.push((config_path.clone(), e.to_string())),
+                },
+                Ok(None) => {}
+                Err(e) => result.errors.push((config_path.clone(), e.to_string())),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Backups of every config file of a host. Each backup is taken right before an edit, so
+    /// comparing it with the next backup (or the current file) shows the address change made.
+    pub async fn config_backups(
+        &self,
+        host: &HostConfig,
+    ) -> Result<Vec<ConfigBackups>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut result = Vec::new();
+        for config_path in &host.config_paths {
+            let config = self.web_server_config(host, config_path.clone()).await?;
+            let files = self
+                .file_service(host, &config)
+                .list_backups(&config)
+                .await?;
+
+            let mut versions = Vec::new();
+            for file in &files {
+                versions.push(addresses_in(&tokio::fs::read_to_string(&file.path).await?));
+            }
+            let current = tokio::fs::read_to_string(config_path)
+                .await
+                .unwrap_or_default();
+            versions.push(addresses_in(&current));
+
+            let backups = files
+                .into_iter()
+                .zip(versions.windows(2))
+                .map(|(file, pair)| BackupInfo {
+                    file,
+                    change: AddressDiff::between(&pair[0], &pair[1]),
+                })
+                .collect();
+            result.push(ConfigBackups {
+                config_path: config_path.clone(),
+                backups,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Delete the backups of a host's config files the retention policy no longer keeps; with
+    /// `dry_run` they are only listed. Returns the pruned backups per config file.
+    pub async fn prune_backups(
+        &self,
+        host: &HostConfig,
+        dry_run: bool,
+    ) -> Result<Vec<(std::path::PathBuf, Vec<BackupFile>)>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        let _locks = if dry_run {
+            Vec::new()
+        } else {
+            self.lock(&host.config_paths).await?
+        };
+        let mut result = Vec::new();
+        for config_path in &host.config_paths {
+            let config = self.web_server_config(host, config_path.clone()).await?;
+            let pruned = self
+                .file_service(host, &config)
+                .prune_backups(&config, dry_run)
+                .await?;
+            result.push((config_path.clone(), pruned));
+        }
+        Ok(result)
+    }
+
+    /// Recorded address changes, oldest first, of one host or all of them and optionally only
+    /// those made since the given time
+    pub async fn history(
+        &self,
+        hostname: Option<&str>,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Vec<IpChange>, Box<dyn std::error::Error + Send + Sync>> {
+        self.ip_repository.change_history(hostname, since).await
+    }
+
+    /// Stored entry of one host, or of every host by hostname
+    pub async fn entries(
+        &self,
+        hostname: Option<&str>,
+    ) -> Result<Vec<IpEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        match hostname {
+            Some(hostname) => Ok(self
+                .ip_repository
+                .get_ip_entry(hostname)
+                .await?
+                .into_iter()
+                .collect()),
+            None => self.ip_repository.list_all_entries().await,
+        }
+    }
+
+    /// Change the comment, owner or tags of a host's stored entry; its addresses and creation
+    /// time are kept
+    pub async fn update_entry_metadata(
         &self,
         hostname: &str,
-        config_path: std::path::PathBuf,
-        stored_ip: Option<std::net::IpAddr>,
-        current_ip: std::net::IpAddr,
-    ) -> Result<UpdateResult, Box<dyn std::error::Error + Send + Sync>> {
-        // Detect server type
-        let server_type = self
-            .config_discovery
-            .detect_server_type(&config_path)
+        metadata: &EntryMetadata,
+    ) -> Result<IpEntry, Box<dyn std::error::Error + Send + Sync>> {
+        let _locks = self.lock(&[]).await?;
+        let mut entry = self
+            .ip_repository
+            .get_ip_entry(hostname)
+            .await?
+            .ok_or_else(|| format!("No stored entry for {}; run an update first", hostname))?;
+        metadata.apply_to(&mut entry);
+        self.ip_repository.save_entry(&entry).await?;
+        Ok(entry)
+    }
+
+    /// Stored entries written in an older layout, which are upgraded as they are loaded, and
+    /// the legacy `<host>_ip.txt` files in `from`
+    pub async fn pending_migrations(
+        &self,
+        from: Option<&std::path::Path>,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        self.ip_repository
+            .pending_migrations(&self.legacy_files(from))
+            .await
+    }
+
+    /// Upgrade the stored entries written in an older layout and take over the legacy
+    /// `<host>_ip.txt` files in `from`; returns what was upgraded
+    pub async fn migrate_storage(
+        &self,
+        from: Option<&std::path::Path>,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let _locks = self.lock(&[]).await?;
+        self.ip_repository.migrate(&self.legacy_files(from)).await
+    }
+
+    /// Legacy files to migrate besides the storage's own, named after the configured hosts
+    fn legacy_files(&self, from: Option<&std::path::Path>) -> LegacyFiles {
+        LegacyFiles {
+            dirs: from.map(|dir| dir.to_path_buf()).into_iter().collect(),
+            hostnames: self
+                .config
+                .hosts
+                .iter()
+                .map(|host| host.hostname.clone())
+                .collect(),
+        }
+    }
+
+    /// Current content of the config file a backup belongs to, with the backup's content as
+    /// the update (what restoring it would change)
+    pub async fn backup_diff(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<FileChange, Box<dyn std::error::Error + Send + Sync>> {
+        let (_, config) = self.backup_owner(backup_path).await?;
+        Ok(FileChange {
+            original: tokio::fs::read_to_string(&config.path).await?,
+            updated: tokio::fs::read_to_string(backup_path).await?,
+            path: config.path,
+        })
+    }
+
+    /// Put a backup back in place of its config file, then test and reload the web server.
+    /// Returns the config file and the backup of the version it replaced.
+    pub async fn restore_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(std::path::PathBuf, std::path::PathBuf), Box<dyn std::error::Error + Send + Sync>>
+    {
+        let (host, config) = self.backup_owner(backup_path).await?;
+        let _locks = self.lock(std::slice::from_ref(&config.path)).await?;
+        let previous = self
+            .file_service(&host, &config)
+            .restore_from_backup(
+                &config,
+                backup_path,
+                self.config.no_reload || host.no_reload,
+            )
             .await?;
+        Ok((config.path, previous))
+    }
 
-        let config = WebServerConfig::new(config_path, server_type.clone());
+    /// Advisory locks of the storage directory and the given config files, taken in that order
+    /// so overlapping runs can't deadlock; released when dropped
+    async fn lock(
+        &self,
+        config_paths: &[std::path::PathBuf],
+    ) -> Result<Vec<FileLock>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut locks = vec![
+            FileLock::acquire(&self.config.storage_dir.join(STORAGE_LOCK), LOCK_TIMEOUT).await?,
+        ];
+        // Paths naming the same file through a symlink share one lock
+        let mut config_paths: Vec<std::path::PathBuf> = config_paths
+            .iter()
+            .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+            .collect();
+        config_paths.sort();
+        config_paths.dedup();
+        for config_path in config_paths {
+            locks.push(
+                FileLock::for_config(
+                    &self.config.storage_dir.join(CONFIG_LOCK_DIR),
+                    &config_path,
+                    LOCK_TIMEOUT,
+                )
+                .await?,
+            );
+        }
+        Ok(locks)
+    }
 
-        // Create appropriate web server handler
-        let web_server_handler =
-            ServiceFactory::create_web_server_handler(server_type, self.config.backup_dir.clone());
+    /// Host and config file a backup was made of
+    async fn backup_owner(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(HostConfig, WebServerConfig), Box<dyn std::error::Error + Send + Sync>> {
+        let wanted = backup_path
+            .canonicalize()
+            .map_err(|e| format!("Backup {} not found: {}", backup_path.display(), e))?;
+        for host in &self.config.hosts {
+            for config_path in &host.config_paths {
+                let config = self.web_server_config(host, config_path.clone()).await?;
+                let backups = self
+                    .file_service(host, &config)
+                    .list_backups(&config)
+                    .await?;
+                if backups
+                    .iter()
+                    .any(|backup| backup.path.canonicalize().ok().as_ref() == Some(&wanted))
+                {
+                    return Ok((host.clone(), config));
+                }
+            }
+        }
+        Err(format!(
+            "{} is not a backup of any configured config file",
+            backup_path.display()
+        )
+        .into())
+    }
 
-        // Create the service but use it directly without storing IP
-        let service = DdnsUpdateService::new(
+    /// Restore every edited file of a failed batch from its backup and reload the web servers
+    /// that already picked up the edits; each file gets an error naming the failure
+    async fn roll_back(
+        &self,
+        batches: &[(DdnsUpdateService, Vec<(WebServerConfig, UpdateResult)>)],
+        reloaded: usize,
+        reason: &str,
+        errors: &mut Vec<(std::path::PathBuf, String)>,
+    ) {
+        for (service, batch) in batches {
+            for (config, result) in batch {
+                let UpdateResult::Updated {
+                    hostname,
+                    backup_path,
+                    ..
+                } = result
+                else {
+                    continue;
+                };
+                let error_msg = match service
+                    .roll_back(config, hostname, backup_path, reason.to_string())
+                    .await
+                {
+                    Ok(_) => format!("Rolled back: {}", reason),
+                    Err(e) => format!("Rollback failed: {}", e),
+                };
+                errors.push((config.path.clone(), error_msg));
+            }
+        }
+
+        for (service, _) in batches.iter().take(reloaded) {
+            if let Err(e) = service.reload(false).await {
+                eprintln!("Warning: Failed to reload after rolling back: {}", e);
+            }
+        }
+    }
+
+    /// Edit a specific config file without storing IP, testing or reloading (used by
+    /// multi-config processing); returns the file's configuration along with the result
+    async fn update_ddns_file_only(
+        &self,
+        host: &HostConfig,
+        config_path: std::path::PathBuf,
+        diff: &AddressDiff,
+    ) -> Result<(WebServerConfig, UpdateResult), Box<dyn std::error::Error + Send + Sync>> {
+        let config = self.web_server_config(host, config_path).await?;
+        let service = self.file_service(host, &config);
+
+        // Process this specific file without storing IP
+        let result = service
+            .update_file_only(&config, &host.hostname, diff)
+            .await?;
+        Ok((config, result))
+    }
+
+    /// Configuration of one of a host's config files, detecting the server type unless the
+    /// host names it
+    async fn web_server_config(
+        &self,
+        host: &HostConfig,
+        config_path: std::path::PathBuf,
+    ) -> Result<WebServerConfig, Box<dyn std::error::Error + Send + Sync>> {
+        let server_type = match &host.server_type {
+            Some(server_type) => server_type.clone(),
+            None => {
+                self.config_discovery
+                    .detect_server_type(&config_path)
+                    .await?
+            }
+        };
+        Ok(WebServerConfig::new(config_path, server_type)
+            .with_prefix_rewrite(host.rewrite_prefix.unwrap_or(self.config.rewrite_prefix)))
+    }
+
+    /// Service working on one config file of a host, with the handler for its server type
+    fn file_service(&self, host: &HostConfig, config: &WebServerConfig) -> DdnsUpdateService {
+        DdnsUpdateService::new(
             self.ip_repository.clone(),
-            web_server_handler,
+            ServiceFactory::create_web_server_handler(
+                config.server_type.clone(),
+                self.backup_dir_for(host),
+            ),
             self.network_service.clone(),
             self.notification_service.clone(),
-        );
+        )
+        .with_backup_retention(self.config.backup_retention())
+    }
 
-        // Process this specific file without storing IP
-        service
-            .update_file_only(
-                &config,
-                hostname,
-                stored_ip,
-                current_ip,
-                self.config.no_reload,
-            )
-            .await
+    /// Backup directory of a host, falling back to the application's
+    fn backup_dir_for(&self, host: &HostConfig) -> Option<std::path::PathBuf> {
+        host.backup_dir
+            .clone()
+            .or_else(|| self.config.backup_dir.clone())
     }
 
     /// Discover configuration files using pattern
@@ -366,36 +936,51 @@ impl DdnsApplication {
     }
 
     /// Initialize DNS host file if it doesn't exist yet
-    /// This tries to resolve the hostname's IP and creates a JSON file with the real IP
+    /// This tries to resolve all of the hostname's IPs and creates a JSON file with them
     /// Falls back to placeholder IP if resolution fails (e.g., in CI/CD environments)
     pub async fn initialize_host_file(
         &self,
         hostname: &str,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        // Try to resolve the hostname to get the current IP
-        let current_ip = match self.network_service.resolve_hostname(hostname).await {
-            Ok(resolved_ips) => match resolved_ips.first() {
-                Some(ip) => *ip,
-                None => {
-                    eprintln!(
-                        "Warning: No IPs resolved for {}, using placeholder",
-                        hostname
-                    );
-                    "0.0.0.0".parse()?
-                }
-            },
+        // Try to resolve the hostname (or detect the public IP) to get the current IPs
+        let current_ips = match current_addresses(
+            self.network_service.as_ref(),
+            self.config.address_source,
+            hostname,
+        )
+        .await
+        {
+            Ok(resolved_ips) if !resolved_ips.is_empty() => normalize_addresses(&resolved_ips),
+            Ok(_) => {
+                eprintln!(
+                    "Warning: No IPs resolved for {}, using placeholder",
+                    hostname
+                );
+                vec!["0.0.0.0".parse()?]
+            }
             Err(e) => {
                 eprintln!(
                     "Warning: Failed to resolve {}: {}, using placeholder",
                     hostname, e
                 );
-                "0.0.0.0".parse()?
+                vec!["0.0.0.0".parse()?]
             }
         };
 
-        // Initialize the host file with the resolved IP (or placeholder if resolution failed)
+        // Initialize the host file with the resolved IPs (or placeholder if resolution failed);
+        // under the storage lock, so a running update can't be overwritten between the
+        // existence check and the write
+        let _locks = self.lock(&[]).await?;
         self.ip_repository
-            .initialize_host_file(hostname, current_ip)
+            .initialize_host_file(hostname, &current_ips)
             .await
     }
 }
+
+/// Comma separated list of addresses for messages
+fn format_ips(ips: &[std::net::IpAddr]) -> String {
+    ips.iter()
+        .map(|ip| ip.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
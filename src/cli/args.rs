@@ -1,6 +1,64 @@
-use crate::config::is_nginx_config_file;
-use clap::Parser;
-use std::path::PathBuf;
+use crate::application::{CheckSchedule, HostConfig, ServiceFactory};
+use crate::cli::config_file::{load_hosts, ConfigFile, DEFAULT_CONFIG_FILE};
+use crate::domain::entities::{
+    AddressSource, DnsTransport, GatewayProtocol, PublicIpBackend, PublicIpConfig, ResolverConfig,
+    StorageBackend,
+};
+use crate::domain::value_objects::IpSource;
+use crate::infrastructure::DEFAULT_DOH_URL;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where the addresses written to the allow lists come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AddressSourceKind {
+    /// Resolve --host
+    Dns,
+    /// This machine's public IP, detected with the --ip-source endpoints; --host names the entry
+    PublicIp,
+    /// The global addresses of --interface (Linux only); --host names the entry
+    Interface,
+    /// The router's external address, asked over NAT-PMP, PCP or UPnP; --host names the entry
+    Gateway,
+}
+
+/// Protocols for asking the router for its external address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GatewayProtocolKind {
+    /// NAT-PMP (RFC 6886)
+    NatPmp,
+    /// Port Control Protocol (RFC 6887), using a short-lived mapping
+    Pcp,
+    /// UPnP Internet Gateway Device
+    Upnp,
+}
+
+/// Where the stored addresses are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StorageKind {
+    /// One `<hostname>.json` file per host
+    Json,
+    /// `ddns-updater.db` in the storage directory, with the history of address changes;
+    /// imports the JSON files when first created
+    Sqlite,
+}
+
+/// How hostnames are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ResolverKind {
+    /// The operating system's resolver, including local caches
+    System,
+    /// Direct UDP queries to the nameservers (retried over TCP when truncated)
+    Udp,
+    /// Direct TCP queries to the nameservers
+    Tcp,
+    /// DNS-over-HTTPS
+    Doh,
+}
 
 #[derive(Parser, Debug)]
 #[command(name = "ddns_updater")]
@@ -8,41 +66,541 @@ use std::path::PathBuf;
 #[command(version = env!("CARGO_PKG_VERSION"))]
 pub struct Args {
     /// Host to check for IP changes
-    #[arg(long, default_value = "google.com")]
+    #[arg(long, default_value = "google.com", global = true)]
     pub host: String,
 
     /// Path to nginx configuration file or directory
-    #[arg(short = 'c', long = "config")]
+    #[arg(short = 'c', long = "config", global = true)]
     pub nginx_config: Option<PathBuf>,
 
-    /// Directory containing nginx configuration files
-    #[arg(short = 'd', long = "config-dir")]
+    /// Directory containing web server configuration files
+    #[arg(short = 'd', long = "config-dir", global = true)]
     pub config_dir: Option<PathBuf>,
 
+    /// TOML file describing many hosts (default: /etc/ddns-updater/config.toml if it exists
+    /// and none of --host, --config and --config-dir is given)
+    #[arg(long = "config-file", global = true, conflicts_with_all = ["host", "nginx_config", "config_dir"])]
+    pub config_file: Option<PathBuf>,
+
     /// Pattern to match nginx config files (used with --config-dir)
-    #[arg(short = 'p', long = "pattern", default_value = "*.conf")]
+    #[arg(short = 'p', long = "pattern", default_value = "*.conf", global = true)]
     pub pattern: String,
 
     /// Directory to store backup files
-    #[arg(short = 'b', long = "backup-dir")]
+    #[arg(short = 'b', long = "backup-dir", global = true)]
     pub backup_dir: Option<PathBuf>,
 
+    /// Storage of the last known addresses in the storage directory
+    #[arg(long = "storage", value_enum, default_value_t = StorageKind::Json, global = true)]
+    pub storage: StorageKind,
+
+    /// Keep at most this many backups of every config file
+    #[arg(long = "max-backups", default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..), global = true)]
+    pub max_backups: u16,
+
+    /// Delete backups older than this many days
+    #[arg(long = "backup-retention-days", default_value_t = 30, value_parser = clap::value_parser!(u16).range(1..), global = true)]
+    pub backup_retention_days: u16,
+
     /// Don't reload nginx after updating configuration
-    #[arg(long = "no-reload")]
+    #[arg(long = "no-reload", global = true)]
     pub no_reload: bool,
 
+    /// Print the changes as a unified diff without writing, backing up, reloading, storing or
+    /// notifying anything; exits with 2 when changes would be made
+    #[arg(long = "dry-run", global = true)]
+    pub dry_run: bool,
+
+    /// Rewrite allow list networks (e.g. a delegated IPv6 /56) that contain the old IP
+    /// to the new IP's network, keeping the configured prefix length
+    #[arg(long = "rewrite-prefix", global = true)]
+    pub rewrite_prefix: bool,
+
+    /// Source of the current addresses
+    #[arg(long = "address-source", value_enum, default_value_t = AddressSourceKind::Dns, global = true)]
+    pub address_source: AddressSourceKind,
+
+    /// Network interface holding the public IP, e.g. ppp0 (with --address-source interface)
+    #[arg(
+        long = "interface",
+        required_if_eq("address_source", "interface"),
+        global = true
+    )]
+    pub interface: Option<String>,
+
+    /// Router to ask with --address-source gateway (default: the default route's gateway)
+    #[arg(long = "gateway", global = true)]
+    pub gateway: Option<IpAddr>,
+
+    /// Gateway protocol to try, in order (repeatable; default: nat-pmp, upnp, pcp)
+    #[arg(long = "gateway-protocol", value_enum, global = true)]
+    pub gateway_protocols: Vec<GatewayProtocolKind>,
+
+    /// DNS resolver used to look up the host
+    #[arg(long = "resolver", value_enum, default_value_t = ResolverKind::System, global = true)]
+    pub resolver: ResolverKind,
+
+    /// Nameserver to query directly, as IP or IP:port (repeatable; default: /etc/resolv.conf)
+    #[arg(long = "nameserver", value_parser = parse_nameserver, global = true)]
+    pub nameservers: Vec<SocketAddr>,
+
+    /// Ask the host's authoritative nameservers, bypassing resolver caches
+    #[arg(long = "authoritative", global = true)]
+    pub authoritative: bool,
+
+    /// DNS-over-HTTPS endpoint used with --resolver doh
+    #[arg(long = "doh-url", default_value = DEFAULT_DOH_URL, global = true)]
+    pub doh_url: String,
+
+    /// Public IP source as [v4@|v6@]URL[#json.field] (repeatable; default: ipify, ipinfo, icanhazip)
+    #[arg(long = "ip-source", global = true)]
+    pub ip_sources: Vec<IpSource>,
+
+    /// Query all public IP sources and require this many to agree on the address
+    #[arg(long = "ip-quorum", global = true)]
+    pub ip_quorum: Option<usize>,
+
     /// Verbose output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Keep running and check the host on an interval instead of once
+    Daemon(DaemonArgs),
+    /// List, compare and restore the backups of the config files
+    Backups(BackupsArgs),
+    /// Show the recorded address changes (kept with --storage sqlite)
+    History(HistoryArgs),
+    /// Maintain the stored IP entries
+    Storage(StorageArgs),
+    /// Show or document the stored entries of hosts (comment, owner, tags)
+    Entry(EntryArgs),
+}
+
+/// How the change history is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistoryFormat {
+    /// One line per change with the edited files below it
+    Table,
+    /// JSON array of the changes
+    Json,
+}
+
+/// Options of the `history` subcommand
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryArgs {
+    /// Only changes within this long ago, in seconds or with an s, m, h or d suffix
+    #[arg(long = "since", value_parser = parse_age)]
+    pub since: Option<Duration>,
+
+    /// Output format
+    #[arg(long = "format", value_enum, default_value_t = HistoryFormat::Table)]
+    pub format: HistoryFormat,
+
+    /// Host whose changes are shown, if --host was given; all hosts otherwise
+    #[arg(skip)]
+    pub host: Option<String>,
+}
+
+/// Options of the `entry` subcommand
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct EntryArgs {
+    #[command(subcommand)]
+    pub action: EntryCommand,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum EntryCommand {
+    /// Show the stored entry of a host, or of every host
+    Show { hostname: Option<String> },
+    /// Set the comment, owner or tags of a host's stored entry; an empty value clears it
+    Set {
+        hostname: String,
+
+        /// Why the host is there, e.g. what the allow-list entry is for
+        #[arg(long = "comment")]
+        comment: Option<String>,
+
+        /// Who is responsible for the host
+        #[arg(long = "owner")]
+        owner: Option<String>,
+
+        /// Comma-separated tags, replacing the current ones
+        #[arg(long = "tags", value_delimiter = ',', num_args = 1)]
+        tags: Option<Vec<String>>,
+    },
+}
+
+/// Options of the `storage` subcommand
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct StorageArgs {
+    #[command(subcommand)]
+    pub action: StorageCommand,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum StorageCommand {
+    /// Upgrade the stored entries written in an older layout, including the `<host>_ip.txt`
+    /// files of earlier versions
+    Migrate {
+        /// Only list the entries that need upgrading and exit with 1 if there are any
+        #[arg(long = "check")]
+        check: bool,
+
+        /// Also take over the `<host>_ip.txt` files in this directory, e.g. the working
+        /// directory the earliest releases were run from
+        #[arg(long = "from", value_name = "DIR")]
+        from: Option<PathBuf>,
+    },
+}
+
+/// Options of the `backups` subcommand
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct BackupsArgs {
+    #[command(subcommand)]
+    pub action: BackupsCommand,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum BackupsCommand {
+    /// List the backups of every config file with the address change made after each
+    List,
+    /// Show what restoring a backup would change in its config file
+    Diff {
+        /// Backup file, as listed by `backups list`
+        backup: PathBuf,
+    },
+    /// Put a backup back in place of its config file, then test and reload the web server
+    Restore {
+        /// Backup file, as listed by `backups list`
+        backup: PathBuf,
+    },
+    /// Delete the backups beyond --max-backups or older than --backup-retention-days
+    /// (with --dry-run only list them)
+    Prune,
+}
+
+/// Options of the `daemon` subcommand
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct DaemonArgs {
+    /// Time between checks, in seconds or with an s, m or h suffix (at least 10s)
+    #[arg(long = "interval", default_value = "5m", value_parser = parse_interval)]
+    pub interval: Duration,
+
+    /// Random delay of up to this much added to every check, 0 for none
+    #[arg(long = "jitter", default_value = "30s", value_parser = parse_delay)]
+    pub jitter: Duration,
+
+    /// Longest wait between retries after repeated failed checks
+    #[arg(long = "max-backoff", default_value = "1h", value_parser = parse_duration)]
+    pub max_backoff: Duration,
+}
+
+impl DaemonArgs {
+    /// Check schedule configured by --interval, --jitter and --max-backoff
+    pub fn schedule(&self) -> CheckSchedule {
+        CheckSchedule::new(self.interval)
+            .with_jitter(self.jitter)
+            .with_max_backoff(self.max_backoff)
+    }
+}
+
+/// Shortest accepted time between checks, so a typo can't hammer the IP sources and DNS API
+pub(crate) const MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Parse a duration given in seconds, optionally with an `s`, `m` or `h` suffix. Zero is
+/// rejected.
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    match parse_delay(value)? {
+        Duration::ZERO => Err(format!(
+            "Invalid duration: {} (must be more than zero)",
+            value
+        )),
+        duration => Ok(duration),
+    }
+}
+
+/// Parse a time between checks: a duration as for `parse_duration`, of at least `MIN_INTERVAL`
+pub(crate) fn parse_interval(value: &str) -> Result<Duration, String> {
+    let interval = parse_duration(value)?;
+    if interval < MIN_INTERVAL {
+        return Err(format!(
+            "Invalid interval: {} (must be at least {}s)",
+            value,
+            MIN_INTERVAL.as_secs()
+        ));
+    }
+    Ok(interval)
+}
+
+/// Parse a duration like `parse_duration`, but allowing zero
+fn parse_delay(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => {
+            return Err(format!(
+                "Invalid duration: {} (use e.g. 90, 90s, 5m or 1h)",
+                value
+            ))
+        }
+    };
+    number
+        .parse::<u64>()
+        .map(|n| Duration::from_secs(n * multiplier))
+        .map_err(|_| format!("Invalid duration: {} (use e.g. 90, 90s, 5m or 1h)", value))
+}
+
+/// Parse an age like `7d`: a duration as for `parse_duration`, or whole days with a `d` suffix
+fn parse_age(value: &str) -> Result<Duration, String> {
+    match value.strip_suffix('d') {
+        Some(days) => days
+            .parse::<u64>()
+            .map(|n| Duration::from_secs(n * 86400))
+            .map_err(|_| format!("Invalid age: {} (use e.g. 90m, 12h or 7d)", value)),
+        None => parse_duration(value),
+    }
+}
+
+/// Parse a configuration file setting holding one of an option's values, e.g. `public-ip`
+fn parse_setting<T: ValueEnum>(key: &str, value: &str) -> Result<T, String> {
+    T::from_str(value, false).map_err(|_| {
+        let values: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|variant| variant.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        format!(
+            "Invalid {}: {} (use one of {})",
+            key,
+            value,
+            values.join(", ")
+        )
+    })
+}
+
+/// Parse `IP` or `IP:port` (IPv6 with a port as `[IP]:port`), defaulting to port 53
+fn parse_nameserver(value: &str) -> Result<SocketAddr, String> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    value
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, 53))
+        .map_err(|_| format!("Invalid nameserver address: {}", value))
 }
 
 impl Args {
     pub fn parse_args() -> Self {
-        Args::parse()
+        Self::from_matches(&Args::command().get_matches()).unwrap_or_else(|e| e.exit())
+    }
+
+    /// Parse the process's arguments again, re-reading --config-file, returning errors instead
+    /// of exiting. Used by the daemon to reload its configuration.
+    pub fn reparse_args() -> Result<Self, clap::Error> {
+        Self::from_matches(&Args::command().try_get_matches_from(std::env::args_os())?)
+    }
+
+    /// Arguments from parsed matches. `history` covers every host unless --host is given,
+    /// and the settings of --config-file fill in the options not given, both of which the
+    /// default values can't tell.
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Self::from_matches_with_default_config(matches, Path::new(DEFAULT_CONFIG_FILE))
+    }
+
+    /// `from_matches`, using `default_config` as --config-file when it exists and neither
+    /// --host nor any config file option is given
+    pub(crate) fn from_matches_with_default_config(
+        matches: &ArgMatches,
+        default_config: &Path,
+    ) -> Result<Self, clap::Error> {
+        let mut args = Args::from_arg_matches(matches)?;
+        let given = |id: &str| {
+            [
+                Some(matches),
+                matches.subcommand().map(|(_, matches)| matches),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|matches| matches.value_source(id) == Some(ValueSource::CommandLine))
+        };
+        if let Some(Command::History(history)) = &mut args.command {
+            history.host = given("host").then(|| args.host.clone());
+        }
+        let hosts_given = ["host", "nginx_config", "config_dir", "config_file"]
+            .into_iter()
+            .any(&given);
+        if !hosts_given && default_config.is_file() {
+            args.config_file = Some(default_config.to_path_buf());
+        }
+        if let Some(path) = args.config_file.clone() {
+            ConfigFile::load(&path)
+                .and_then(|file| {
+                    args.apply_config_file(&file, given)
+                        .map_err(|e| format!("{}: {}", path.display(), e))
+                })
+                .map_err(|e| clap::Error::raw(ErrorKind::InvalidValue, format!("{}\n", e)))?;
+        }
+        Ok(args)
+    }
+
+    /// Take the settings of a configuration file for the options `given` says weren't on the
+    /// command line
+    fn apply_config_file(
+        &mut self,
+        file: &ConfigFile,
+        given: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        if let Some(storage) = file.storage.as_deref().filter(|_| !given("storage")) {
+            self.storage = parse_setting("storage", storage)?;
+        }
+        if let Some(max_backups) = file.max_backups.filter(|_| !given("max_backups")) {
+            if max_backups == 0 {
+                return Err("Invalid max_backups: 0 (must be at least 1)".to_string());
+            }
+            self.max_backups = max_backups;
+        }
+        if let Some(days) = file
+            .backup_retention_days
+            .filter(|_| !given("backup_retention_days"))
+        {
+            if days == 0 {
+                return Err("Invalid backup_retention_days: 0 (must be at least 1)".to_string());
+            }
+            self.backup_retention_days = days;
+        }
+        if let Some(source) = file
+            .address_source
+            .as_deref()
+            .filter(|_| !given("address_source"))
+        {
+            self.address_source = parse_setting("address_source", source)?;
+        }
+        if self.interface.is_none() {
+            self.interface = file.interface.clone();
+        }
+        if self.address_source == AddressSourceKind::Interface && self.interface.is_none() {
+            return Err("address_source = \"interface\" needs an interface".to_string());
+        }
+        self.gateway = self.gateway.or(file.gateway);
+        if let Some(resolver) = file.resolver.as_deref().filter(|_| !given("resolver")) {
+            self.resolver = parse_setting("resolver", resolver)?;
+        }
+        if let Some(nameservers) = file.nameservers.as_ref().filter(|_| !given("nameservers")) {
+            self.nameservers = nameservers
+                .iter()
+                .map(|nameserver| parse_nameserver(nameserver))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(url) = file.doh_url.as_ref().filter(|_| !given("doh_url")) {
+            self.doh_url = url.clone();
+        }
+        if let Some(sources) = file.ip_sources.as_ref().filter(|_| !given("ip_sources")) {
+            self.ip_sources = sources
+                .iter()
+                .map(|source| source.parse::<IpSource>().map_err(|e| e.to_string()))
+                .collect::<Result<_, _>>()?;
+        }
+        self.ip_quorum = self.ip_quorum.or(file.ip_quorum);
+        Ok(())
+    }
+
+    /// Resolver configuration selected by the DNS options.
+    ///
+    /// Explicit nameservers or --authoritative imply direct queries over UDP.
+    pub fn resolver_config(&self) -> ResolverConfig {
+        let transport = match self.resolver {
+            ResolverKind::Doh => {
+                return ResolverConfig::DnsOverHttps {
+                    url: self.doh_url.clone(),
+                }
+            }
+            ResolverKind::System if self.nameservers.is_empty() && !self.authoritative => {
+                return ResolverConfig::System
+            }
+            ResolverKind::Tcp => DnsTransport::Tcp,
+            ResolverKind::System | ResolverKind::Udp => DnsTransport::Udp,
+        };
+        ResolverConfig::Nameservers {
+            servers: self.nameservers.clone(),
+            transport,
+            authoritative: self.authoritative,
+        }
+    }
+
+    /// Storage backend selected by --storage
+    pub fn storage_backend(&self) -> StorageBackend {
+        match self.storage {
+            StorageKind::Json => StorageBackend::Json,
+            StorageKind::Sqlite => StorageBackend::Sqlite,
+        }
+    }
+
+    /// Address source selected by --address-source
+    pub fn address_source(&self) -> AddressSource {
+        match self.address_source {
+            AddressSourceKind::Dns => AddressSource::Dns,
+            AddressSourceKind::PublicIp
+            | AddressSourceKind::Interface
+            | AddressSourceKind::Gateway => AddressSource::PublicIp,
+        }
+    }
+
+    /// How the public IP is detected, selected by --address-source
+    pub fn public_ip_backend(&self) -> PublicIpBackend {
+        match (self.address_source, &self.interface) {
+            (AddressSourceKind::Interface, Some(name)) => {
+                PublicIpBackend::Interface { name: name.clone() }
+            }
+            (AddressSourceKind::Gateway, _) => PublicIpBackend::Gateway {
+                gateway: self.gateway,
+                protocols: self
+                    .gateway_protocols
+                    .iter()
+                    .map(|protocol| match protocol {
+                        GatewayProtocolKind::NatPmp => GatewayProtocol::NatPmp,
+                        GatewayProtocolKind::Pcp => GatewayProtocol::Pcp,
+                        GatewayProtocolKind::Upnp => GatewayProtocol::Upnp,
+                    })
+                    .collect(),
+            },
+            _ => PublicIpBackend::Http,
+        }
+    }
+
+    /// Public IP detection configured by --ip-source and --ip-quorum
+    pub fn public_ip_config(&self) -> PublicIpConfig {
+        let mut config = PublicIpConfig::default();
+        if !self.ip_sources.is_empty() {
+            config.sources = self.ip_sources.clone();
+        }
+        config.quorum = self.ip_quorum;
+        config
+    }
+
+    /// Hosts to process: those of --config-file, or --host with its config files
+    pub async fn host_configs(&self) -> Result<Vec<HostConfig>, String> {
+        match &self.config_file {
+            Some(path) => load_hosts(path),
+            None => Ok(vec![HostConfig::new(
+                self.host.clone(),
+                self.get_nginx_config_paths().await?,
+            )]),
+        }
     }
 
     /// Get the nginx config path, either from explicit config or from config directory
-    pub fn get_nginx_config_path(&self) -> Result<PathBuf, String> {
+    pub async fn get_nginx_config_path(&self) -> Result<PathBuf, String> {
         if let Some(config) = &self.nginx_config {
             if config.exists() {
                 Ok(config.clone())
@@ -62,7 +620,7 @@ impl Args {
 
             // Find the first matching config file in the directory
             let pattern = &self.pattern;
-            match self.find_config_files(config_dir, pattern) {
+            match self.find_config_files(config_dir, pattern).await {
                 Ok(files) => {
                     if files.is_empty() {
                         Err(format!(
@@ -89,7 +647,7 @@ impl Args {
     }
 
     /// Get ALL nginx config paths (for processing multiple files in a directory)
-    pub fn get_nginx_config_paths(&self) -> Result<Vec<PathBuf>, String> {
+    pub async fn get_nginx_config_paths(&self) -> Result<Vec<PathBuf>, String> {
         if let Some(config) = &self.nginx_config {
             if config.exists() {
                 Ok(vec![config.clone()])
@@ -109,7 +667,7 @@ impl Args {
 
             // Find ALL matching config files in the directory
             let pattern = &self.pattern;
-            match self.find_config_files(config_dir, pattern) {
+            match self.find_config_files(config_dir, pattern).await {
                 Ok(files) => {
                     if files.is_empty() {
                         Err(format!(
@@ -135,10 +693,17 @@ impl Args {
         }
     }
 
-    /// Find config files matching the pattern in the given directory
-    fn find_config_files(&self, dir: &PathBuf, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    /// Find the config files of any supported web server matching the pattern in the given
+    /// directory
+    async fn find_config_files(
+        &self,
+        dir: &PathBuf,
+        pattern: &str,
+    ) -> Result<Vec<PathBuf>, String> {
         use std::fs;
 
+        let discovery = ServiceFactory::create_config_discovery_service();
+
         let entries = fs::read_dir(dir)
             .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
 
@@ -152,17 +717,10 @@ impl Args {
             if path.is_file() {
                 if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
                     if self.matches_pattern(filename, pattern) {
-                        // Validate that it's actually an nginx config file
-                        match is_nginx_config_file(&path.to_string_lossy()) {
-                            Ok(true) => {
-                                config_files.push(path);
-                            }
-                            Ok(false) => {
-                                skipped_files.push((path, "not an nginx config file".to_string()));
-                            }
-                            Err(e) => {
-                                skipped_files.push((path, format!("validation error: {}", e)));
-                            }
+                        // Keep the configs of every web server the updater can edit
+                        match discovery.detect_server_type(&path).await {
+                            Ok(_) => config_files.push(path),
+                            Err(e) => skipped_files.push((path, e.to_string())),
                         }
                     }
                 }
@@ -171,7 +729,7 @@ impl Args {
 
         // Show skipped files in verbose mode
         if self.verbose && !skipped_files.is_empty() {
-            println!("Skipped files that don't appear to be nginx configs:");
+            println!("Skipped files that can't be read as web server configs:");
             for (path, reason) in &skipped_files {
                 println!("  {} ({})", path.display(), reason);
             }
@@ -183,20 +741,7 @@ impl Args {
 
     /// Simple pattern matching (supports * wildcard)
     fn matches_pattern(&self, filename: &str, pattern: &str) -> bool {
-        if pattern == "*" {
-            return true;
-        }
-
-        if pattern.contains('*') {
-            let parts: Vec<&str> = pattern.split('*').collect();
-            if parts.len() == 2 {
-                let prefix = parts[0];
-                let suffix = parts[1];
-                return filename.starts_with(prefix) && filename.ends_with(suffix);
-            }
-        }
-
-        filename == pattern
+        matches_pattern(filename, pattern)
     }
 
     /// Get the backup directory path, creating it if necessary
@@ -231,3 +776,21 @@ impl Args {
         Ok(backup_path)
     }
 }
+
+/// Simple file name pattern matching (supports one * wildcard)
+pub(crate) fn matches_pattern(filename: &str, pattern: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+
+    if pattern.contains('*') {
+        let parts: Vec<&str> = pattern.split('*').collect();
+        if parts.len() == 2 {
+            let prefix = parts[0];
+            let suffix = parts[1];
+            return filename.starts_with(prefix) && filename.ends_with(suffix);
+        }
+    }
+
+    filename == pattern
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use crate::cli::Args;
+    use crate::cli::{AddressSourceKind, Args, ResolverKind, StorageKind};
+    use crate::infrastructure::DEFAULT_DOH_URL;
     use std::path::PathBuf;
 
     #[test]
@@ -10,10 +11,27 @@ mod tests {
             host: "example.com".to_string(),
             nginx_config: Some(PathBuf::from("/etc/nginx/nginx.conf")),
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: Some(PathBuf::from("/var/backups")),
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: true,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: true,
+            command: None,
         };
 
         // Verify all CLI arguments are accessible
@@ -32,10 +50,27 @@ mod tests {
             host: "test.local".to_string(),
             nginx_config: None,
             config_dir: Some(PathBuf::from("/etc/nginx/conf.d")),
+            config_file: None,
             pattern: "*.nginx".to_string(),
             backup_dir: None,
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         // Test directory-based configuration
@@ -55,10 +90,27 @@ mod tests {
             host: "flags.test".to_string(),
             nginx_config: Some(PathBuf::from("test.conf")),
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: Some(PathBuf::from("/custom/backup")),
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: true,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: true,
+            command: None,
         };
 
         assert!(args_verbose_no_reload.no_reload && args_verbose_no_reload.verbose);
@@ -67,10 +119,27 @@ mod tests {
             host: "default.test".to_string(),
             nginx_config: None,
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: None,
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         assert!(!args_defaults.no_reload && !args_defaults.verbose);
@@ -85,10 +154,27 @@ mod tests {
                 host: "pattern.test".to_string(),
                 nginx_config: None,
                 config_dir: Some(PathBuf::from("/etc/nginx")),
+                config_file: None,
                 pattern: pattern.to_string(),
                 backup_dir: None,
+                storage: StorageKind::Json,
+                max_backups: 10,
+                backup_retention_days: 30,
                 no_reload: false,
+                dry_run: false,
+                rewrite_prefix: false,
+                address_source: AddressSourceKind::Dns,
+                interface: None,
+                gateway: None,
+                gateway_protocols: vec![],
+                resolver: ResolverKind::System,
+                nameservers: vec![],
+                authoritative: false,
+                doh_url: DEFAULT_DOH_URL.to_string(),
+                ip_sources: vec![],
+                ip_quorum: None,
                 verbose: false,
+                command: None,
             };
 
             assert_eq!(args.pattern, pattern);
@@ -110,10 +196,27 @@ mod tests {
                 host: hostname.to_string(),
                 nginx_config: None,
                 config_dir: None,
+                config_file: None,
                 pattern: "*.conf".to_string(),
                 backup_dir: None,
+                storage: StorageKind::Json,
+                max_backups: 10,
+                backup_retention_days: 30,
                 no_reload: false,
+                dry_run: false,
+                rewrite_prefix: false,
+                address_source: AddressSourceKind::Dns,
+                interface: None,
+                gateway: None,
+                gateway_protocols: vec![],
+                resolver: ResolverKind::System,
+                nameservers: vec![],
+                authoritative: false,
+                doh_url: DEFAULT_DOH_URL.to_string(),
+                ip_sources: vec![],
+                ip_quorum: None,
                 verbose: false,
+                command: None,
             };
 
             assert_eq!(args.host, hostname);
@@ -127,10 +230,27 @@ mod tests {
             host: "backup.test".to_string(),
             nginx_config: Some(PathBuf::from("config.conf")),
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: Some(PathBuf::from("/custom/backup/location")),
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         assert!(args_with_backup.backup_dir.is_some());
@@ -144,10 +264,27 @@ mod tests {
             host: "no-backup.test".to_string(),
             nginx_config: Some(PathBuf::from("config.conf")),
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: None,
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         assert!(args_no_backup.backup_dir.is_none());
@@ -160,10 +297,27 @@ mod tests {
             host: "single.test".to_string(),
             nginx_config: Some(PathBuf::from("single.conf")),
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: None,
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         assert!(args_single_file.nginx_config.is_some());
@@ -174,13 +328,515 @@ mod tests {
             host: "directory.test".to_string(),
             nginx_config: None,
             config_dir: Some(PathBuf::from("/etc/nginx")),
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: None,
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         assert!(args_directory.nginx_config.is_none());
         assert!(args_directory.config_dir.is_some());
     }
+
+    #[test]
+    fn test_resolver_config_from_flags() {
+        use crate::domain::entities::{DnsTransport, ResolverConfig};
+        use clap::Parser;
+
+        let parse = |flags: &[&str]| {
+            Args::try_parse_from(std::iter::once("ddns_updater").chain(flags.iter().copied()))
+                .unwrap()
+                .resolver_config()
+        };
+
+        assert_eq!(parse(&[]), ResolverConfig::System);
+        assert_eq!(
+            parse(&[
+                "--nameserver",
+                "192.0.2.53",
+                "--nameserver",
+                "[2001:db8::53]:5353"
+            ]),
+            ResolverConfig::Nameservers {
+                servers: vec![
+                    "192.0.2.53:53".parse().unwrap(),
+                    "[2001:db8::53]:5353".parse().unwrap()
+                ],
+                transport: DnsTransport::Udp,
+                authoritative: false,
+            }
+        );
+        assert_eq!(
+            parse(&["--resolver", "tcp", "--authoritative"]),
+            ResolverConfig::Nameservers {
+                servers: vec![],
+                transport: DnsTransport::Tcp,
+                authoritative: true,
+            }
+        );
+        assert_eq!(
+            parse(&["--resolver", "doh"]),
+            ResolverConfig::DnsOverHttps {
+                url: DEFAULT_DOH_URL.to_string()
+            }
+        );
+        assert!(Args::try_parse_from(["ddns_updater", "--nameserver", "ns1"]).is_err());
+    }
+
+    #[test]
+    fn test_public_ip_config_from_flags() {
+        use crate::domain::entities::PublicIpConfig;
+        use clap::Parser;
+
+        let args = Args::try_parse_from(["ddns_updater"]).unwrap();
+        assert_eq!(args.public_ip_config(), PublicIpConfig::default());
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "--ip-source",
+            "https://ip.example.net/",
+            "--ip-source",
+            "v6@https://ipinfo.io/json#ip",
+            "--ip-quorum",
+            "2",
+        ])
+        .unwrap();
+        let config = args.public_ip_config();
+        assert_eq!(config.sources.len(), 2);
+        assert_eq!(config.sources[1].json_field.as_deref(), Some("ip"));
+        assert_eq!(config.quorum, Some(2));
+
+        assert!(Args::try_parse_from(["ddns_updater", "--ip-source", "ipinfo.io"]).is_err());
+    }
+
+    #[test]
+    fn test_address_source_from_flags() {
+        use crate::domain::entities::{AddressSource, GatewayProtocol, PublicIpBackend};
+        use clap::Parser;
+
+        let args = Args::try_parse_from(["ddns_updater"]).unwrap();
+        assert_eq!(args.address_source(), AddressSource::Dns);
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "--host",
+            "branch-17.example.com",
+            "--address-source",
+            "public-ip",
+        ])
+        .unwrap();
+        assert_eq!(args.address_source(), AddressSource::PublicIp);
+        assert_eq!(args.public_ip_backend(), PublicIpBackend::Http);
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "--address-source",
+            "interface",
+            "--interface",
+            "ppp0",
+        ])
+        .unwrap();
+        assert_eq!(args.address_source(), AddressSource::PublicIp);
+        assert_eq!(
+            args.public_ip_backend(),
+            PublicIpBackend::Interface {
+                name: "ppp0".to_string()
+            }
+        );
+
+        // The interface has to be named
+        assert!(Args::try_parse_from(["ddns_updater", "--address-source", "interface"]).is_err());
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "--address-source",
+            "gateway",
+            "--gateway",
+            "192.168.1.1",
+            "--gateway-protocol",
+            "upnp",
+            "--gateway-protocol",
+            "nat-pmp",
+        ])
+        .unwrap();
+        assert_eq!(args.address_source(), AddressSource::PublicIp);
+        assert_eq!(
+            args.public_ip_backend(),
+            PublicIpBackend::Gateway {
+                gateway: Some("192.168.1.1".parse().unwrap()),
+                protocols: vec![GatewayProtocol::Upnp, GatewayProtocol::NatPmp],
+            }
+        );
+    }
+
+    #[test]
+    fn test_daemon_subcommand() {
+        use crate::cli::Command;
+        use clap::Parser;
+        use std::time::Duration;
+
+        let args = Args::try_parse_from(["ddns_updater", "--host", "home.example.com"]).unwrap();
+        assert_eq!(args.command, None);
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "--host",
+            "home.example.com",
+            "daemon",
+            "--interval",
+            "90",
+            "--jitter",
+            "10s",
+            "--max-backoff",
+            "2h",
+        ])
+        .unwrap();
+        let Some(Command::Daemon(daemon)) = &args.command else {
+            panic!("expected the daemon subcommand");
+        };
+        assert_eq!(daemon.interval, Duration::from_secs(90));
+        assert_eq!(daemon.jitter, Duration::from_secs(10));
+        assert_eq!(daemon.max_backoff, Duration::from_secs(7200));
+
+        // Defaults: every 5 minutes with up to 30 seconds of jitter
+        let args = Args::try_parse_from(["ddns_updater", "daemon"]).unwrap();
+        let Some(Command::Daemon(daemon)) = &args.command else {
+            panic!("expected the daemon subcommand");
+        };
+        assert_eq!(daemon.interval, Duration::from_secs(300));
+        assert_eq!(daemon.jitter, Duration::from_secs(30));
+        assert_eq!(daemon.max_backoff, Duration::from_secs(3600));
+
+        // Options may also follow the subcommand
+        let args =
+            Args::try_parse_from(["ddns_updater", "daemon", "--host", "home.example.com"]).unwrap();
+        assert_eq!(args.host, "home.example.com");
+        assert!(matches!(args.command, Some(Command::Daemon(_))));
+
+        assert!(Args::try_parse_from(["ddns_updater", "daemon", "--interval", "5d"]).is_err());
+        assert!(Args::try_parse_from(["ddns_updater", "daemon", "--interval", "m"]).is_err());
+
+        // Zero or very short intervals would check in a tight loop
+        assert!(Args::try_parse_from(["ddns_updater", "daemon", "--interval", "0s"]).is_err());
+        assert!(Args::try_parse_from(["ddns_updater", "daemon", "--interval", "5"]).is_err());
+        assert!(Args::try_parse_from(["ddns_updater", "daemon", "--interval", "10s"]).is_ok());
+        assert!(Args::try_parse_from(["ddns_updater", "daemon", "--max-backoff", "0"]).is_err());
+
+        // Jitter may be turned off
+        let args = Args::try_parse_from(["ddns_updater", "daemon", "--jitter", "0"]).unwrap();
+        let Some(Command::Daemon(daemon)) = &args.command else {
+            panic!("expected the daemon subcommand");
+        };
+        assert_eq!(daemon.jitter, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_config_file_settings() {
+        use crate::cli::{AddressSourceKind, ResolverKind, StorageKind};
+        use clap::CommandFactory;
+        use std::net::SocketAddr;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+storage = "sqlite"
+max_backups = 3
+backup_retention_days = 7
+address_source = "interface"
+interface = "ppp0"
+resolver = "udp"
+nameservers = ["192.0.2.53", "[2001:db8::53]:5353"]
+ip_sources = ["v4@https://ip.example.com"]
+ip_quorum = 2
+
+[[host]]
+name = "home.example.com"
+configs = ["sites"]
+"#,
+        )
+        .unwrap();
+        let config = config_path.to_str().unwrap();
+        let parse = |argv: &[&str]| {
+            Args::from_matches(&Args::command().try_get_matches_from(argv).unwrap())
+        };
+
+        let args = parse(&["ddns_updater", "--config-file", config]).unwrap();
+        assert_eq!(args.storage, StorageKind::Sqlite);
+        assert_eq!(args.max_backups, 3);
+        assert_eq!(args.backup_retention_days, 7);
+        assert_eq!(args.address_source, AddressSourceKind::Interface);
+        assert_eq!(args.interface.as_deref(), Some("ppp0"));
+        assert_eq!(args.resolver, ResolverKind::Udp);
+        assert_eq!(
+            args.nameservers,
+            vec![
+                "192.0.2.53:53".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::53]:5353".parse().unwrap()
+            ]
+        );
+        assert_eq!(args.ip_sources.len(), 1);
+        assert_eq!(args.ip_quorum, Some(2));
+
+        // The command line takes precedence, also after a subcommand
+        let args = parse(&[
+            "ddns_updater",
+            "--config-file",
+            config,
+            "--storage",
+            "json",
+            "daemon",
+            "--address-source",
+            "dns",
+            "--resolver",
+            "system",
+            "--ip-quorum",
+            "1",
+        ])
+        .unwrap();
+        assert_eq!(args.storage, StorageKind::Json);
+        assert_eq!(args.address_source, AddressSourceKind::Dns);
+        assert_eq!(args.resolver, ResolverKind::System);
+        assert_eq!(args.ip_quorum, Some(1));
+        assert_eq!(args.max_backups, 3);
+
+        for invalid in [
+            "storage = \"csv\"",
+            "address_source = \"interface\"",
+            "max_backups = 0",
+            "nameservers = [\"ns.example.com\"]",
+        ] {
+            std::fs::write(&config_path, invalid).unwrap();
+            assert!(
+                parse(&["ddns_updater", "--config-file", config]).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_config_file() {
+        use clap::CommandFactory;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let default_config = temp_dir.path().join("config.toml");
+        let parse = |argv: &[&str]| {
+            Args::from_matches_with_default_config(
+                &Args::command().try_get_matches_from(argv).unwrap(),
+                &default_config,
+            )
+            .unwrap()
+        };
+
+        // Not there: the options alone
+        assert_eq!(parse(&["ddns_updater", "daemon"]).config_file, None);
+
+        std::fs::write(&default_config, "storage = \"sqlite\"\n").unwrap();
+        let args = parse(&["ddns_updater", "daemon"]);
+        assert_eq!(args.config_file.as_ref(), Some(&default_config));
+        assert_eq!(args.storage, crate::cli::StorageKind::Sqlite);
+
+        // Hosts or config files on the command line replace it
+        for argv in [
+            &["ddns_updater", "--host", "home.example.com"][..],
+            &["ddns_updater", "daemon", "--config", "site.conf"][..],
+            &["ddns_updater", "--config-dir", "/etc/nginx/sites-enabled"][..],
+        ] {
+            assert_eq!(parse(argv).config_file, None, "{:?}", argv);
+        }
+        let other = temp_dir.path().join("other.toml");
+        std::fs::write(&other, "").unwrap();
+        let args = parse(&["ddns_updater", "--config-file", other.to_str().unwrap()]);
+        assert_eq!(args.config_file, Some(other));
+        assert_eq!(args.storage, crate::cli::StorageKind::Json);
+    }
+
+    #[tokio::test]
+    async fn test_config_dir_keeps_every_web_server() {
+        use clap::Parser;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let files = [
+            (
+                "a-nginx.conf",
+                "server {\n    listen 80;\n    server_name a;\n}\n",
+            ),
+            (
+                "b-apache.conf",
+                "<VirtualHost *:80>\n    DocumentRoot /srv\n</VirtualHost>\n",
+            ),
+            (
+                "c-caddy.conf",
+                "example.com {\n    reverse_proxy localhost:8080\n}\n",
+            ),
+            (
+                "d-traefik.conf",
+                "[http.middlewares.allow.ipAllowList]\nsourceRange = [\"192.0.2.1\"]\n",
+            ),
+        ];
+        for (name, content) in files {
+            std::fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "--config-dir",
+            temp_dir.path().to_str().unwrap(),
+        ])
+        .unwrap();
+        let found = args.get_nginx_config_paths().await.unwrap();
+        let names: Vec<_> = found
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, files.map(|(name, _)| name));
+    }
+
+    #[test]
+    fn test_history_subcommand() {
+        use crate::cli::{Command, HistoryFormat};
+        use clap::CommandFactory;
+        use std::time::Duration;
+
+        let parse = |argv: &[&str]| {
+            Args::from_matches(&Args::command().try_get_matches_from(argv).unwrap()).unwrap()
+        };
+
+        // Every host by default, as a table
+        let args = parse(&["ddns_updater", "history"]);
+        let Some(Command::History(history)) = &args.command else {
+            panic!("expected the history subcommand");
+        };
+        assert_eq!(history.host, None);
+        assert_eq!(history.since, None);
+        assert_eq!(history.format, HistoryFormat::Table);
+
+        for argv in [
+            &["ddns_updater", "--host", "home.example.com", "history"][..],
+            &["ddns_updater", "history", "--host", "home.example.com"][..],
+        ] {
+            let args = parse(argv);
+            let Some(Command::History(history)) = &args.command else {
+                panic!("expected the history subcommand");
+            };
+            assert_eq!(history.host.as_deref(), Some("home.example.com"));
+        }
+
+        let args = parse(&[
+            "ddns_updater",
+            "history",
+            "--since",
+            "7d",
+            "--format",
+            "json",
+        ]);
+        let Some(Command::History(history)) = &args.command else {
+            panic!("expected the history subcommand");
+        };
+        assert_eq!(history.since, Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(history.format, HistoryFormat::Json);
+
+        let args = parse(&["ddns_updater", "history", "--since", "90m"]);
+        let Some(Command::History(history)) = &args.command else {
+            panic!("expected the history subcommand");
+        };
+        assert_eq!(history.since, Some(Duration::from_secs(5400)));
+
+        assert!(Args::command()
+            .try_get_matches_from(["ddns_updater", "history", "--since", "d"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_storage_migrate_subcommand() {
+        use crate::cli::{Command, StorageCommand};
+        use clap::Parser;
+
+        let args = Args::try_parse_from(["ddns_updater", "storage", "migrate"]).unwrap();
+        let Some(Command::Storage(storage)) = &args.command else {
+            panic!("expected the storage subcommand");
+        };
+        assert_eq!(
+            storage.action,
+            StorageCommand::Migrate {
+                check: false,
+                from: None
+            }
+        );
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "storage",
+            "migrate",
+            "--check",
+            "--from",
+            "/root",
+        ])
+        .unwrap();
+        let Some(Command::Storage(storage)) = &args.command else {
+            panic!("expected the storage subcommand");
+        };
+        assert_eq!(
+            storage.action,
+            StorageCommand::Migrate {
+                check: true,
+                from: Some(PathBuf::from("/root"))
+            }
+        );
+    }
+
+    #[test]
+    fn test_entry_set_subcommand() {
+        use crate::cli::{Command, EntryCommand};
+        use clap::Parser;
+
+        let args = Args::try_parse_from([
+            "ddns_updater",
+            "entry",
+            "set",
+            "home.example.com",
+            "--comment",
+            "Office VPN gateway",
+            "--tags",
+            "vpn,office",
+        ])
+        .unwrap();
+        let Some(Command::Entry(entry)) = &args.command else {
+            panic!("expected the entry subcommand");
+        };
+        assert_eq!(
+            entry.action,
+            EntryCommand::Set {
+                hostname: "home.example.com".to_string(),
+                comment: Some("Office VPN gateway".to_string()),
+                owner: None,
+                tags: Some(vec!["vpn".to_string(), "office".to_string()]),
+            }
+        );
+
+        let args = Args::try_parse_from(["ddns_updater", "entry", "show"]).unwrap();
+        let Some(Command::Entry(entry)) = &args.command else {
+            panic!("expected the entry subcommand");
+        };
+        assert_eq!(entry.action, EntryCommand::Show { hostname: None });
+    }
 }
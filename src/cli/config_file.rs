@@ -0,0 +1,222 @@
+use crate::application::HostConfig;
+use crate::cli::args::{matches_pattern, parse_interval};
+use crate::domain::entities::{NotificationChannel, WebServerType};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Usual location of the configuration file
+pub const DEFAULT_CONFIG_FILE: &str = "/etc/ddns-updater/config.toml";
+
+/// Configuration file describing many hosts, with defaults shared by all of them
+///
+/// The settings outside the `[[host]]` tables apply to every host; options given on the
+/// command line take precedence over them.
+///
+/// ```toml
+/// backup_dir = "/var/backups/nginx"
+/// storage = "sqlite"
+/// address_source = "public-ip"
+/// ip_quorum = 2
+///
+/// [[host]]
+/// name = "home.example.com"
+/// configs = ["/etc/nginx/sites-available/*.conf"]
+///
+/// [[host]]
+/// name = "office.example.com"
+/// configs = ["/etc/apache2/sites-available/office.conf"]
+/// server_type = "apache"
+/// reload = false
+/// notify = ["log", "https://hooks.example.com/ddns"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Backup directory of hosts that don't set their own
+    pub backup_dir: Option<PathBuf>,
+    /// Reload the web servers after updating them (default: true)
+    pub reload: Option<bool>,
+    /// Rewrite allow list networks that contain the old IP to the new IP's network
+    pub rewrite_prefix: Option<bool>,
+    /// Notification channels of hosts that don't set their own
+    pub notify: Option<Vec<String>>,
+    /// Time between checks in daemon mode, e.g. `5m`
+    pub interval: Option<String>,
+    /// `json` or `sqlite`, as --storage
+    pub storage: Option<String>,
+    /// Backups kept of every config file, as --max-backups
+    pub max_backups: Option<u16>,
+    /// Days backups are kept, as --backup-retention-days
+    pub backup_retention_days: Option<u16>,
+    /// `dns`, `public-ip`, `interface` or `gateway`, as --address-source
+    pub address_source: Option<String>,
+    /// Interface holding the public IP, as --interface
+    pub interface: Option<String>,
+    /// Router asked with the gateway address source, as --gateway
+    pub gateway: Option<IpAddr>,
+    /// `system`, `udp`, `tcp` or `doh`, as --resolver
+    pub resolver: Option<String>,
+    /// Nameservers as IP or IP:port, as --nameserver
+    pub nameservers: Option<Vec<String>>,
+    /// DNS-over-HTTPS endpoint, as --doh-url
+    pub doh_url: Option<String>,
+    /// Public IP sources as [v4@|v6@]URL[#json.field], as --ip-source
+    pub ip_sources: Option<Vec<String>>,
+    /// Public IP sources that must agree on the address, as --ip-quorum
+    pub ip_quorum: Option<usize>,
+    #[serde(default, rename = "host")]
+    pub hosts: Vec<HostSection>,
+}
+
+/// One `[[host]]` table of the configuration file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostSection {
+    pub name: String,
+    /// Config files, `dir/pattern` with one `*` wildcard, or directories (their `*.conf` files)
+    pub configs: Vec<String>,
+    /// Server type of all config files (nginx, apache, caddy, traefik); detected if unset
+    pub server_type: Option<String>,
+    pub backup_dir: Option<PathBuf>,
+    pub reload: Option<bool>,
+    pub rewrite_prefix: Option<bool>,
+    /// `console`, `log` or webhook URLs
+    pub notify: Option<Vec<String>>,
+    pub interval: Option<String>,
+}
+
+impl ConfigFile {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Invalid configuration file: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            format!(
+                "Failed to read configuration file {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+        Self::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The configured hosts with their config file targets expanded; relative paths are
+    /// taken from `base_dir`
+    pub fn hosts(&self, base_dir: &Path) -> Result<Vec<HostConfig>, String> {
+        if self.hosts.is_empty() {
+            return Err("No [[host]] entries in the configuration file".to_string());
+        }
+
+        let mut hosts: Vec<HostConfig> = Vec::new();
+        for section in &self.hosts {
+            if hosts.iter().any(|host| host.hostname == section.name) {
+                return Err(format!(
+                    "Host {} is configured more than once",
+                    section.name
+                ));
+            }
+            if section.configs.is_empty() {
+                return Err(format!("Host {} has no configs", section.name));
+            }
+
+            let mut config_paths = Vec::new();
+            for target in &section.configs {
+                for path in expand_target(base_dir, target)? {
+                    if !config_paths.contains(&path) {
+                        config_paths.push(path);
+                    }
+                }
+            }
+
+            let server_type = section
+                .server_type
+                .as_deref()
+                .map(|name| name.parse::<WebServerType>())
+                .transpose()
+                .map_err(|e| format!("Host {}: {}", section.name, e))?;
+            let notifications = section
+                .notify
+                .as_ref()
+                .or(self.notify.as_ref())
+                .map(|channels| {
+                    channels
+                        .iter()
+                        .map(|channel| channel.parse::<NotificationChannel>())
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()
+                .map_err(|e| format!("Host {}: {}", section.name, e))?
+                .unwrap_or_default();
+            let interval = section
+                .interval
+                .as_ref()
+                .or(self.interval.as_ref())
+                .map(|interval| parse_interval(interval))
+                .transpose()
+                .map_err(|e| format!("Host {}: {}", section.name, e))?;
+
+            hosts.push(
+                HostConfig::new(section.name.clone(), config_paths)
+                    .with_server_type(server_type)
+                    .with_backup_dir(
+                        section
+                            .backup_dir
+                            .as_ref()
+                            .or(self.backup_dir.as_ref())
+                            .map(|dir| base_dir.join(dir)),
+                    )
+                    .with_no_reload(!section.reload.or(self.reload).unwrap_or(true))
+                    .with_prefix_rewrite(section.rewrite_prefix.or(self.rewrite_prefix))
+                    .with_notifications(notifications)
+                    .with_interval(interval),
+            );
+        }
+        Ok(hosts)
+    }
+}
+
+/// Load the hosts of a configuration file, with paths relative to the file's directory
+pub fn load_hosts(path: &Path) -> Result<Vec<HostConfig>, String> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    ConfigFile::load(path)?.hosts(base_dir)
+}
+
+/// Config files named by one `configs` entry, sorted
+fn expand_target(base_dir: &Path, target: &str) -> Result<Vec<PathBuf>, String> {
+    let path = base_dir.join(target);
+    let (dir, pattern) = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains('*') => (
+            path.parent().unwrap_or(base_dir).to_path_buf(),
+            name.to_string(),
+        ),
+        _ if path.is_dir() => (path.clone(), "*.conf".to_string()),
+        _ if path.is_file() => return Ok(vec![path]),
+        _ => return Err(format!("Config file not found: {}", path.display())),
+    };
+
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        let matches = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| matches_pattern(name, &pattern));
+        if matches && path.is_file() {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        return Err(format!(
+            "No config files matching '{}' found in {}",
+            pattern,
+            dir.display()
+        ));
+    }
+    files.sort();
+    Ok(files)
+}
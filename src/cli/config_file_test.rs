@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use crate::cli::{load_hosts, ConfigFile};
+    use crate::domain::entities::{NotificationChannel, WebServerType};
+    use std::path::Path;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hosts_with_own_settings_and_shared_defaults() {
+        let temp_dir = tempdir().unwrap();
+        let sites = temp_dir.path().join("sites");
+        std::fs::create_dir(&sites).unwrap();
+        for name in ["b.conf", "a.conf", "notes.txt"] {
+            std::fs::write(sites.join(name), "server { listen 80; }").unwrap();
+        }
+        let office = temp_dir.path().join("office.conf");
+        std::fs::write(&office, "<VirtualHost *:80>\n</VirtualHost>\n").unwrap();
+
+        let config_path = temp_dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+backup_dir = "/var/backups/nginx"
+notify = ["log"]
+interval = "10m"
+
+[[host]]
+name = "home.example.com"
+configs = ["sites/*.conf"]
+
+[[host]]
+name = "office.example.com"
+configs = ["office.conf", "sites"]
+server_type = "apache"
+backup_dir = "backups"
+reload = false
+rewrite_prefix = true
+notify = ["console", "https://hooks.example.com/ddns"]
+interval = "90"
+"#,
+        )
+        .unwrap();
+
+        let hosts = load_hosts(&config_path).unwrap();
+        assert_eq!(hosts.len(), 2);
+
+        let home = &hosts[0];
+        assert_eq!(home.hostname, "home.example.com");
+        assert_eq!(
+            home.config_paths,
+            vec![sites.join("a.conf"), sites.join("b.conf")]
+        );
+        assert_eq!(home.server_type, None);
+        assert_eq!(
+            home.backup_dir.as_deref(),
+            Some(Path::new("/var/backups/nginx"))
+        );
+        assert!(!home.no_reload);
+        assert_eq!(home.rewrite_prefix, None);
+        assert_eq!(home.notifications, vec![NotificationChannel::Log]);
+        assert_eq!(home.interval, Some(Duration::from_secs(600)));
+
+        let office_host = &hosts[1];
+        assert_eq!(
+            office_host.config_paths,
+            vec![office.clone(), sites.join("a.conf"), sites.join("b.conf")]
+        );
+        assert_eq!(office_host.server_type, Some(WebServerType::Apache));
+        assert_eq!(
+            office_host.backup_dir.as_deref(),
+            Some(temp_dir.path().join("backups").as_path())
+        );
+        assert!(office_host.no_reload);
+        assert_eq!(office_host.rewrite_prefix, Some(true));
+        assert_eq!(
+            office_host.notifications,
+            vec![
+                NotificationChannel::Console,
+                NotificationChannel::Webhook {
+                    url: "https://hooks.example.com/ddns".to_string()
+                }
+            ]
+        );
+        assert_eq!(office_host.interval, Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_invalid_configuration_files() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("site.conf"), "server {}").unwrap();
+        let hosts = |content: &str| ConfigFile::parse(content)?.hosts(temp_dir.path());
+
+        assert!(hosts("").unwrap_err().contains("No [[host]] entries"));
+        assert!(
+            hosts("[[host]]\nname = \"a\"\nconfigs = [\"site.conf\"]\nreload = \"yes\"").is_err()
+        );
+        assert!(
+            hosts("[[host]]\nname = \"a\"\nconfigs = [\"site.conf\"]\ncolour = \"red\"").is_err()
+        );
+        assert!(hosts("[[host]]\nname = \"a\"\nconfigs = []")
+            .unwrap_err()
+            .contains("has no configs"));
+        assert!(
+            hosts("[[host]]\nname = \"a\"\nconfigs = [\"missing.conf\"]")
+                .unwrap_err()
+                .contains("not found")
+        );
+        assert!(hosts("[[host]]\nname = \"a\"\nconfigs = [\"*.nginx\"]")
+            .unwrap_err()
+            .contains("No config files matching"));
+        assert!(
+            hosts("[[host]]\nname = \"a\"\nconfigs = [\"site.conf\"]\nserver_type = \"iis\"")
+                .unwrap_err()
+                .contains("Unsupported web server type")
+        );
+        assert!(
+            hosts("[[host]]\nname = \"a\"\nconfigs = [\"site.conf\"]\nnotify = [\"pager\"]")
+                .unwrap_err()
+                .contains("Unknown notification channel")
+        );
+        assert!(hosts(
+            "[[host]]\nname = \"a\"\nconfigs = [\"site.conf\"]\n\n[[host]]\nname = \"a\"\nconfigs = [\"site.conf\"]"
+        )
+        .unwrap_err()
+        .contains("more than once"));
+    }
+}
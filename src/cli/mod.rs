@@ -1,6 +1,10 @@
 pub mod args;
+pub mod config_file;
 
 #[cfg(test)]
 mod args_test;
+#[cfg(test)]
+mod config_file_test;
 
 pub use args::*;
+pub use config_file::*;
@@ -2,6 +2,10 @@ use std::fs;
 use std::io::{Read, Write};
 use std::net::IpAddr;
 
+pub mod parser;
+
+pub use parser::{AccessDirective, AccessKind, BlockContext, NginxConfig, ParseError, Span};
+
 /// Open and read the contents of a file
 pub fn open_and_read_file(path: &str) -> Result<String, std::io::Error> {
     let mut file = std::fs::File::open(path)?;
@@ -56,51 +60,124 @@ pub fn is_nginx_config_content(content: &str) -> bool {
         "client_max_body_size",
     ];
 
-    // Count how many nginx directives we find
+    // Files the parser doesn't understand (e.g. with Lua or Perl blocks) are judged line by line
+    let config = match NginxConfig::parse(content) {
+        Ok(config) => config,
+        Err(_) => return looks_like_nginx_by_lines(content, &nginx_directives),
+    };
+
+    let directives = config.directives();
+    if directives.is_empty() {
+        return false;
+    }
+
+    // Nginx config files should have blocks and some nginx directives
+    // We require at least 20% of the directives to be well-known nginx directives
+    let has_blocks = directives.iter().any(|d| d.block.is_some());
+    let known_count = directives
+        .iter()
+        .filter(|d| nginx_directives.contains(&d.name.as_str()))
+        .count();
+    let directive_ratio = known_count as f64 / directives.len() as f64;
+    has_blocks && directive_ratio >= 0.2
+}
+
+/// Line-based check used when the content doesn't parse: braces, and at least 20% of the
+/// non-empty lines starting with a well-known nginx directive
+fn looks_like_nginx_by_lines(content: &str, nginx_directives: &[&str]) -> bool {
     let mut directive_count = 0;
     let mut total_lines = 0;
     let mut has_braces = false;
 
     for line in content.lines() {
         let trimmed = line.trim();
-
-        // Skip empty lines and comments
         if trimmed.is_empty() || trimmed.starts_with('#') {
             continue;
         }
-
         total_lines += 1;
+        has_braces |= trimmed.contains('{') || trimmed.contains('}');
 
-        // Check for nginx-style braces
-        if trimmed.contains('{') || trimmed.contains('}') {
-            has_braces = true;
+        let is_directive = nginx_directives.iter().any(|directive| {
+            trimmed
+                .strip_prefix(directive)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '{']))
+        });
+        if is_directive {
+            directive_count += 1;
         }
+    }
 
-        // Check for nginx directives
-        for directive in &nginx_directives {
-            if let Some(after_directive) = trimmed.strip_prefix(directive) {
-                // Make sure it's followed by a space, tab, or brace (not part of another word)
-                if after_directive.is_empty()
-                    || after_directive.starts_with(' ')
-                    || after_directive.starts_with('\t')
-                    || after_directive.starts_with('{')
-                {
-                    directive_count += 1;
-                    break;
-                }
-            }
+    total_lines > 0 && has_braces && directive_count as f64 / total_lines as f64 >= 0.2
+}
+
+/// `allow` / `deny` directives found line by line, for files the parser rejects. Only
+/// directives with a single unquoted argument on one line are found, and their block context
+/// is unknown.
+pub fn line_access_directives(content: &str) -> Vec<AccessDirective> {
+    let mut result = Vec::new();
+    let mut offset = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+
+        let text = line.trim_end_matches(['\n', '\r']);
+        let code_start = text.len() - text.trim_start().len();
+        let code = &text[code_start..];
+        let (kind, rest) = if let Some(rest) = code.strip_prefix("allow") {
+            (AccessKind::Allow, rest)
+        } else if let Some(rest) = code.strip_prefix("deny") {
+            (AccessKind::Deny, rest)
+        } else {
+            continue;
+        };
+        if !rest.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some(semicolon) = rest.find(';') else {
+            continue;
+        };
+        let value = rest[..semicolon].trim();
+        if value.is_empty() || value.contains(char::is_whitespace) || value.contains(['"', '\'']) {
+            continue;
         }
-    }
 
-    // If we have no meaningful content, it's not a config file
-    if total_lines == 0 {
-        return false;
+        let rest_start = line_start + code_start + (code.len() - rest.len());
+        let value_start = rest_start + rest.find(value).unwrap_or(0);
+        let end = rest_start + semicolon + 1;
+        let after = &text[end - line_start..];
+        let (comment, comment_span) = match after.trim_start().strip_prefix('#') {
+            Some(comment) => {
+                let start = end + (after.len() - after.trim_start().len());
+                (
+                    Some(comment.trim().to_string()),
+                    Some(Span {
+                        start,
+                        end: line_start + text.len(),
+                    }),
+                )
+            }
+            None => (None, None),
+        };
+
+        result.push(AccessDirective {
+            kind,
+            value: value.to_string(),
+            value_span: Span {
+                start: value_start,
+                end: value_start + value.len(),
+            },
+            span: Span {
+                start: line_start + code_start,
+                end,
+            },
+            context: Vec::new(),
+            comment,
+            comment_span,
+            line: index + 1,
+        });
     }
-
-    // Nginx config files should have braces and some nginx directives
-    // We require at least 20% of non-empty lines to contain nginx directives
-    let directive_ratio = directive_count as f64 / total_lines as f64;
-    has_braces && directive_ratio >= 0.2
+    result
 }
 
 /// Validate nginx config file and provide detailed feedback
@@ -0,0 +1,485 @@
+//! Tokenizer and syntax tree for nginx configuration files.
+//!
+//! The tree keeps byte spans into the original source instead of normalized text, so
+//! [`NginxConfig::source`] always returns the input byte-for-byte and edits made through
+//! [`NginxConfig::apply_edits`] touch nothing but the replaced spans.
+
+use std::fmt;
+use std::net::IpAddr;
+
+/// Byte range within the configuration source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A directive argument; `value` has quotes and escapes removed, `span` covers the raw token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub value: String,
+    pub span: Span,
+}
+
+/// A simple (`name args;`) or block (`name args { ... }`) directive
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub name: String,
+    pub args: Vec<Argument>,
+    /// From the start of the name to the terminating `;` or closing `}`
+    pub span: Span,
+    /// 1-based line number of the directive name
+    pub line: usize,
+    pub block: Option<Vec<Node>>,
+}
+
+/// A `#` comment; `text` excludes the leading `#`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Directive(Directive),
+    Comment(Comment),
+}
+
+/// Kind of an access control directive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Allow,
+    Deny,
+}
+
+/// An enclosing block of a directive, e.g. `server` or `location` with its arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockContext {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// An `allow` / `deny` directive together with where it appears
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessDirective {
+    pub kind: AccessKind,
+    /// The single argument, e.g. `203.0.113.7`, `10.0.0.0/8` or `all`
+    pub value: String,
+    pub value_span: Span,
+    /// The whole directive from its name up to and including the `;`
+    pub span: Span,
+    /// Enclosing blocks from the outermost inwards
+    pub context: Vec<BlockContext>,
+    /// Comment following the directive on the same line
+    pub comment: Option<String>,
+    pub comment_span: Option<Span>,
+    pub line: usize,
+}
+
+impl AccessDirective {
+    /// The address if the value is a single IP (not a CIDR range or `all`)
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.value.parse().ok()
+    }
+
+    /// Innermost enclosing block with the given name
+    pub fn enclosing(&self, name: &str) -> Option<&BlockContext> {
+        self.context.iter().rev().find(|block| block.name == name)
+    }
+}
+
+/// Syntax errors found while parsing
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    UnterminatedQuote { line: usize },
+    UnexpectedCloseBrace { line: usize },
+    UnclosedBlock { line: usize },
+    MissingSemicolon { line: usize },
+    MissingDirectiveName { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnterminatedQuote { line } => {
+                write!(f, "Unterminated quoted string starting on line {}", line)
+            }
+            ParseError::UnexpectedCloseBrace { line } => {
+                write!(f, "Unexpected '}}' on line {}", line)
+            }
+            ParseError::UnclosedBlock { line } => {
+                write!(f, "Block opened on line {} is never closed", line)
+            }
+            ParseError::MissingSemicolon { line } => {
+                write!(f, "Directive on line {} is not terminated by ';'", line)
+            }
+            ParseError::MissingDirectiveName { line } => {
+                write!(f, "Block or ';' without a directive name on line {}", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A parsed nginx configuration file
+#[derive(Debug, Clone)]
+pub struct NginxConfig {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl NginxConfig {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let nodes = parser.parse_block(None)?;
+        Ok(Self {
+            source: source.to_string(),
+            nodes,
+        })
+    }
+
+    /// The original configuration text, unchanged
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// All directives in document order, including those nested in blocks
+    pub fn directives(&self) -> Vec<&Directive> {
+        fn collect<'a>(nodes: &'a [Node], out: &mut Vec<&'a Directive>) {
+            for node in nodes {
+                if let Node::Directive(directive) = node {
+                    out.push(directive);
+                    if let Some(block) = &directive.block {
+                        collect(block, out);
+                    }
+                }
+            }
+        }
+
+        let mut directives = Vec::new();
+        collect(&self.nodes, &mut directives);
+        directives
+    }
+
+    /// All `allow` / `deny` directives with a single argument, in document order
+    pub fn access_directives(&self) -> Vec<AccessDirective> {
+        let mut result = Vec::new();
+        let mut context = Vec::new();
+        self.collect_access(&self.nodes, &mut context, &mut result);
+        result
+    }
+
+    /// Replace the given spans and return the new source. Spans must not overlap.
+    pub fn apply_edits(&self, edits: &[(Span, String)]) -> String {
+        let mut edits: Vec<&(Span, String)> = edits.iter().collect();
+        edits.sort_by_key(|(span, _)| span.start);
+
+        let mut output = String::with_capacity(self.source.len());
+        let mut position = 0;
+        for (span, replacement) in edits {
+            output.push_str(&self.source[position..span.start]);
+            output.push_str(replacement);
+            position = span.end;
+        }
+        output.push_str(&self.source[position..]);
+        output
+    }
+
+    fn collect_access(
+        &self,
+        nodes: &[Node],
+        context: &mut Vec<BlockContext>,
+        result: &mut Vec<AccessDirective>,
+    ) {
+        for (index, node) in nodes.iter().enumerate() {
+            let Node::Directive(directive) = node else {
+                continue;
+            };
+
+            if let Some(block) = &directive.block {
+                context.push(BlockContext {
+                    name: directive.name.clone(),
+                    args: directive.args.iter().map(|a| a.value.clone()).collect(),
+                });
+                self.collect_access(block, context, result);
+                context.pop();
+                continue;
+            }
+
+            let kind = match directive.name.as_str() {
+                "allow" => AccessKind::Allow,
+                "deny" => AccessKind::Deny,
+                _ => continue,
+            };
+            let [argument] = directive.args.as_slice() else {
+                continue;
+            };
+
+            // A comment counts as trailing if nothing but blanks separate it from the `;`
+            let trailing_comment = match nodes.get(index + 1) {
+                Some(Node::Comment(comment))
+                    if !self.source[directive.span.end..comment.span.start].contains('\n') =>
+                {
+                    Some(comment)
+                }
+                _ => None,
+            };
+
+            result.push(AccessDirective {
+                kind,
+                value: argument.value.clone(),
+                value_span: argument.span,
+                span: directive.span,
+                context: context.clone(),
+                comment: trailing_comment.map(|comment| comment.text.trim().to_string()),
+                comment_span: trailing_comment.map(|comment| comment.span),
+                line: directive.line,
+            });
+        }
+    }
+}
+
+impl fmt::Display for NginxConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Semicolon,
+    OpenBrace,
+    CloseBrace,
+    Comment(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+    line: usize,
+}
+
+/// Split the source into tokens following nginx's own lexer rules: `;`, `{` and `}` always end a
+/// word, `#` only starts a comment at the beginning of a token, and `${var}` stays in one word.
+fn tokenize(source: &str) -> Result<Vec<Token>, ParseError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    let mut line = 1;
+
+    while index < bytes.len() {
+        let start = index;
+        let start_line = line;
+        let kind = match bytes[index] {
+            b'\n' => {
+                line += 1;
+                index += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' => {
+                index += 1;
+                continue;
+            }
+            b';' => {
+                index += 1;
+                TokenKind::Semicolon
+            }
+            b'{' => {
+                index += 1;
+                TokenKind::OpenBrace
+            }
+            b'}' => {
+                index += 1;
+                TokenKind::CloseBrace
+            }
+            b'#' => {
+                while index < bytes.len() && bytes[index] != b'\n' {
+                    index += 1;
+                }
+                TokenKind::Comment(source[start + 1..index].to_string())
+            }
+            quote @ (b'"' | b'\'') => {
+                let mut value = String::new();
+                index += 1;
+                loop {
+                    match bytes.get(index) {
+                        None => return Err(ParseError::UnterminatedQuote { line: start_line }),
+                        Some(&b) if b == quote => {
+                            index += 1;
+                            break;
+                        }
+                        Some(b'\\') if index + 1 < bytes.len() => {
+                            let escaped = source[index + 1..].chars().next().unwrap_or('\\');
+                            if escaped == '\n' {
+                                line += 1;
+                            }
+                            if !matches!(escaped, '"' | '\'' | '\\') {
+                                value.push('\\');
+                            }
+                            value.push(escaped);
+                            index += 1 + escaped.len_utf8();
+                        }
+                        Some(_) => {
+                            let c = source[index..].chars().next().unwrap_or_default();
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                            index += c.len_utf8();
+                        }
+                    }
+                }
+                TokenKind::Word(value)
+            }
+            _ => {
+                while index < bytes.len() {
+                    match bytes[index] {
+                        b' ' | b'\t' | b'\r' | b'\n' | b';' | b'}' => break,
+                        b'{' if index > start && bytes[index - 1] == b'$' => {
+                            // ${variable} syntax
+                            while index < bytes.len() && bytes[index] != b'}' {
+                                index += 1;
+                            }
+                            index = (index + 1).min(bytes.len());
+                        }
+                        b'{' => break,
+                        b'\\' if index + 1 < bytes.len() => {
+                            index +=
+                                1 + source[index + 1..].chars().next().map_or(1, char::len_utf8);
+                        }
+                        _ => index += 1,
+                    }
+                }
+                TokenKind::Word(source[start..index].to_string())
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            span: Span { start, end: index },
+            line: start_line,
+        });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    /// Parse nodes until the matching `}` (for blocks) or the end of input (top level)
+    fn parse_block(&mut self, opened_on: Option<usize>) -> Result<Vec<Node>, ParseError> {
+        let mut nodes = Vec::new();
+
+        while let Some(token) = self.tokens.get(self.position).cloned() {
+            self.position += 1;
+            match &token.kind {
+                TokenKind::Comment(text) => nodes.push(Node::Comment(Comment {
+                    text: text.clone(),
+                    span: token.span,
+                    line: token.line,
+                })),
+                TokenKind::CloseBrace => {
+                    return match opened_on {
+                        Some(_) => Ok(nodes),
+                        None => Err(ParseError::UnexpectedCloseBrace { line: token.line }),
+                    };
+                }
+                TokenKind::Semicolon | TokenKind::OpenBrace => {
+                    return Err(ParseError::MissingDirectiveName { line: token.line });
+                }
+                TokenKind::Word(name) => {
+                    let directive = self.parse_directive(name.clone(), &token, &mut nodes)?;
+                    nodes.push(Node::Directive(directive));
+                }
+            }
+        }
+
+        match opened_on {
+            Some(line) => Err(ParseError::UnclosedBlock { line }),
+            None => Ok(nodes),
+        }
+    }
+
+    /// Parse the arguments and terminator of a directive whose name was just consumed.
+    /// Comments between the arguments are hoisted into `nodes` in front of the directive.
+    fn parse_directive(
+        &mut self,
+        name: String,
+        name_token: &Token,
+        nodes: &mut Vec<Node>,
+    ) -> Result<Directive, ParseError> {
+        let mut args = Vec::new();
+
+        while let Some(token) = self.tokens.get(self.position).cloned() {
+            self.position += 1;
+            match token.kind {
+                TokenKind::Word(value) => args.push(Argument {
+                    value,
+                    span: token.span,
+                }),
+                TokenKind::Comment(text) => nodes.push(Node::Comment(Comment {
+                    text,
+                    span: token.span,
+                    line: token.line,
+                })),
+                TokenKind::Semicolon => {
+                    return Ok(Directive {
+                        name,
+                        args,
+                        span: Span {
+                            start: name_token.span.start,
+                            end: token.span.end,
+                        },
+                        line: name_token.line,
+                        block: None,
+                    });
+                }
+                TokenKind::OpenBrace => {
+                    let block = self.parse_block(Some(token.line))?;
+                    let end = self.tokens[self.position - 1].span.end;
+                    return Ok(Directive {
+                        name,
+                        args,
+                        span: Span {
+                            start: name_token.span.start,
+                            end,
+                        },
+                        line: name_token.line,
+                        block: Some(block),
+                    });
+                }
+                TokenKind::CloseBrace => {
+                    return Err(ParseError::MissingSemicolon {
+                        line: name_token.line,
+                    });
+                }
+            }
+        }
+
+        Err(ParseError::MissingSemicolon {
+            line: name_token.line,
+        })
+    }
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "parser_tests.rs"]
+mod tests;
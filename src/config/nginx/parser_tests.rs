@@ -0,0 +1,118 @@
+use super::*;
+
+const CONFIG: &str = "# managed by ddns_updater
+server {
+    listen 80;
+    server_name example.com;
+
+    location /admin {
+        allow 203.0.113.40; # office
+        allow 203.0.113.4; allow 10.0.0.0/8;   # DDNS: home.example.com
+        # allow 198.51.100.1;
+        deny all;
+    }
+
+    location ~ \"^/api/v[0-9]{1,2}\" {
+        set $target \"${scheme}://backend\";
+        proxy_pass $target;
+    }
+}
+";
+
+#[test]
+fn test_round_trips_source_unchanged() {
+    let config = NginxConfig::parse(CONFIG).unwrap();
+    assert_eq!(config.source(), CONFIG);
+    assert_eq!(config.to_string(), CONFIG);
+    assert_eq!(config.apply_edits(&[]), CONFIG);
+}
+
+#[test]
+fn test_access_directives_with_context() {
+    let config = NginxConfig::parse(CONFIG).unwrap();
+    let access = config.access_directives();
+
+    let values: Vec<(&str, AccessKind)> =
+        access.iter().map(|d| (d.value.as_str(), d.kind)).collect();
+    assert_eq!(
+        values,
+        vec![
+            ("203.0.113.40", AccessKind::Allow),
+            ("203.0.113.4", AccessKind::Allow),
+            ("10.0.0.0/8", AccessKind::Allow),
+            ("all", AccessKind::Deny),
+        ]
+    );
+
+    // The commented-out directive is not an entry and 1.2.3.40 is not 1.2.3.4
+    let wanted: IpAddr = "203.0.113.4".parse().unwrap();
+    let matches: Vec<usize> = access
+        .iter()
+        .filter(|d| d.ip() == Some(wanted))
+        .map(|d| d.line)
+        .collect();
+    assert_eq!(matches, vec![8]);
+
+    let location = access[0].enclosing("location").unwrap();
+    assert_eq!(location.args, vec!["/admin".to_string()]);
+    assert!(access[0].enclosing("server").is_some());
+    assert_eq!(access[0].comment.as_deref(), Some("office"));
+    // Only the last directive of a line owns the trailing comment
+    assert_eq!(access[1].comment, None);
+    assert_eq!(access[2].comment.as_deref(), Some("DDNS: home.example.com"));
+}
+
+#[test]
+fn test_apply_edits_only_touches_spans() {
+    let config = NginxConfig::parse(CONFIG).unwrap();
+    let target = config
+        .access_directives()
+        .into_iter()
+        .find(|d| d.value == "203.0.113.4")
+        .unwrap();
+
+    let updated = config.apply_edits(&[(target.value_span, "203.0.113.99".to_string())]);
+    assert_eq!(
+        updated,
+        CONFIG.replace("allow 203.0.113.4; allow", "allow 203.0.113.99; allow")
+    );
+}
+
+#[test]
+fn test_quoted_arguments_and_variables() {
+    let config = NginxConfig::parse(CONFIG).unwrap();
+    let directives = config.directives();
+
+    let location = directives
+        .iter()
+        .find(|d| d.name == "location" && d.args.len() == 2)
+        .unwrap();
+    assert_eq!(location.args[1].value, "^/api/v[0-9]{1,2}");
+
+    let set = directives.iter().find(|d| d.name == "set").unwrap();
+    assert_eq!(set.args[1].value, "${scheme}://backend");
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        NginxConfig::parse("server {\n    listen 80;\n").unwrap_err(),
+        ParseError::UnclosedBlock { line: 1 }
+    );
+    assert_eq!(
+        NginxConfig::parse("listen 80;\n}\n").unwrap_err(),
+        ParseError::UnexpectedCloseBrace { line: 2 }
+    );
+    assert_eq!(
+        NginxConfig::parse("server example.com\nlisten 80\n").unwrap_err(),
+        ParseError::MissingSemicolon { line: 1 }
+    );
+    assert_eq!(
+        NginxConfig::parse("{\n  \"name\": \"json\"\n}\n").unwrap_err(),
+        ParseError::MissingDirectiveName { line: 1 }
+    );
+    assert_eq!(
+        NginxConfig::parse("return 200 \"unterminated;\n").unwrap_err(),
+        ParseError::UnterminatedQuote { line: 1 }
+    );
+}
@@ -1,14 +1,66 @@
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Address family of an IP address (A vs. AAAA records)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    pub fn of(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => IpFamily::V4,
+            IpAddr::V6(_) => IpFamily::V6,
+        }
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpFamily::V4 => write!(f, "IPv4"),
+            IpFamily::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// Layout version of stored `IpEntry` records written by this build
+pub const IP_ENTRY_VERSION: u32 = 2;
 
 /// Domain entity representing an IP address entry in a configuration
+///
+/// IPv4 and IPv6 addresses are tracked separately so dual-stack hosts don't flip between
+/// families, and each family holds the full resolved set for round-robin hostnames. `ip` is the
+/// primary address (the first IPv4 address if known) and is all that older entries contain.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IpEntry {
+    /// Layout version the entry was stored with; entries from before versioning are 1
+    #[serde(default = "unversioned")]
+    pub version: u32,
     pub ip: IpAddr,
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub ipv4: Vec<Ipv4Addr>,
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub ipv6: Vec<Ipv6Addr>,
     pub hostname: String,
     pub comment: Option<String>,
+    /// Who is responsible for the host, e.g. to ask before removing it from an allow-list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -16,24 +68,147 @@ pub struct IpEntry {
 impl IpEntry {
     pub fn new(ip: IpAddr, hostname: String, comment: Option<String>) -> Self {
         let now = chrono::Utc::now();
-        Self {
+        let mut entry = Self {
+            version: IP_ENTRY_VERSION,
             ip,
+            ipv4: Vec::new(),
+            ipv6: Vec::new(),
             hostname,
             comment,
+            owner: None,
+            tags: Vec::new(),
             created_at: now,
             updated_at: now,
-        }
+        };
+        entry.set_addresses(IpFamily::of(ip), &[ip]);
+        entry
     }
 
+    /// Replace the stored addresses of `new_ip`'s family with just `new_ip`, keeping the other
+    /// family
     pub fn update_ip(&mut self, new_ip: IpAddr) {
-        self.ip = new_ip;
+        self.set_addresses(IpFamily::of(new_ip), &[new_ip]);
         self.updated_at = chrono::Utc::now();
     }
 
+    /// Replace the stored address set of every family present in `ips`
+    pub fn update_ips(&mut self, ips: &[IpAddr]) {
+        for family in [IpFamily::V4, IpFamily::V6] {
+            let addresses: Vec<IpAddr> = ips
+                .iter()
+                .filter(|ip| IpFamily::of(**ip) == family)
+                .copied()
+                .collect();
+            if !addresses.is_empty() {
+                self.set_addresses(family, &addresses);
+            }
+        }
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// First stored address of the given family
+    pub fn address(&self, family: IpFamily) -> Option<IpAddr> {
+        self.addresses_of(family).first().copied()
+    }
+
+    /// All stored addresses of the given family
+    pub fn addresses_of(&self, family: IpFamily) -> Vec<IpAddr> {
+        // Entries written before per-family tracking only have `ip`
+        if self.ipv4.is_empty() && self.ipv6.is_empty() {
+            return if IpFamily::of(self.ip) == family {
+                vec![self.ip]
+            } else {
+                Vec::new()
+            };
+        }
+
+        match family {
+            IpFamily::V4 => self.ipv4.iter().map(|ip| IpAddr::V4(*ip)).collect(),
+            IpFamily::V6 => self.ipv6.iter().map(|ip| IpAddr::V6(*ip)).collect(),
+        }
+    }
+
+    /// All stored addresses, IPv4 first
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        [IpFamily::V4, IpFamily::V6]
+            .into_iter()
+            .flat_map(|family| self.addresses_of(family))
+            .collect()
+    }
+
+    fn set_addresses(&mut self, family: IpFamily, ips: &[IpAddr]) {
+        // Carry over the family known only from a legacy `ip` field
+        if self.ipv4.is_empty() && self.ipv6.is_empty() {
+            match self.ip {
+                IpAddr::V4(v4) => self.ipv4 = vec![v4],
+                IpAddr::V6(v6) => self.ipv6 = vec![v6],
+            }
+        }
+
+        match family {
+            IpFamily::V4 => {
+                self.ipv4 = ips
+                    .iter()
+                    .filter_map(|ip| match ip {
+                        IpAddr::V4(v4) => Some(*v4),
+                        IpAddr::V6(_) => None,
+                    })
+                    .collect()
+            }
+            IpFamily::V6 => {
+                self.ipv6 = ips
+                    .iter()
+                    .filter_map(|ip| match ip {
+                        IpAddr::V6(v6) => Some(*v6),
+                        IpAddr::V4(_) => None,
+                    })
+                    .collect()
+            }
+        }
+
+        if let Some(primary) = self.addresses().first() {
+            self.ip = *primary;
+        }
+    }
+
     pub fn update_comment(&mut self, comment: Option<String>) {
         self.comment = comment;
         self.updated_at = chrono::Utc::now();
     }
+
+    pub fn update_owner(&mut self, owner: Option<String>) {
+        self.owner = owner;
+        self.updated_at = chrono::Utc::now();
+    }
+
+    pub fn update_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+        self.updated_at = chrono::Utc::now();
+    }
+}
+
+fn unversioned() -> u32 {
+    1
+}
+
+/// Accept a single address (as written by earlier versions) or a list of addresses
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match Option::<OneOrMany<T>>::deserialize(deserializer)? {
+        Some(OneOrMany::One(value)) => vec![value],
+        Some(OneOrMany::Many(values)) => values,
+        None => Vec::new(),
+    })
 }
 
 /// Configuration entry for a web server
@@ -42,6 +217,8 @@ pub struct WebServerConfig {
     pub path: std::path::PathBuf,
     pub server_type: WebServerType,
     pub backup_path: Option<std::path::PathBuf>,
+    /// Rewrite allow list networks that contain the old address to the new address' network
+    pub rewrite_prefix: bool,
 }
 
 impl WebServerConfig {
@@ -50,6 +227,7 @@ impl WebServerConfig {
             path,
             server_type,
             backup_path: None,
+            rewrite_prefix: false,
         }
     }
 
@@ -57,6 +235,129 @@ impl WebServerConfig {
         self.backup_path = Some(backup_path);
         self
     }
+
+    pub fn with_prefix_rewrite(mut self, rewrite_prefix: bool) -> Self {
+        self.rewrite_prefix = rewrite_prefix;
+        self
+    }
+}
+
+/// Changes to the descriptive fields of a stored entry; fields left `None` are kept, and an
+/// empty comment or owner clears it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub comment: Option<String>,
+    pub owner: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl EntryMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.comment.is_none() && self.owner.is_none() && self.tags.is_none()
+    }
+
+    /// Apply the changes to an entry
+    pub fn apply_to(&self, entry: &mut IpEntry) {
+        let non_empty = |value: &String| Some(value.clone()).filter(|value| !value.is_empty());
+        if let Some(comment) = &self.comment {
+            entry.update_comment(non_empty(comment));
+        }
+        if let Some(owner) = &self.owner {
+            entry.update_owner(non_empty(owner));
+        }
+        if let Some(tags) = &self.tags {
+            let mut tags: Vec<String> = tags
+                .iter()
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            tags.sort();
+            tags.dedup();
+            entry.update_tags(tags);
+        }
+    }
+}
+
+/// Where `storage migrate` finds the legacy `<host>_ip.txt` files besides the storage
+/// directory, and the hostnames they may belong to: the files wrote a hostname's dots as
+/// underscores, so only a known hostname tells which of them were dots
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegacyFiles {
+    /// Other directories to look in, e.g. the working directory of the earliest releases
+    pub dirs: Vec<std::path::PathBuf>,
+    /// Known hostnames, e.g. the configured hosts
+    pub hostnames: Vec<String>,
+}
+
+/// A stored entry kept in an older layout than the current one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMigration {
+    pub hostname: String,
+    /// Where the entry is stored, e.g. its file
+    pub location: String,
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+/// What became of a detected address change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOutcome {
+    /// The config files were updated and the new addresses stored
+    Applied,
+    /// An edit, configuration test or reload failed and every edited file was restored
+    RolledBack,
+}
+
+impl fmt::Display for ChangeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeOutcome::Applied => write!(f, "applied"),
+            ChangeOutcome::RolledBack => write!(f, "rolled_back"),
+        }
+    }
+}
+
+impl std::str::FromStr for ChangeOutcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "applied" => Ok(ChangeOutcome::Applied),
+            "rolled_back" => Ok(ChangeOutcome::RolledBack),
+            _ => Err(format!("Unknown change outcome: {}", s)),
+        }
+    }
+}
+
+/// A config file edited for an address change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub path: std::path::PathBuf,
+    /// Backup taken right before the edit
+    pub backup_path: Option<std::path::PathBuf>,
+}
+
+/// An address change of a host, as kept in the change history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpChange {
+    pub hostname: String,
+    pub old_ips: Vec<IpAddr>,
+    pub new_ips: Vec<IpAddr>,
+    pub changed_at: chrono::DateTime<chrono::Utc>,
+    pub files: Vec<ChangedFile>,
+    pub outcome: ChangeOutcome,
+    /// Whether the web servers were reloaded with the change
+    pub reloaded: bool,
+    /// Why the change was rolled back
+    pub error: Option<String>,
+}
+
+/// Copy of a config file taken before it was edited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    pub path: std::path::PathBuf,
+    pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 /// Supported web server types
@@ -93,6 +394,237 @@ impl std::str::FromStr for WebServerType {
     }
 }
 
+/// How hostnames are resolved to their current addresses
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ResolverConfig {
+    /// The operating system's resolver (getaddrinfo), including its caches
+    #[default]
+    System,
+    /// Query nameservers directly, bypassing local caches
+    Nameservers {
+        /// Recursive nameservers to ask; empty means those from /etc/resolv.conf
+        servers: Vec<std::net::SocketAddr>,
+        transport: DnsTransport,
+        /// Find the hostname's authoritative nameservers and ask them instead
+        authoritative: bool,
+    },
+    /// DNS-over-HTTPS (RFC 8484) endpoint, e.g. `https://cloudflare-dns.com/dns-query`
+    DnsOverHttps { url: String },
+}
+
+impl fmt::Display for ResolverConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolverConfig::System => write!(f, "system resolver"),
+            ResolverConfig::Nameservers {
+                servers,
+                transport,
+                authoritative,
+            } => {
+                let servers: Vec<String> = servers.iter().map(|s| s.to_string()).collect();
+                let servers = if servers.is_empty() {
+                    "resolv.conf nameservers".to_string()
+                } else {
+                    servers.join(", ")
+                };
+                if *authoritative {
+                    write!(
+                        f,
+                        "authoritative nameservers over {} (via {})",
+                        transport, servers
+                    )
+                } else {
+                    write!(f, "{} over {}", servers, transport)
+                }
+            }
+            ResolverConfig::DnsOverHttps { url } => write!(f, "DNS-over-HTTPS {}", url),
+        }
+    }
+}
+
+/// Transport for direct nameserver queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DnsTransport {
+    /// UDP, retried over TCP when the answer is truncated
+    #[default]
+    Udp,
+    Tcp,
+}
+
+impl fmt::Display for DnsTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsTransport::Udp => write!(f, "UDP"),
+            DnsTransport::Tcp => write!(f, "TCP"),
+        }
+    }
+}
+
+/// Where the current addresses of a host come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressSource {
+    /// Resolve the hostname's A and AAAA records
+    #[default]
+    Dns,
+    /// Detect this machine's own public address; the hostname only names the stored entry and
+    /// the tagged allow list entries
+    PublicIp,
+}
+
+impl fmt::Display for AddressSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressSource::Dns => write!(f, "DNS"),
+            AddressSource::PublicIp => write!(f, "public IP"),
+        }
+    }
+}
+
+/// How the machine's own address is detected when it is the source of truth
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PublicIpBackend {
+    /// Ask the configured HTTP sources
+    #[default]
+    Http,
+    /// Read the global addresses of a local network interface, e.g. `ppp0`
+    Interface { name: String },
+    /// Ask the router for its external address; `gateway` defaults to the default route's
+    Gateway {
+        gateway: Option<std::net::IpAddr>,
+        protocols: Vec<GatewayProtocol>,
+    },
+}
+
+impl fmt::Display for PublicIpBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublicIpBackend::Http => write!(f, "HTTP IP sources"),
+            PublicIpBackend::Interface { name } => write!(f, "interface {}", name),
+            PublicIpBackend::Gateway {
+                gateway: Some(gateway),
+                ..
+            } => write!(f, "gateway {}", gateway),
+            PublicIpBackend::Gateway { gateway: None, .. } => write!(f, "default gateway"),
+        }
+    }
+}
+
+/// Where the stored addresses are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    /// One `<hostname>.json` file per host
+    #[default]
+    Json,
+    /// An SQLite database that also keeps the history of address changes
+    Sqlite,
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageBackend::Json => write!(f, "JSON files"),
+            StorageBackend::Sqlite => write!(f, "SQLite database"),
+        }
+    }
+}
+
+/// Protocols for asking a router for its external address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GatewayProtocol {
+    /// NAT Port Mapping Protocol (RFC 6886)
+    NatPmp,
+    /// Port Control Protocol (RFC 6887)
+    Pcp,
+    /// UPnP Internet Gateway Device
+    Upnp,
+}
+
+impl GatewayProtocol {
+    /// All protocols, read-only queries first as PCP needs a temporary mapping
+    pub const ALL: [GatewayProtocol; 3] = [
+        GatewayProtocol::NatPmp,
+        GatewayProtocol::Upnp,
+        GatewayProtocol::Pcp,
+    ];
+}
+
+impl fmt::Display for GatewayProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatewayProtocol::NatPmp => write!(f, "NAT-PMP"),
+            GatewayProtocol::Pcp => write!(f, "PCP"),
+            GatewayProtocol::Upnp => write!(f, "UPnP"),
+        }
+    }
+}
+
+/// Where and how the public IP address is detected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicIpConfig {
+    /// Endpoints in order of preference
+    pub sources: Vec<crate::domain::value_objects::IpSource>,
+    /// Query all sources of a family concurrently and only accept an address reported by at
+    /// least this many of them; `None` takes the first valid answer
+    pub quorum: Option<usize>,
+}
+
+impl Default for PublicIpConfig {
+    fn default() -> Self {
+        use crate::domain::value_objects::IpSource;
+
+        Self {
+            sources: vec![
+                IpSource::plain("https://api.ipify.org"),
+                IpSource::plain("https://ipinfo.io/ip"),
+                IpSource::plain("https://icanhazip.com"),
+            ],
+            quorum: None,
+        }
+    }
+}
+
+/// Where notifications about a host's address changes are sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationChannel {
+    /// Print to stdout
+    Console,
+    /// Timestamped lines on stderr, for the journal
+    Log,
+    /// POST a JSON document to the URL
+    Webhook { url: String },
+}
+
+impl fmt::Display for NotificationChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotificationChannel::Console => write!(f, "console"),
+            NotificationChannel::Log => write!(f, "log"),
+            NotificationChannel::Webhook { url } => write!(f, "{}", url),
+        }
+    }
+}
+
+impl std::str::FromStr for NotificationChannel {
+    type Err = String;
+
+    /// `console`, `log`, or an http(s) URL for a webhook
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "console" => Ok(NotificationChannel::Console),
+            "log" => Ok(NotificationChannel::Log),
+            url if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(NotificationChannel::Webhook {
+                    url: url.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Unknown notification channel: {} (use console, log or a webhook URL)",
+                s
+            )),
+        }
+    }
+}
+
 /// Domain-specific errors
 #[derive(Debug, Clone)]
 pub enum DomainError {
@@ -1,4 +1,8 @@
-use crate::domain::entities::{DomainError, IpEntry, WebServerConfig};
+use crate::domain::entities::{
+    BackupFile, DomainError, EntryMigration, IpChange, IpEntry, IpFamily, LegacyFiles,
+    WebServerConfig,
+};
+use crate::domain::value_objects::AddressDiff;
 use async_trait::async_trait;
 use std::net::IpAddr;
 
@@ -10,6 +14,12 @@ pub trait IpRepository: Send + Sync {
         hostname: &str,
         ip: IpAddr,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Store the full address set of every family present in `ips`; other families are kept
+    async fn store_ips(
+        &self,
+        hostname: &str,
+        ips: &[IpAddr],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     async fn load_ip(
         &self,
         hostname: &str,
@@ -25,16 +35,75 @@ pub trait IpRepository: Send + Sync {
         &self,
         hostname: &str,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+    /// Store an entry as it is, replacing the host's current one
+    async fn save_entry(
+        &self,
+        entry: &IpEntry,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Load the stored address set of one family
+    async fn load_ips_for_family(
+        &self,
+        hostname: &str,
+        family: IpFamily,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self
+            .get_ip_entry(hostname)
+            .await?
+            .map(|entry| entry.addresses_of(family))
+            .unwrap_or_default())
+    }
+
+    /// Append an address change to the history (ignored by repositories without history)
+    async fn record_change(
+        &self,
+        change: &IpChange,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = change;
+        Ok(())
+    }
 
-    /// Initialize DNS host file if it doesn't exist yet (optional for implementations that support it)
+    /// Recorded address changes, oldest first, of one host or all of them, optionally only
+    /// those since the given time
+    async fn change_history(
+        &self,
+        hostname: Option<&str>,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Vec<IpChange>, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = (hostname, since);
+        Ok(Vec::new())
+    }
+
+    /// Stored entries in an older layout, which loading them upgrades, and the legacy files of
+    /// `legacy`
+    async fn pending_migrations(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = legacy;
+        Ok(Vec::new())
+    }
+
+    /// Upgrade every stored entry in an older layout now, and take over the legacy files of
+    /// `legacy`; returns what was upgraded
+    async fn migrate(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = legacy;
+        Ok(Vec::new())
+    }
+
+    /// Initialize DNS host file with the whole resolved address set if it doesn't exist yet
+    /// (optional for implementations that support it)
     async fn initialize_host_file(
         &self,
         hostname: &str,
-        resolved_ip: IpAddr,
+        resolved_ips: &[IpAddr],
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         // Default implementation does nothing - only FileIpRepository implements this
         let _ = hostname;
-        let _ = resolved_ip;
+        let _ = resolved_ips;
         Ok(false)
     }
 }
@@ -42,14 +111,25 @@ pub trait IpRepository: Send + Sync {
 /// Web server configuration handler trait
 #[async_trait]
 pub trait WebServerHandler: Send + Sync {
+    /// Apply the whole address diff to the allow list in a single edit: entries of removed
+    /// addresses are rewritten to added ones of the same family, leftover removed entries are
+    /// dropped and leftover additions are inserted next to the managed entries
     async fn update_allow_list(
         &self,
         config: &WebServerConfig,
         hostname: &str,
-        old_ip: Option<IpAddr>,
-        new_ip: IpAddr,
+        diff: &AddressDiff,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
 
+    /// Content `update_allow_list` would write to the config file, without writing it; None
+    /// if nothing would change
+    async fn preview_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>;
+
     async fn validate_config(
         &self,
         config: &WebServerConfig,
@@ -62,11 +142,44 @@ pub trait WebServerHandler: Send + Sync {
         config: &WebServerConfig,
     ) -> Result<std::path::PathBuf, Box<dyn std::error::Error + Send + Sync>>;
 
+    /// Backups made by `create_backup` of a config file, oldest first
+    async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Delete a backup listed by `list_backups`
+    async fn remove_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Put a backup made by `create_backup` back in place of the config file
+    async fn restore_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
     async fn test_configuration(
         &self,
         config: &WebServerConfig,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
 
+    /// Test several edited config files of this web server at once, before a single reload;
+    /// by default each file is tested on its own
+    async fn test_configurations(
+        &self,
+        configs: &[WebServerConfig],
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        for config in configs {
+            if !self.test_configuration(config).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     async fn check_ip_in_config(
         &self,
         config: &WebServerConfig,
@@ -80,6 +193,12 @@ pub trait WebServerHandler: Send + Sync {
 #[async_trait]
 pub trait NetworkService: Send + Sync {
     async fn get_public_ip(&self) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>>;
+    /// Public addresses, one per address family that could be detected
+    async fn get_public_ips(
+        &self,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(vec![self.get_public_ip().await?])
+    }
     async fn resolve_hostname(
         &self,
         hostname: &str,
@@ -90,6 +209,15 @@ pub trait NetworkService: Send + Sync {
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
 }
 
+/// Resolves hostnames to their A and AAAA records; used by `NetworkService::resolve_hostname`
+#[async_trait]
+pub trait DnsResolver: Send + Sync {
+    async fn resolve(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
 /// Configuration discovery service
 #[async_trait]
 pub trait ConfigDiscoveryService: Send + Sync {
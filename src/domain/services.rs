@@ -1,5 +1,9 @@
-use crate::domain::entities::{IpEntry, WebServerConfig};
+use crate::domain::entities::{
+    AddressSource, BackupFile, ChangeOutcome, ChangedFile, IpChange, IpEntry, IpFamily,
+    WebServerConfig,
+};
 use crate::domain::ports::{IpRepository, NetworkService, NotificationService, WebServerHandler};
+use crate::domain::value_objects::{AddressDiff, BackupRetention};
 use std::net::IpAddr;
 use std::sync::Arc;
 
@@ -9,6 +13,8 @@ pub struct DdnsUpdateService {
     web_server_handler: Arc<dyn WebServerHandler>,
     network_service: Arc<dyn NetworkService>,
     notification_service: Arc<dyn NotificationService>,
+    address_source: AddressSource,
+    backup_retention: Option<BackupRetention>,
 }
 
 impl DdnsUpdateService {
@@ -23,9 +29,23 @@ impl DdnsUpdateService {
             web_server_handler,
             network_service,
             notification_service,
+            address_source: AddressSource::Dns,
+            backup_retention: None,
         }
     }
 
+    /// Take the current addresses from the given source instead of resolving the hostname
+    pub fn with_address_source(mut self, address_source: AddressSource) -> Self {
+        self.address_source = address_source;
+        self
+    }
+
+    /// Delete backups the policy no longer keeps after every new backup
+    pub fn with_backup_retention(mut self, backup_retention: BackupRetention) -> Self {
+        self.backup_retention = Some(backup_retention);
+        self
+    }
+
     /// Main update operation - checks current IP and updates configuration if changed
     pub async fn update_ddns(
         &self,
@@ -52,137 +72,364 @@ impl DdnsUpdateService {
             return Err("Invalid web server configuration".into());
         }
 
-        // Resolve the hostname to get its current IP address
-        eprintln!("DEBUG: Resolving hostname: {}", hostname);
-        let resolved_ips = self.network_service.resolve_hostname(hostname).await?;
+        // Resolve the hostname (or detect the public IP) to get the current IP addresses
+        eprintln!(
+            "DEBUG: Getting current addresses for {} from {}",
+            hostname, self.address_source
+        );
+        let resolved_ips =
+            current_addresses(self.network_service.as_ref(), self.address_source, hostname).await?;
         eprintln!("DEBUG: Resolved IPs: {:?}", resolved_ips);
 
-        if resolved_ips.is_empty() {
-            return Err(format!("Could not resolve hostname: {}", hostname).into());
-        }
-
-        // Use the first resolved IP (typically the primary A record)
-        let current_ip = resolved_ips[0];
-        eprintln!("DEBUG: Using IP: {}", current_ip);
-
-        // Get stored IP for this hostname
-        eprintln!("DEBUG: Loading stored IP for hostname: {}", hostname);
-        let stored_ip = self.ip_repository.load_ip(hostname).await?;
-        eprintln!("DEBUG: Stored IP: {:?}", stored_ip);
-
-        // If no JSON file exists, create it with current IP and check config
-        if stored_ip.is_none() {
-            eprintln!("DEBUG: No stored IP found, creating JSON file and checking config");
+        // Compare the A and AAAA addresses with the stored ones, family by family
+        eprintln!("DEBUG: Loading stored IPs for hostname: {}", hostname);
+        let changes =
+            detect_address_changes(self.ip_repository.as_ref(), hostname, &resolved_ips).await?;
+        eprintln!("DEBUG: Address changes: {:?}", changes);
 
-            // Store the current IP in JSON file
-            self.ip_repository.store_ip(hostname, current_ip).await?;
-            eprintln!("DEBUG: Created new JSON file with IP: {}", current_ip);
+        // A family seen for the first time is only recorded, entries are never added
+        if !changes.first_seen.is_empty() {
+            self.ip_repository
+                .store_ips(hostname, &changes.first_seen)
+                .await?;
+        }
+        for &ip in &changes.first_seen {
+            eprintln!("DEBUG: Stored first {} address: {}", IpFamily::of(ip), ip);
 
-            // Check if the current IP is already in the config file
             let ip_in_config = self
                 .web_server_handler
-                .check_ip_in_config(config, current_ip)
+                .check_ip_in_config(config, ip)
                 .await?;
-
             if ip_in_config {
                 eprintln!(
                     "DEBUG: IP {} already exists in config, no update needed",
-                    current_ip
+                    ip
                 );
-                return Ok(UpdateResult::NoChange { ip: current_ip });
             } else {
                 eprintln!(
                     "DEBUG: IP {} not found in config, but not adding new entries",
-                    current_ip
+                    ip
                 );
-                return Ok(UpdateResult::NoChange { ip: current_ip });
-            }
-        } else {
-            // Check if IP has changed
-            if let Some(old_ip) = stored_ip {
-                if old_ip == current_ip {
-                    eprintln!("DEBUG: IP unchanged, returning NoChange");
-                    return Ok(UpdateResult::NoChange { ip: current_ip });
-                }
             }
         }
 
+        if changes.diff.is_empty() {
+            eprintln!("DEBUG: IPs unchanged, returning NoChange");
+            return Ok(UpdateResult::NoChange {
+                ips: changes.current,
+            });
+        }
+
         // Only proceed with backup and update if we have an IP change
         eprintln!("DEBUG: IP has changed, proceeding with backup and update");
+        let Some(backup_path) = self.edit_config(config, hostname, &changes.diff).await? else {
+            return Ok(UpdateResult::NoChange {
+                ips: changes.current,
+            });
+        };
 
-        // First, check if this specific config file actually needs updating
-        // by checking if the old IP exists in this file
-        let needs_update = if let Some(old_ip) = stored_ip {
-            self.web_server_handler
-                .check_ip_in_config(config, old_ip)
-                .await?
-        } else {
-            // No stored IP, check if current IP is already in config
-            !self
+        // A file failing its test or a failed reload is put back from the backup, and the new
+        // addresses are neither stored nor announced
+        let files = vec![ChangedFile {
+            path: config.path.clone(),
+            backup_path: Some(backup_path.clone()),
+        }];
+        if let Err(e) = self.test_configs(std::slice::from_ref(config)).await {
+            let mut change = ip_change(hostname, &changes.diff, files, ChangeOutcome::RolledBack);
+            change.error = Some(e.to_string());
+            record_change(self.ip_repository.as_ref(), &change).await;
+            return self
+                .roll_back(config, hostname, &backup_path, e.to_string())
+                .await;
+        }
+        if let Err(e) = self.reload(no_reload).await {
+            let reason = format!("Server reload failed: {}", e);
+            let mut change = ip_change(hostname, &changes.diff, files, ChangeOutcome::RolledBack);
+            change.error = Some(reason.clone());
+            record_change(self.ip_repository.as_ref(), &change).await;
+            let result = self
+                .roll_back(config, hostname, &backup_path, reason)
+                .await?;
+            // Give the server the restored configuration
+            if let Err(e) = self.reload(no_reload).await {
+                eprintln!("Warning: Failed to reload after rolling back: {}", e);
+            }
+            return Ok(result);
+        }
+
+        // Store the new address set
+        eprintln!("DEBUG: About to store IPs");
+        match self
+            .ip_repository
+            .store_ips(hostname, &changes.current)
+            .await
+        {
+            Ok(()) => eprintln!("DEBUG: IPs stored successfully"),
+            Err(e) => {
+                eprintln!("DEBUG: Failed to store IPs: {}", e);
+                return Err(e);
+            }
+        }
+
+        let mut change = ip_change(hostname, &changes.diff, files, ChangeOutcome::Applied);
+        change.reloaded = !no_reload;
+        record_change(self.ip_repository.as_ref(), &change).await;
+
+        self.notify_diff(hostname, &changes.diff).await?;
+
+        Ok(UpdateResult::Updated {
+            hostname: hostname.to_string(),
+            diff: changes.diff,
+            backup_path,
+        })
+    }
+
+    /// Send a notification per replaced or added address
+    async fn notify_diff(
+        &self,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for (old_ip, new_ip) in diff.replacements() {
+            self.notification_service
+                .notify_ip_change(hostname, Some(old_ip), new_ip)
+                .await?;
+        }
+        for new_ip in diff.additions() {
+            self.notification_service
+                .notify_ip_change(hostname, None, new_ip)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Check if this specific config file actually needs updating by checking if one of the
+    /// old IPs exists in this file (or one staying next to an added address)
+    async fn needs_update(
+        &self,
+        config: &WebServerConfig,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut candidates = diff.removed.clone();
+        if !diff.added.is_empty() {
+            candidates.extend(&diff.unchanged);
+        }
+        for ip in candidates {
+            if self
                 .web_server_handler
-                .check_ip_in_config(config, current_ip)
+                .check_ip_in_config(config, ip)
                 .await?
-        };
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
 
-        if !needs_update {
+    /// Back up one config file and apply the whole diff to it in a single edit, without testing
+    /// or reloading. Returns the backup path, or None if the file holds none of the hostname's
+    /// stored addresses or nothing changed.
+    async fn edit_config(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<std::path::PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.needs_update(config, diff).await? {
             eprintln!("DEBUG: Config file doesn't contain old IP, no update needed for this file");
-            return Ok(UpdateResult::NoChange { ip: current_ip });
+            return Ok(None);
         }
 
         // Create backup only when we're actually going to modify the file
+        if self
+            .web_server_handler
+            .preview_allow_list(config, hostname, diff)
+            .await?
+            .is_none()
+        {
+            eprintln!("DEBUG: Allow list already up to date, no update needed for this file");
+            return Ok(None);
+        }
         eprintln!("DEBUG: Creating backup before modifying config file");
         let backup_path = self.web_server_handler.create_backup(config).await?;
 
-        // Update the web server configuration
         let updated = self
             .web_server_handler
-            .update_allow_list(config, hostname, stored_ip, current_ip)
+            .update_allow_list(config, hostname, diff)
             .await?;
+        if !updated {
+            // Changed since the preview; the backup would only crowd out older ones
+            self.web_server_handler.remove_backup(&backup_path).await?;
+            return Ok(None);
+        }
+        eprintln!("DEBUG: Updated allow list: {}", diff);
+        if let Err(e) = self.prune_backups(config, false).await {
+            eprintln!("Warning: Failed to remove old backups: {}", e);
+        }
+        Ok(Some(backup_path))
+    }
 
-        if updated {
-            // Test the new configuration
-            if !self.web_server_handler.test_configuration(config).await? {
-                return Err("Configuration test failed after update".into());
-            }
+    /// Content a config file would have after applying the diff, without backing it up or
+    /// writing it (dry run); None if the file would stay as it is
+    pub async fn preview_file(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.web_server_handler.validate_config(config).await? {
+            return Err("Invalid web server configuration".into());
+        }
+        if diff.is_empty() || !self.needs_update(config, diff).await? {
+            return Ok(None);
+        }
+        self.web_server_handler
+            .preview_allow_list(config, hostname, diff)
+            .await
+    }
 
-            // Reload the web server (unless --no-reload is specified)
-            if !no_reload {
-                eprintln!("DEBUG: About to reload server");
-                match self.web_server_handler.reload_server().await {
-                    Ok(()) => eprintln!("DEBUG: Server reload completed successfully"),
-                    Err(e) => {
-                        eprintln!("DEBUG: Server reload failed: {}", e);
-                        return Err(e);
-                    }
-                }
-            } else {
-                eprintln!("DEBUG: Skipping server reload (--no-reload specified)");
-            }
+    /// Test the edited config files of this service's web server together, then reload it once
+    /// (unless `no_reload`)
+    pub async fn test_and_reload(
+        &self,
+        configs: &[WebServerConfig],
+        no_reload: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.test_configs(configs).await?;
+        self.reload(no_reload).await
+    }
+
+    /// Test the edited config files of this service's web server together
+    pub async fn test_configs(
+        &self,
+        configs: &[WebServerConfig],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.web_server_handler.test_configurations(configs).await? {
+            return Err("Configuration test failed after update".into());
+        }
+        Ok(())
+    }
 
-            // Store the new IP
-            eprintln!("DEBUG: About to store IP");
-            match self.ip_repository.store_ip(hostname, current_ip).await {
-                Ok(()) => eprintln!("DEBUG: IP stored successfully"),
-                Err(e) => {
-                    eprintln!("DEBUG: Failed to store IP: {}", e);
-                    return Err(e);
-                }
+    /// Reload the web server (unless `no_reload`)
+    pub async fn reload(
+        &self,
+        no_reload: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if no_reload {
+            eprintln!("DEBUG: Skipping server reload (--no-reload specified)");
+            return Ok(());
+        }
+        eprintln!("DEBUG: About to reload server");
+        match self.web_server_handler.reload_server().await {
+            Ok(()) => {
+                eprintln!("DEBUG: Server reload completed successfully");
+                Ok(())
             }
+            Err(e) => {
+                eprintln!("DEBUG: Server reload failed: {}", e);
+                Err(e)
+            }
+        }
+    }
 
-            // Send notification
-            self.notification_service
-                .notify_ip_change(hostname, stored_ip, current_ip)
-                .await?;
+    /// Put an edited config file back from the backup taken before the edit
+    pub async fn restore_config(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        eprintln!(
+            "DEBUG: Restoring {} from {}",
+            config.path.display(),
+            backup_path.display()
+        );
+        self.web_server_handler
+            .restore_backup(config, backup_path)
+            .await
+    }
 
-            Ok(UpdateResult::Updated {
-                hostname: hostname.to_string(),
-                old_ip: stored_ip,
-                new_ip: current_ip,
-                backup_path,
-            })
-        } else {
-            Ok(UpdateResult::NoChange { ip: current_ip })
+    /// Restore a config file whose update failed its test or reload from the backup taken
+    /// before the edit, then check that the restored file passes the test again
+    pub async fn roll_back(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        backup_path: &std::path::Path,
+        reason: String,
+    ) -> Result<UpdateResult, Box<dyn std::error::Error + Send + Sync>> {
+        eprintln!("DEBUG: Rolling back {}: {}", config.path.display(), reason);
+        self.restore_config(config, backup_path).await?;
+        if !self.web_server_handler.test_configuration(config).await? {
+            return Err(format!(
+                "Restored {} from {} after: {}, but it still fails the configuration test",
+                config.path.display(),
+                backup_path.display(),
+                reason
+            )
+            .into());
         }
+        Ok(UpdateResult::RolledBack {
+            hostname: hostname.to_string(),
+            reason,
+            backup_path: backup_path.to_path_buf(),
+        })
+    }
+
+    /// Backups of a config file, oldest first
+    pub async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+        self.web_server_handler.list_backups(config).await
+    }
+
+    /// Delete the backups of a config file the retention policy no longer keeps (none without
+    /// a policy); with `dry_run` they are only returned. Returns the pruned backups.
+    pub async fn prune_backups(
+        &self,
+        config: &WebServerConfig,
+        dry_run: bool,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(retention) = self.backup_retention else {
+            return Ok(Vec::new());
+        };
+        let backups = self.list_backups(config).await?;
+        let pruned: Vec<BackupFile> = retention
+            .to_prune(&backups, chrono::Utc::now())
+            .into_iter()
+            .cloned()
+            .collect();
+        if !dry_run {
+            for backup in &pruned {
+                eprintln!("DEBUG: Removing old backup {}", backup.path.display());
+                self.web_server_handler.remove_backup(&backup.path).await?;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Put a chosen backup back in place of a config file, then test and reload the web server
+    /// (unless `no_reload`). The current file is backed up first, and put back if the restored
+    /// one fails its test. Returns the backup of the replaced file.
+    pub async fn restore_from_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+        no_reload: bool,
+    ) -> Result<std::path::PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        let previous = self.web_server_handler.create_backup(config).await?;
+        self.restore_config(config, backup_path).await?;
+        if let Err(e) = self.test_configs(std::slice::from_ref(config)).await {
+            self.restore_config(config, &previous).await?;
+            return Err(format!(
+                "{} failed the configuration test, kept the current file: {}",
+                backup_path.display(),
+                e
+            )
+            .into());
+        }
+        self.reload(no_reload).await?;
+        Ok(previous)
     }
 
     /// List all stored IP entries
@@ -226,14 +473,13 @@ impl DdnsUpdateService {
         Ok(results)
     }
 
-    /// Update a single config file without storing IP (used by multi-config processing)
+    /// Update a single config file without storing IP, testing or reloading (used by
+    /// multi-config processing, which calls `test_and_reload` once for all edited files)
     pub async fn update_file_only(
         &self,
         config: &WebServerConfig,
         hostname: &str,
-        stored_ip: Option<std::net::IpAddr>,
-        current_ip: std::net::IpAddr,
-        no_reload: bool,
+        diff: &AddressDiff,
     ) -> Result<UpdateResult, Box<dyn std::error::Error + Send + Sync>> {
         // Validate the configuration file
         let is_valid = self.web_server_handler.validate_config(config).await?;
@@ -241,76 +487,153 @@ impl DdnsUpdateService {
             return Err("Invalid web server configuration".into());
         }
 
-        // If no stored IP, don't update anything (this should have been handled at a higher level)
-        let stored_ip = match stored_ip {
-            Some(ip) => ip,
-            None => {
-                // Check if current IP is already in config
-                let ip_in_config = self
-                    .web_server_handler
-                    .check_ip_in_config(config, current_ip)
-                    .await?;
-                if ip_in_config {
-                    return Ok(UpdateResult::NoChange { ip: current_ip });
-                } else {
-                    // No stored IP and current IP not in config - this is a new setup
-                    // but we don't add new entries in batch mode
-                    return Ok(UpdateResult::NoChange { ip: current_ip });
-                }
-            }
-        };
+        let current_ips: Vec<IpAddr> = diff.unchanged.iter().chain(&diff.added).copied().collect();
 
-        // If IP hasn't changed, no update needed
-        if stored_ip == current_ip {
-            return Ok(UpdateResult::NoChange { ip: current_ip });
+        // Without a changed address there is nothing to do (new families are handled at a
+        // higher level and never add entries)
+        if diff.is_empty() {
+            return Ok(UpdateResult::NoChange { ips: current_ips });
         }
 
-        // Check if this specific config file actually needs updating
-        // by checking if the old IP exists in this file
-        let needs_update = self
-            .web_server_handler
-            .check_ip_in_config(config, stored_ip)
-            .await?;
-
-        if !needs_update {
-            return Ok(UpdateResult::NoChange { ip: current_ip });
+        // Note: We don't store IP here - that's handled by the calling function
+        // Note: We don't send notification here either - that should be done once for all files
+        match self.edit_config(config, hostname, diff).await? {
+            Some(backup_path) => Ok(UpdateResult::Updated {
+                hostname: hostname.to_string(),
+                diff: diff.clone(),
+                backup_path,
+            }),
+            None => Ok(UpdateResult::NoChange { ips: current_ips }),
         }
+    }
+}
 
-        // Create backup only when we're actually going to modify the file
-        let backup_path = self.web_server_handler.create_backup(config).await?;
+/// Comparison of resolved addresses with the stored ones
+#[derive(Debug, Clone, Default)]
+pub struct AddressChanges {
+    /// All current addresses, IPv4 first
+    pub current: Vec<IpAddr>,
+    /// Changes within families that have stored addresses; a family missing from the
+    /// resolver's answer keeps its stored addresses
+    pub diff: AddressDiff,
+    /// Current addresses of families without a stored address
+    pub first_seen: Vec<IpAddr>,
+}
 
-        // Update the web server configuration
-        let updated = self
-            .web_server_handler
-            .update_allow_list(config, hostname, Some(stored_ip), current_ip)
-            .await?;
+/// Current addresses of a host from its address source; never empty
+pub async fn current_addresses(
+    network_service: &dyn NetworkService,
+    address_source: AddressSource,
+    hostname: &str,
+) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let ips = match address_source {
+        AddressSource::Dns => network_service.resolve_hostname(hostname).await?,
+        AddressSource::PublicIp => network_service.get_public_ips().await?,
+    };
+    if ips.is_empty() {
+        return Err(match address_source {
+            AddressSource::Dns => format!("Could not resolve hostname: {}", hostname).into(),
+            AddressSource::PublicIp => "No public IP address detected".into(),
+        });
+    }
+    Ok(ips)
+}
 
-        if updated {
-            // Test the new configuration
-            if !self.web_server_handler.test_configuration(config).await? {
-                return Err("Configuration test failed after update".into());
-            }
+/// The resolved addresses without duplicates, IPv4 first, so resolver ordering between A and
+/// AAAA records doesn't matter
+pub fn normalize_addresses(resolved_ips: &[IpAddr]) -> Vec<IpAddr> {
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    for &ip in resolved_ips {
+        if !addresses.contains(&ip) {
+            addresses.push(ip);
+        }
+    }
+    addresses.sort_by_key(|ip| IpFamily::of(*ip));
+    addresses
+}
 
-            // Reload the web server (unless --no-reload is specified)
-            // Note: In batch mode, we might want to reload only once at the end
-            // but for now, we'll reload for each file to maintain consistency
-            if !no_reload {
-                self.web_server_handler.reload_server().await?;
+/// History record of an address change, made now; not reloaded and without an error until
+/// the caller says otherwise
+pub fn ip_change(
+    hostname: &str,
+    diff: &AddressDiff,
+    files: Vec<ChangedFile>,
+    outcome: ChangeOutcome,
+) -> IpChange {
+    IpChange {
+        hostname: hostname.to_string(),
+        old_ips: normalize_addresses(&[diff.unchanged.as_slice(), &diff.removed].concat()),
+        new_ips: normalize_addresses(&[diff.unchanged.as_slice(), &diff.added].concat()),
+        changed_at: chrono::Utc::now(),
+        files,
+        outcome,
+        reloaded: false,
+        error: None,
+    }
+}
+
+/// Append an address change to the history; a repository failing to keep it only warns, as
+/// the change itself has already happened
+pub async fn record_change(repository: &dyn IpRepository, change: &IpChange) {
+    if let Err(e) = repository.record_change(change).await {
+        eprintln!(
+            "Warning: Failed to record the address change of {}: {}",
+            change.hostname, e
+        );
+    }
+}
+
+/// Addresses written anywhere in a config file, e.g. `allow 203.0.113.7;` or `- 10.0.0.0/8`
+/// (networks count by their address), without duplicates and in order of appearance
+pub fn addresses_in(content: &str) -> Vec<IpAddr> {
+    let mut addresses = Vec::new();
+    let words = content.split(|c: char| !(c.is_ascii_hexdigit() || matches!(c, ':' | '.' | '/')));
+    for word in words {
+        let address = word.split('/').next().unwrap_or_default();
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            if !addresses.contains(&ip) {
+                addresses.push(ip);
             }
+        }
+    }
+    addresses
+}
 
-            // Note: We don't store IP here - that's handled by the calling function
-            // Note: We don't send notification here either - that should be done once for all files
+/// Compare resolved addresses with the stored addresses of each family
+pub async fn detect_address_changes(
+    ip_repository: &dyn IpRepository,
+    hostname: &str,
+    resolved_ips: &[IpAddr],
+) -> Result<AddressChanges, Box<dyn std::error::Error + Send + Sync>> {
+    let current = normalize_addresses(resolved_ips);
+    let mut stored = Vec::new();
+    let mut compared = Vec::new();
+    let mut first_seen = Vec::new();
+
+    for family in [IpFamily::V4, IpFamily::V6] {
+        let resolved: Vec<IpAddr> = current
+            .iter()
+            .filter(|ip| IpFamily::of(**ip) == family)
+            .copied()
+            .collect();
+        if resolved.is_empty() {
+            continue;
+        }
 
-            Ok(UpdateResult::Updated {
-                hostname: hostname.to_string(),
-                old_ip: Some(stored_ip),
-                new_ip: current_ip,
-                backup_path,
-            })
+        let stored_ips = ip_repository.load_ips_for_family(hostname, family).await?;
+        if stored_ips.is_empty() {
+            first_seen.extend(resolved);
         } else {
-            Ok(UpdateResult::NoChange { ip: current_ip })
+            stored.extend(stored_ips);
+            compared.extend(resolved);
         }
     }
+
+    Ok(AddressChanges {
+        diff: AddressDiff::between(&stored, &compared),
+        current,
+        first_seen,
+    })
 }
 
 /// Result of a DDNS update operation
@@ -318,12 +641,18 @@ impl DdnsUpdateService {
 pub enum UpdateResult {
     Updated {
         hostname: String,
-        old_ip: Option<IpAddr>,
-        new_ip: IpAddr,
+        diff: AddressDiff,
         backup_path: std::path::PathBuf,
     },
     NoChange {
-        ip: IpAddr,
+        ips: Vec<IpAddr>,
+    },
+    /// The edit failed its configuration test or the reload, and the file was restored from
+    /// the backup
+    RolledBack {
+        hostname: String,
+        reason: String,
+        backup_path: std::path::PathBuf,
     },
 }
 
@@ -334,3 +663,8 @@ pub struct ValidationResult {
     pub valid: bool,
     pub error: Option<String>,
 }
+
+// Include the test module
+#[cfg(test)]
+#[path = "services_tests.rs"]
+mod tests;
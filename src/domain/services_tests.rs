@@ -0,0 +1,617 @@
+use super::*;
+use crate::infrastructure::notifications::LogNotificationService;
+use crate::infrastructure::repositories::InMemoryIpRepository;
+use crate::infrastructure::webservers::TraefikHandler;
+use async_trait::async_trait;
+use tempfile::tempdir;
+
+const TRAEFIK_CONFIG: &str = "http:
+  middlewares:
+    home-allow:
+      ipAllowList:
+        sourceRange:
+          - 203.0.113.7
+          - 2001:db8::7
+";
+
+/// Resolver returning a fixed answer, in the given record order
+struct StaticResolver(Vec<IpAddr>);
+
+#[async_trait]
+impl NetworkService for StaticResolver {
+    async fn get_public_ip(&self) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.0[0])
+    }
+
+    async fn resolve_hostname(
+        &self,
+        _hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.0.clone())
+    }
+
+    async fn is_reachable(
+        &self,
+        _ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(true)
+    }
+}
+
+fn ips(values: &[&str]) -> Vec<IpAddr> {
+    values.iter().map(|v| v.parse().unwrap()).collect()
+}
+
+async fn run_update(
+    repository: Arc<InMemoryIpRepository>,
+    config: &WebServerConfig,
+    resolved: &[&str],
+) -> UpdateResult {
+    let service = DdnsUpdateService::new(
+        repository,
+        Arc::new(TraefikHandler::new()),
+        Arc::new(StaticResolver(ips(resolved))),
+        Arc::new(LogNotificationService::new()),
+    );
+    service
+        .update_ddns_with_options("home.example.com", config, true)
+        .await
+        .unwrap()
+}
+
+#[test]
+fn test_normalize_addresses_ignores_record_order_and_duplicates() {
+    assert_eq!(
+        normalize_addresses(&ips(&[
+            "2001:db8::7",
+            "203.0.113.7",
+            "203.0.113.8",
+            "203.0.113.7"
+        ])),
+        ips(&["203.0.113.7", "203.0.113.8", "2001:db8::7"])
+    );
+}
+
+#[tokio::test]
+async fn test_dual_stack_families_are_tracked_separately() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let repository = Arc::new(InMemoryIpRepository::new());
+
+    // First run records both families
+    run_update(repository.clone(), &config, &["203.0.113.7", "2001:db8::7"]).await;
+    let entry = repository
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.addresses(), ips(&["203.0.113.7", "2001:db8::7"]));
+
+    // A different resolver order is not a change
+    let result = run_update(repository.clone(), &config, &["2001:db8::7", "203.0.113.7"]).await;
+    assert!(matches!(result, UpdateResult::NoChange { .. }));
+
+    // Only the IPv6 line follows an AAAA change
+    let result = run_update(repository.clone(), &config, &["2001:db8::8", "203.0.113.7"]).await;
+    match result {
+        UpdateResult::Updated { diff, .. } => assert_eq!(
+            diff,
+            AddressDiff {
+                removed: ips(&["2001:db8::7"]),
+                added: ips(&["2001:db8::8"]),
+                unchanged: ips(&["203.0.113.7"]),
+            }
+        ),
+        other => panic!("expected an update, got {:?}", other),
+    }
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("          - 203.0.113.7\n          - 2001:db8::8\n"));
+
+    let entry = repository
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.addresses(), ips(&["203.0.113.7", "2001:db8::8"]));
+}
+
+#[tokio::test]
+async fn test_full_address_set_is_managed() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let repository = Arc::new(InMemoryIpRepository::new());
+    run_update(repository.clone(), &config, &["203.0.113.7", "2001:db8::7"]).await;
+
+    // A second A record joins the list, in the same edit as the AAAA change
+    let result = run_update(
+        repository.clone(),
+        &config,
+        &["203.0.113.7", "203.0.113.9", "2001:db8::8"],
+    )
+    .await;
+    assert!(matches!(result, UpdateResult::Updated { .. }));
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content
+        .contains("          - 203.0.113.7\n          - 203.0.113.9\n          - 2001:db8::8\n"));
+
+    let entry = repository
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        entry.addresses(),
+        ips(&["203.0.113.7", "203.0.113.9", "2001:db8::8"])
+    );
+
+    // Dropping the record removes its entry again
+    run_update(repository.clone(), &config, &["203.0.113.9", "2001:db8::8"]).await;
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content
+        .contains("        sourceRange:\n          - 203.0.113.9\n          - 2001:db8::8\n"));
+}
+
+#[test]
+fn test_legacy_entry_has_single_family() {
+    let entry: IpEntry = serde_json::from_str(
+        r#"{"ip":"203.0.113.7","hostname":"home.example.com","comment":null,
+            "created_at":"2024-01-01T00:00:00Z","updated_at":"2024-01-01T00:00:00Z"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(entry.address(IpFamily::V4), "203.0.113.7".parse().ok());
+    assert_eq!(entry.address(IpFamily::V6), None);
+
+    let mut entry = entry;
+    entry.update_ip("2001:db8::7".parse().unwrap());
+    assert_eq!(entry.addresses(), ips(&["203.0.113.7", "2001:db8::7"]));
+    assert_eq!(entry.ip, "203.0.113.7".parse::<IpAddr>().unwrap());
+}
+
+/// Network service of a machine whose hostname doesn't resolve, but whose public IP is known
+struct PublicIpOnly(Vec<IpAddr>);
+
+#[async_trait]
+impl NetworkService for PublicIpOnly {
+    async fn get_public_ip(&self) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.0[0])
+    }
+
+    async fn get_public_ips(
+        &self,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.0.clone())
+    }
+
+    async fn resolve_hostname(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("{} does not exist", hostname).into())
+    }
+
+    async fn is_reachable(
+        &self,
+        _ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(true)
+    }
+}
+
+#[tokio::test]
+async fn test_public_ip_is_the_source_of_truth() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let repository = Arc::new(InMemoryIpRepository::new());
+    repository
+        .store_ips("home.example.com", &ips(&["203.0.113.7", "2001:db8::7"]))
+        .await
+        .unwrap();
+
+    let service = |public_ips: &[&str]| {
+        DdnsUpdateService::new(
+            repository.clone(),
+            Arc::new(TraefikHandler::new()),
+            Arc::new(PublicIpOnly(ips(public_ips))),
+            Arc::new(LogNotificationService::new()),
+        )
+    };
+
+    // Resolving the hostname is not an option for this machine
+    assert!(service(&["203.0.113.8"])
+        .update_ddns_with_options("home.example.com", &config, true)
+        .await
+        .is_err());
+
+    // Only IPv4 was detected, so the stored IPv6 address stays
+    let result = service(&["203.0.113.8"])
+        .with_address_source(AddressSource::PublicIp)
+        .update_ddns_with_options("home.example.com", &config, true)
+        .await
+        .unwrap();
+    assert!(matches!(result, UpdateResult::Updated { .. }));
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("          - 203.0.113.8\n          - 2001:db8::7\n"));
+
+    let entry = repository
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.addresses(), ips(&["203.0.113.8", "2001:db8::7"]));
+}
+
+/// Traefik handler counting configuration tests and reloads
+#[derive(Default)]
+struct CountingHandler {
+    inner: TraefikHandler,
+    tests: std::sync::atomic::AtomicUsize,
+    reloads: std::sync::atomic::AtomicUsize,
+    failing_reload: bool,
+}
+
+#[async_trait]
+impl WebServerHandler for CountingHandler {
+    async fn update_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.update_allow_list(config, hostname, diff).await
+    }
+
+    async fn preview_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.preview_allow_list(config, hostname, diff).await
+    }
+
+    async fn validate_config(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.validate_config(config).await
+    }
+
+    async fn reload_server(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.reloads
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if self.failing_reload {
+            return Err("reload refused".into());
+        }
+        Ok(())
+    }
+
+    async fn create_backup(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<std::path::PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.create_backup(config).await
+    }
+
+    async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<crate::domain::entities::BackupFile>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        self.inner.list_backups(config).await
+    }
+
+    async fn remove_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.remove_backup(backup_path).await
+    }
+
+    async fn restore_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.restore_backup(config, backup_path).await
+    }
+
+    async fn test_configuration(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.test_configuration(config).await
+    }
+
+    async fn test_configurations(
+        &self,
+        configs: &[WebServerConfig],
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.tests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        for config in configs {
+            if !self.inner.test_configuration(config).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn check_ip_in_config(
+        &self,
+        config: &WebServerConfig,
+        ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.check_ip_in_config(config, ip).await
+    }
+
+    fn server_type(&self) -> crate::domain::entities::WebServerType {
+        self.inner.server_type()
+    }
+}
+
+#[tokio::test]
+async fn test_edited_files_are_tested_and_reloaded_once() {
+    let dir = tempdir().unwrap();
+    let configs: Vec<WebServerConfig> = (0..3)
+        .map(|i| {
+            let path = dir.path().join(format!("dynamic-{}.yml", i));
+            std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+            WebServerConfig::new(path, crate::domain::entities::WebServerType::Traefik)
+        })
+        .collect();
+    let handler = Arc::new(CountingHandler::default());
+    let service = DdnsUpdateService::new(
+        Arc::new(InMemoryIpRepository::new()),
+        handler.clone(),
+        Arc::new(StaticResolver(ips(&["203.0.113.8"]))),
+        Arc::new(LogNotificationService::new()),
+    );
+    let diff = AddressDiff::between(&ips(&["203.0.113.7"]), &ips(&["203.0.113.8"]));
+
+    for config in &configs {
+        let result = service
+            .update_file_only(config, "home.example.com", &diff)
+            .await
+            .unwrap();
+        assert!(matches!(result, UpdateResult::Updated { .. }));
+    }
+    // Editing alone neither tests nor reloads
+    assert_eq!(handler.tests.load(std::sync::atomic::Ordering::SeqCst), 0);
+    assert_eq!(handler.reloads.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+    service.test_and_reload(&configs, false).await.unwrap();
+    assert_eq!(handler.tests.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert_eq!(handler.reloads.load(std::sync::atomic::Ordering::SeqCst), 1);
+    for config in &configs {
+        let content = std::fs::read_to_string(&config.path).unwrap();
+        assert!(content.contains("          - 203.0.113.8\n"));
+    }
+}
+
+#[tokio::test]
+async fn test_restore_config_puts_the_backup_back() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let service = DdnsUpdateService::new(
+        Arc::new(InMemoryIpRepository::new()),
+        Arc::new(CountingHandler::default()),
+        Arc::new(StaticResolver(ips(&["203.0.113.8"]))),
+        Arc::new(LogNotificationService::new()),
+    );
+    let diff = AddressDiff::between(&ips(&["203.0.113.7"]), &ips(&["203.0.113.8"]));
+
+    let UpdateResult::Updated { backup_path, .. } = service
+        .update_file_only(&config, "home.example.com", &diff)
+        .await
+        .unwrap()
+    else {
+        panic!("expected the file to be updated");
+    };
+    assert_ne!(std::fs::read_to_string(&path).unwrap(), TRAEFIK_CONFIG);
+
+    service.restore_config(&config, &backup_path).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), TRAEFIK_CONFIG);
+}
+
+#[tokio::test]
+async fn test_failed_reload_rolls_back_the_file() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let repository = Arc::new(InMemoryIpRepository::new());
+    run_update(repository.clone(), &config, &["203.0.113.7", "2001:db8::7"]).await;
+
+    let handler = Arc::new(CountingHandler {
+        failing_reload: true,
+        ..Default::default()
+    });
+    let service = DdnsUpdateService::new(
+        repository.clone(),
+        handler.clone(),
+        Arc::new(StaticResolver(ips(&["203.0.113.8", "2001:db8::7"]))),
+        Arc::new(LogNotificationService::new()),
+    );
+    let result = service
+        .update_ddns("home.example.com", &config)
+        .await
+        .unwrap();
+
+    match result {
+        UpdateResult::RolledBack { reason, .. } => assert!(reason.contains("reload refused")),
+        other => panic!("expected a rollback, got {:?}", other),
+    }
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), TRAEFIK_CONFIG);
+    // The server is reloaded again to pick up the restored file
+    assert_eq!(handler.reloads.load(std::sync::atomic::Ordering::SeqCst), 2);
+    let entry = repository
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.addresses(), ips(&["203.0.113.7", "2001:db8::7"]));
+
+    // The failed change is kept in the history with the file and its backup
+    let history = repository.change_history(None, None).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].outcome, ChangeOutcome::RolledBack);
+    assert_eq!(history[0].old_ips, ips(&["203.0.113.7", "2001:db8::7"]));
+    assert_eq!(history[0].new_ips, ips(&["203.0.113.8", "2001:db8::7"]));
+    assert_eq!(history[0].files[0].path, path);
+    assert!(history[0].files[0].backup_path.is_some());
+    assert!(!history[0].reloaded);
+    assert!(history[0]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("reload refused"));
+}
+
+#[tokio::test]
+async fn test_preview_leaves_the_file_alone() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let service = DdnsUpdateService::new(
+        Arc::new(InMemoryIpRepository::new()),
+        Arc::new(CountingHandler::default()),
+        Arc::new(StaticResolver(ips(&["203.0.113.8"]))),
+        Arc::new(LogNotificationService::new()),
+    );
+
+    let diff = AddressDiff::between(&ips(&["203.0.113.7"]), &ips(&["203.0.113.8"]));
+    let preview = service
+        .preview_file(&config, "home.example.com", &diff)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(preview.contains("          - 203.0.113.8\n"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), TRAEFIK_CONFIG);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // A file without the old address would not change
+    let diff = AddressDiff::between(&ips(&["198.51.100.1"]), &ips(&["203.0.113.8"]));
+    assert_eq!(
+        service
+            .preview_file(&config, "home.example.com", &diff)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_addresses_in_config_content() {
+    assert_eq!(
+        addresses_in(
+            "allow 203.0.113.7; # DDNS: home\nallow 2001:db8::/56;\n- \"10.0.0.0/8\"\nlisten 80;\n"
+        ),
+        ips(&["203.0.113.7", "2001:db8::", "10.0.0.0"])
+    );
+}
+
+#[tokio::test]
+async fn test_backups_beyond_the_retention_are_removed_after_each_edit() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let service = DdnsUpdateService::new(
+        Arc::new(InMemoryIpRepository::new()),
+        Arc::new(CountingHandler::default()),
+        Arc::new(StaticResolver(ips(&["203.0.113.9"]))),
+        Arc::new(LogNotificationService::new()),
+    )
+    .with_backup_retention(BackupRetention::new(2, 30).unwrap());
+
+    let mut made = Vec::new();
+    for (old_ip, new_ip) in [
+        ("203.0.113.7", "203.0.113.8"),
+        ("203.0.113.8", "203.0.113.9"),
+        ("203.0.113.9", "203.0.113.10"),
+    ] {
+        let diff = AddressDiff::between(&ips(&[old_ip]), &ips(&[new_ip]));
+        match service
+            .update_file_only(&config, "home.example.com", &diff)
+            .await
+            .unwrap()
+        {
+            UpdateResult::Updated { backup_path, .. } => made.push(backup_path),
+            other => panic!("expected an update, got {:?}", other),
+        }
+    }
+
+    let kept: Vec<std::path::PathBuf> = service
+        .list_backups(&config)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|backup| backup.path)
+        .collect();
+    assert_eq!(kept, made[1..].to_vec());
+    assert!(service
+        .prune_backups(&config, false)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_no_backup_is_left_when_nothing_changes() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("dynamic.yml");
+    std::fs::write(&path, TRAEFIK_CONFIG).unwrap();
+    let config = WebServerConfig::new(
+        path.clone(),
+        crate::domain::entities::WebServerType::Traefik,
+    );
+    let service = DdnsUpdateService::new(
+        Arc::new(InMemoryIpRepository::new()),
+        Arc::new(CountingHandler::default()),
+        Arc::new(StaticResolver(ips(&["203.0.113.7", "2001:db8::7"]))),
+        Arc::new(LogNotificationService::new()),
+    );
+
+    // The file holds the stored address, but already lists the added one
+    let diff = AddressDiff::between(
+        &ips(&["203.0.113.7"]),
+        &ips(&["203.0.113.7", "2001:db8::7"]),
+    );
+    let result = service
+        .update_file_only(&config, "home.example.com", &diff)
+        .await
+        .unwrap();
+    assert!(matches!(result, UpdateResult::NoChange { .. }));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), TRAEFIK_CONFIG);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -1,6 +1,9 @@
 use std::fmt;
+use std::net::IpAddr;
 use std::path::PathBuf;
 
+use crate::domain::entities::{BackupFile, IpFamily};
+
 /// Value object for configuration paths with validation
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConfigPath {
@@ -116,6 +119,368 @@ impl BackupRetention {
             max_age_days,
         })
     }
+
+    /// Backups of one config file to delete under this policy: those older than
+    /// `max_age_days`, then the oldest of the rest beyond `max_backups`. `backups` must be
+    /// sorted oldest first.
+    pub fn to_prune<'a>(
+        &self,
+        backups: &'a [BackupFile],
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<&'a BackupFile> {
+        let cutoff = now - chrono::Duration::days(i64::from(self.max_age_days));
+        let expired = backups
+            .iter()
+            .take_while(|backup| backup.created_at < cutoff)
+            .count();
+        let excess = backups.len().saturating_sub(usize::from(self.max_backups));
+        backups[..expired.max(excess)].iter().collect()
+    }
+}
+
+/// An IP network in CIDR notation; plain addresses are host networks (/32 or /128)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    pub fn new(address: IpAddr, prefix_len: u8) -> Result<Self, IpNetworkError> {
+        if prefix_len > max_prefix_len(address) {
+            return Err(IpNetworkError::InvalidPrefixLength(prefix_len));
+        }
+
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    /// Network containing exactly one address
+    pub fn host(address: IpAddr) -> Self {
+        Self {
+            address,
+            prefix_len: max_prefix_len(address),
+        }
+    }
+
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    pub fn family(&self) -> IpFamily {
+        IpFamily::of(self.address)
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.prefix_len == max_prefix_len(self.address)
+    }
+
+    /// First address of the network (host bits cleared)
+    pub fn network(&self) -> IpAddr {
+        mask_address(self.address, self.prefix_len)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.family() == IpFamily::of(ip) && mask_address(ip, self.prefix_len) == self.network()
+    }
+
+    /// The network of the same size that contains `ip`
+    pub fn with_address(&self, ip: IpAddr) -> Result<Self, IpNetworkError> {
+        if self.family() != IpFamily::of(ip) {
+            return Err(IpNetworkError::FamilyMismatch);
+        }
+
+        Ok(Self {
+            address: mask_address(ip, self.prefix_len),
+            prefix_len: self.prefix_len,
+        })
+    }
+
+    /// How this allow list entry changes when a managed address moves from `old_ip` to `new_ip`.
+    ///
+    /// Host entries equal to `old_ip` become `new_ip`. Networks containing `old_ip` are only
+    /// touched with `rewrite_prefix`: they move to the network of the same length around
+    /// `new_ip`, unless `new_ip` is still inside. Entries never change their address family.
+    /// Returns None if the entry stays as it is.
+    pub fn updated_for(
+        &self,
+        old_ip: IpAddr,
+        new_ip: IpAddr,
+        rewrite_prefix: bool,
+    ) -> Option<Self> {
+        if self.family() != IpFamily::of(new_ip) {
+            return None;
+        }
+
+        if self.is_host() {
+            return (self.address == old_ip).then(|| Self::host(new_ip));
+        }
+
+        if !rewrite_prefix || !self.contains(old_ip) || self.contains(new_ip) {
+            return None;
+        }
+        self.with_address(new_ip).ok()
+    }
+
+    /// Whether this entry manages `ip`: an equal host entry, or a network containing it when
+    /// prefix rewriting is enabled
+    pub fn manages(&self, ip: IpAddr, rewrite_prefix: bool) -> bool {
+        if self.is_host() {
+            self.address == ip
+        } else {
+            rewrite_prefix && self.contains(ip)
+        }
+    }
+
+    /// Format the entry in the same style as `original` (bare address vs. CIDR notation)
+    pub fn format_like(&self, original: &str) -> String {
+        if self.is_host() && !original.contains('/') {
+            self.address.to_string()
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+impl std::str::FromStr for IpNetwork {
+    type Err = IpNetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((address, prefix_len)) => {
+                let address = address
+                    .parse()
+                    .map_err(|_| IpNetworkError::InvalidAddress(s.to_string()))?;
+                let prefix_len = prefix_len
+                    .parse()
+                    .map_err(|_| IpNetworkError::InvalidAddress(s.to_string()))?;
+                Self::new(address, prefix_len)
+            }
+            None => s
+                .parse()
+                .map(Self::host)
+                .map_err(|_| IpNetworkError::InvalidAddress(s.to_string())),
+        }
+    }
+}
+
+/// Difference between the stored and the current address set of a hostname
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressDiff {
+    pub removed: Vec<IpAddr>,
+    pub added: Vec<IpAddr>,
+    /// Addresses in both sets; allow list entries holding them anchor where additions go
+    pub unchanged: Vec<IpAddr>,
+}
+
+impl AddressDiff {
+    pub fn between(stored: &[IpAddr], current: &[IpAddr]) -> Self {
+        Self {
+            removed: stored
+                .iter()
+                .filter(|ip| !current.contains(ip))
+                .copied()
+                .collect(),
+            added: current
+                .iter()
+                .filter(|ip| !stored.contains(ip))
+                .copied()
+                .collect(),
+            unchanged: current
+                .iter()
+                .filter(|ip| stored.contains(ip))
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// A single address moving from `old_ip` to `new_ip`
+    pub fn replacement(old_ip: IpAddr, new_ip: IpAddr) -> Self {
+        Self {
+            removed: vec![old_ip],
+            added: vec![new_ip],
+            unchanged: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+
+    /// Removed and added addresses of the same family paired up in order; allow list entries
+    /// of the removed address are rewritten in place
+    pub fn replacements(&self) -> Vec<(IpAddr, IpAddr)> {
+        [IpFamily::V4, IpFamily::V6]
+            .into_iter()
+            .flat_map(|family| {
+                let removed = self
+                    .removed
+                    .iter()
+                    .filter(move |ip| IpFamily::of(**ip) == family);
+                let added = self
+                    .added
+                    .iter()
+                    .filter(move |ip| IpFamily::of(**ip) == family);
+                removed.copied().zip(added.copied())
+            })
+            .collect()
+    }
+
+    /// Removed addresses without a replacement of the same family
+    pub fn removals(&self) -> Vec<IpAddr> {
+        let replaced: Vec<IpAddr> = self.replacements().iter().map(|(old, _)| *old).collect();
+        self.removed
+            .iter()
+            .filter(|ip| !replaced.contains(ip))
+            .copied()
+            .collect()
+    }
+
+    /// Added addresses that don't replace a removed one
+    pub fn additions(&self) -> Vec<IpAddr> {
+        let replacing: Vec<IpAddr> = self.replacements().iter().map(|(_, new)| *new).collect();
+        self.added
+            .iter()
+            .filter(|ip| !replacing.contains(ip))
+            .copied()
+            .collect()
+    }
+}
+
+impl fmt::Display for AddressDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |ips: &[IpAddr]| {
+            ips.iter()
+                .map(|ip| ip.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let replacements = self.replacements();
+        let mut parts: Vec<String> = replacements
+            .iter()
+            .map(|(old, new)| format!("{} → {}", old, new))
+            .collect();
+        if !self.removals().is_empty() {
+            parts.push(format!("removed {}", join(&self.removals())));
+        }
+        if !self.additions().is_empty() {
+            parts.push(format!("added {}", join(&self.additions())));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+fn max_prefix_len(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn mask_address(address: IpAddr, prefix_len: u8) -> IpAddr {
+    match address {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    }
+}
+
+/// Endpoint reporting the caller's public IP address.
+///
+/// Written as `[v4@|v6@]URL[#field]`: the optional prefix pins the source to one address
+/// family (the request is then made over that family), and the optional fragment names the
+/// field holding the address in a JSON response, with `.` separating nested fields or array
+/// indices, e.g. `https://ipinfo.io/json#ip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpSource {
+    pub url: String,
+    pub family: Option<IpFamily>,
+    pub json_field: Option<String>,
+}
+
+impl IpSource {
+    /// Source returning the address as plain text, over either family
+    pub fn plain(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            family: None,
+            json_field: None,
+        }
+    }
+
+    pub fn with_family(mut self, family: IpFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn with_json_field(mut self, field: &str) -> Self {
+        self.json_field = Some(field.to_string());
+        self
+    }
+
+    /// Whether this source can report an address of the given family
+    pub fn serves(&self, family: IpFamily) -> bool {
+        self.family.is_none_or(|own| own == family)
+    }
+}
+
+impl fmt::Display for IpSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.family {
+            Some(IpFamily::V4) => write!(f, "v4@")?,
+            Some(IpFamily::V6) => write!(f, "v6@")?,
+            None => {}
+        }
+        write!(f, "{}", self.url)?;
+        if let Some(field) = &self.json_field {
+            write!(f, "#{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for IpSource {
+    type Err = IpSourceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (family, rest) = match s.split_once('@') {
+            Some(("v4", rest)) => (Some(IpFamily::V4), rest),
+            Some(("v6", rest)) => (Some(IpFamily::V6), rest),
+            _ => (None, s),
+        };
+        let (url, json_field) = match rest.split_once('#') {
+            Some((_, "")) => return Err(IpSourceError::EmptyField(s.to_string())),
+            Some((url, field)) => (url, Some(field.to_string())),
+            None => (rest, None),
+        };
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(IpSourceError::InvalidUrl(s.to_string()));
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            family,
+            json_field,
+        })
+    }
 }
 
 /// Configuration path errors
@@ -187,3 +552,56 @@ impl fmt::Display for BackupRetentionError {
 }
 
 impl std::error::Error for BackupRetentionError {}
+
+/// IP network parsing errors
+#[derive(Debug, Clone)]
+pub enum IpNetworkError {
+    InvalidAddress(String),
+    InvalidPrefixLength(u8),
+    FamilyMismatch,
+}
+
+impl fmt::Display for IpNetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpNetworkError::InvalidAddress(value) => {
+                write!(f, "Invalid IP address or network: {}", value)
+            }
+            IpNetworkError::InvalidPrefixLength(len) => {
+                write!(f, "Invalid prefix length: /{}", len)
+            }
+            IpNetworkError::FamilyMismatch => {
+                write!(f, "Cannot mix IPv4 and IPv6 addresses in one network")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IpNetworkError {}
+
+/// Public IP source parsing errors
+#[derive(Debug, Clone)]
+pub enum IpSourceError {
+    InvalidUrl(String),
+    EmptyField(String),
+}
+
+impl fmt::Display for IpSourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpSourceError::InvalidUrl(value) => {
+                write!(f, "IP source must be an http(s) URL: {}", value)
+            }
+            IpSourceError::EmptyField(value) => {
+                write!(f, "Empty JSON field name in IP source: {}", value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IpSourceError {}
+
+// Include the test module
+#[cfg(test)]
+#[path = "value_objects_tests.rs"]
+mod tests;
@@ -0,0 +1,128 @@
+use super::*;
+
+fn network(value: &str) -> IpNetwork {
+    value.parse().unwrap()
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+#[test]
+fn test_parse_ip_network() {
+    assert!(network("203.0.113.7").is_host());
+    assert!(network("203.0.113.7/32").is_host());
+    assert_eq!(network("10.1.2.3/8").network(), ip("10.0.0.0"));
+    assert_eq!(
+        network("2001:db8:aa12::1/56").to_string(),
+        "2001:db8:aa12::1/56"
+    );
+
+    assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+    assert!("2001:db8::/129".parse::<IpNetwork>().is_err());
+    assert!("example.com".parse::<IpNetwork>().is_err());
+}
+
+#[test]
+fn test_contains_respects_family_and_prefix() {
+    let lan = network("192.168.1.0/24");
+    assert!(lan.contains(ip("192.168.1.200")));
+    assert!(!lan.contains(ip("192.168.2.1")));
+    assert!(!lan.contains(ip("::ffff:192.168.1.1")));
+    assert!(network("0.0.0.0/0").contains(ip("198.51.100.1")));
+}
+
+#[test]
+fn test_updated_for_host_entries() {
+    let old_ip = ip("203.0.113.7");
+    let new_ip = ip("203.0.113.8");
+
+    let updated = network("203.0.113.7/32").updated_for(old_ip, new_ip, false);
+    assert_eq!(updated, Some(IpNetwork::host(new_ip)));
+    assert_eq!(
+        updated.unwrap().format_like("203.0.113.7/32"),
+        "203.0.113.8/32"
+    );
+    assert_eq!(updated.unwrap().format_like("203.0.113.7"), "203.0.113.8");
+
+    assert_eq!(
+        network("203.0.113.70").updated_for(old_ip, new_ip, true),
+        None
+    );
+}
+
+#[test]
+fn test_updated_for_networks_keeps_prefix_length() {
+    let delegated = network("2001:db8:aa:1200::/56");
+    let old_ip = ip("2001:db8:aa:1234::10");
+    let new_ip = ip("2001:db8:bb:4242::10");
+
+    // Networks are only managed with prefix rewriting enabled
+    assert!(!delegated.manages(old_ip, false));
+    assert_eq!(delegated.updated_for(old_ip, new_ip, false), None);
+
+    assert!(delegated.manages(old_ip, true));
+    let updated = delegated.updated_for(old_ip, new_ip, true).unwrap();
+    assert_eq!(updated.to_string(), "2001:db8:bb:4200::/56");
+
+    // Nothing to do while the new address stays inside the network
+    assert_eq!(
+        delegated.updated_for(old_ip, ip("2001:db8:aa:12ff::99"), true),
+        None
+    );
+    // Networks of the other family are never rewritten
+    assert_eq!(delegated.updated_for(old_ip, ip("203.0.113.8"), true), None);
+}
+
+#[test]
+fn test_parse_ip_source() {
+    let source: IpSource = "v6@https://ip.example.net/json#data.ip".parse().unwrap();
+    assert_eq!(source.url, "https://ip.example.net/json");
+    assert_eq!(source.family, Some(IpFamily::V6));
+    assert_eq!(source.json_field.as_deref(), Some("data.ip"));
+    assert_eq!(source.to_string(), "v6@https://ip.example.net/json#data.ip");
+    assert!(!source.serves(IpFamily::V4));
+
+    let plain: IpSource = "http://10.0.0.1:8080/ip".parse().unwrap();
+    assert_eq!(plain, IpSource::plain("http://10.0.0.1:8080/ip"));
+    assert!(plain.serves(IpFamily::V4) && plain.serves(IpFamily::V6));
+
+    assert!("ipinfo.io/ip".parse::<IpSource>().is_err());
+    assert!("v5@https://ipinfo.io/ip".parse::<IpSource>().is_err());
+    assert!("https://ipinfo.io/json#".parse::<IpSource>().is_err());
+}
+
+#[test]
+fn test_backup_retention_prunes_old_and_excess_backups() {
+    let now = chrono::Utc::now();
+    let backup = |days_old: i64, name: &str| BackupFile {
+        path: PathBuf::from(name),
+        created_at: now - chrono::Duration::days(days_old),
+    };
+    let backups = vec![
+        backup(40, "a"),
+        backup(35, "b"),
+        backup(10, "c"),
+        backup(5, "d"),
+        backup(1, "e"),
+        backup(0, "f"),
+    ];
+    let names = |pruned: Vec<&BackupFile>| -> Vec<String> {
+        pruned
+            .iter()
+            .map(|backup| backup.path.display().to_string())
+            .collect()
+    };
+
+    let retention = BackupRetention::new(10, 30).unwrap();
+    assert_eq!(names(retention.to_prune(&backups, now)), vec!["a", "b"]);
+
+    let retention = BackupRetention::new(3, 30).unwrap();
+    assert_eq!(
+        names(retention.to_prune(&backups, now)),
+        vec!["a", "b", "c"]
+    );
+
+    assert!(BackupRetention::default().to_prune(&[], now).is_empty());
+    assert!(BackupRetention::new(0, 30).is_err());
+}
@@ -4,6 +4,7 @@ use tokio::fs;
 
 use crate::domain::entities::{DomainError, WebServerConfig, WebServerType};
 use crate::domain::ports::ConfigDiscoveryService;
+use crate::infrastructure::webservers::traefik::is_traefik_dynamic_config;
 
 /// File system-based configuration discovery service
 pub struct FileSystemConfigDiscovery;
@@ -39,6 +40,14 @@ impl FileSystemConfigDiscovery {
             return Ok(WebServerType::Apache);
         }
 
+        if path_str.contains("caddy") {
+            return Ok(WebServerType::Caddy);
+        }
+
+        if path_str.contains("traefik") {
+            return Ok(WebServerType::Traefik);
+        }
+
         // If path doesn't give us a clue, examine file content
         let path_buf = path.to_path_buf();
         let content_result =
@@ -50,6 +59,11 @@ impl FileSystemConfigDiscovery {
 
         match content_result {
             Ok(content) => {
+                // Traefik dynamic configs use camelCase keys, so check before lowercasing
+                if is_traefik_dynamic_config(&content) {
+                    return Ok(WebServerType::Traefik);
+                }
+
                 let content_lower = content.to_lowercase();
 
                 // Look for Nginx-specific directives
@@ -70,6 +84,8 @@ impl FileSystemConfigDiscovery {
                 // Look for Caddy-specific syntax
                 if content_lower.contains("caddyfile")
                     || (content_lower.contains("{") && content_lower.contains("reverse_proxy"))
+                    || content_lower.contains("remote_ip")
+                    || content_lower.contains("client_ip")
                 {
                     return Ok(WebServerType::Caddy);
                 }
@@ -98,6 +114,7 @@ impl FileSystemConfigDiscovery {
             "/etc/httpd/sites-available/*",
             "/usr/local/etc/nginx/*",
             "/usr/local/etc/apache2*/*",
+            "/etc/traefik/dynamic/*",
         ]
     }
 
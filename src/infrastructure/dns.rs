@@ -0,0 +1,307 @@
+//! Minimal DNS wire format (RFC 1035) for the A, AAAA, NS and CNAME lookups done by the
+//! nameserver and DNS-over-HTTPS resolvers
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub(crate) const TYPE_A: u16 = 1;
+pub(crate) const TYPE_NS: u16 = 2;
+pub(crate) const TYPE_CNAME: u16 = 5;
+pub(crate) const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+pub(crate) const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+
+pub(crate) const RCODE_NO_ERROR: u8 = 0;
+pub(crate) const RCODE_NXDOMAIN: u8 = 3;
+
+/// Compression pointers followed before a name is considered malformed
+const MAX_POINTERS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DnsError {
+    /// The message ends before the data announced in it
+    Truncated,
+    InvalidName(String),
+    PointerLoop,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsError::Truncated => write!(f, "DNS message is shorter than announced"),
+            DnsError::InvalidName(name) => write!(f, "Invalid DNS name: {}", name),
+            DnsError::PointerLoop => write!(f, "DNS name compression pointers form a loop"),
+        }
+    }
+}
+
+impl std::error::Error for DnsError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Question {
+    pub name: String,
+    pub qtype: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(String),
+    Cname(String),
+    Other { rtype: u16, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Record {
+    /// Owner name without the trailing dot
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Message {
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub authority: Vec<Record>,
+    pub additional: Vec<Record>,
+}
+
+impl Message {
+    pub fn query(id: u16, name: &str, qtype: u16, recursion_desired: bool) -> Self {
+        Self {
+            id,
+            flags: if recursion_desired {
+                FLAG_RECURSION_DESIRED
+            } else {
+                0
+            },
+            questions: vec![Question {
+                name: name.trim_end_matches('.').to_string(),
+                qtype,
+            }],
+            answers: Vec::new(),
+            authority: Vec::new(),
+            additional: Vec::new(),
+        }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.flags & FLAG_TRUNCATED != 0
+    }
+
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0x000f) as u8
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, DnsError> {
+        let mut out = Vec::with_capacity(512);
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&self.flags.to_be_bytes());
+        for count in [
+            self.questions.len(),
+            self.answers.len(),
+            self.authority.len(),
+            self.additional.len(),
+        ] {
+            out.extend_from_slice(&(count as u16).to_be_bytes());
+        }
+
+        for question in &self.questions {
+            encode_name(&mut out, &question.name)?;
+            out.extend_from_slice(&question.qtype.to_be_bytes());
+            out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        }
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authority)
+            .chain(&self.additional)
+        {
+            encode_record(&mut out, record)?;
+        }
+        Ok(out)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DnsError> {
+        let mut reader = Reader { bytes, position: 0 };
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        let counts = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+
+        let mut questions = Vec::new();
+        for _ in 0..counts[0] {
+            let name = reader.name()?;
+            let qtype = reader.u16()?;
+            let _class = reader.u16()?;
+            questions.push(Question { name, qtype });
+        }
+
+        let mut sections = [Vec::new(), Vec::new(), Vec::new()];
+        for (section, count) in sections.iter_mut().zip(&counts[1..]) {
+            for _ in 0..*count {
+                section.push(reader.record()?);
+            }
+        }
+        let [answers, authority, additional] = sections;
+
+        Ok(Self {
+            id,
+            flags,
+            questions,
+            answers,
+            authority,
+            additional,
+        })
+    }
+}
+
+fn encode_name(out: &mut Vec<u8>, name: &str) -> Result<(), DnsError> {
+    let name = name.trim_end_matches('.');
+    if name.len() > 253 {
+        return Err(DnsError::InvalidName(name.to_string()));
+    }
+    if !name.is_empty() {
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(DnsError::InvalidName(name.to_string()));
+            }
+            out.push(label.len() as u8);
+            out.extend_from_slice(label.as_bytes());
+        }
+    }
+    out.push(0);
+    Ok(())
+}
+
+fn encode_record(out: &mut Vec<u8>, record: &Record) -> Result<(), DnsError> {
+    encode_name(out, &record.name)?;
+    let (rtype, data) = match &record.data {
+        RecordData::A(ip) => (TYPE_A, ip.octets().to_vec()),
+        RecordData::Aaaa(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+        RecordData::Ns(name) => {
+            let mut data = Vec::new();
+            encode_name(&mut data, name)?;
+            (TYPE_NS, data)
+        }
+        RecordData::Cname(name) => {
+            let mut data = Vec::new();
+            encode_name(&mut data, name)?;
+            (TYPE_CNAME, data)
+        }
+        RecordData::Other { rtype, data } => (*rtype, data.clone()),
+    };
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&CLASS_IN.to_be_bytes());
+    out.extend_from_slice(&record.ttl.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(&data);
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], DnsError> {
+        let end = self.position + len;
+        let slice = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(DnsError::Truncated)?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, DnsError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, DnsError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a possibly compressed name at the current position
+    fn name(&mut self) -> Result<String, DnsError> {
+        let (name, end) = read_name(self.bytes, self.position)?;
+        self.position = end;
+        Ok(name)
+    }
+
+    fn record(&mut self) -> Result<Record, DnsError> {
+        let name = self.name()?;
+        let rtype = self.u16()?;
+        let _class = self.u16()?;
+        let ttl = self.u32()?;
+        let len = self.u16()? as usize;
+        let start = self.position;
+        let data = self.take(len)?;
+
+        let data = match (rtype, len) {
+            (TYPE_A, 4) => RecordData::A(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            (TYPE_AAAA, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                RecordData::Aaaa(Ipv6Addr::from(octets))
+            }
+            // Names inside record data may point anywhere into the message
+            (TYPE_NS, _) => RecordData::Ns(read_name(self.bytes, start)?.0),
+            (TYPE_CNAME, _) => RecordData::Cname(read_name(self.bytes, start)?.0),
+            _ => RecordData::Other {
+                rtype,
+                data: data.to_vec(),
+            },
+        };
+        Ok(Record { name, ttl, data })
+    }
+}
+
+/// Read the name starting at `position`; returns it with the position after its first part
+fn read_name(bytes: &[u8], mut position: usize) -> Result<(String, usize), DnsError> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    let mut pointers = 0;
+
+    loop {
+        let len = *bytes.get(position).ok_or(DnsError::Truncated)? as usize;
+        match len {
+            0 => {
+                end.get_or_insert(position + 1);
+                break;
+            }
+            len if len & 0xc0 == 0xc0 => {
+                let low = *bytes.get(position + 1).ok_or(DnsError::Truncated)? as usize;
+                end.get_or_insert(position + 2);
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(DnsError::PointerLoop);
+                }
+                position = ((len & 0x3f) << 8) | low;
+            }
+            len => {
+                let label = bytes
+                    .get(position + 1..position + 1 + len)
+                    .ok_or(DnsError::Truncated)?;
+                labels.push(String::from_utf8_lossy(label).to_lowercase());
+                position += 1 + len;
+            }
+        }
+    }
+
+    Ok((labels.join("."), end.unwrap_or(position)))
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "dns_tests.rs"]
+mod tests;
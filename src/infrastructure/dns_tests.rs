@@ -0,0 +1,64 @@
+use super::*;
+
+#[test]
+fn test_query_round_trip() {
+    let query = Message::query(0x1234, "Home.Example.com.", TYPE_AAAA, true);
+    let bytes = query.encode().unwrap();
+    assert_eq!(&bytes[..4], &[0x12, 0x34, 0x01, 0x00]);
+
+    let decoded = Message::decode(&bytes).unwrap();
+    assert_eq!(decoded.id, 0x1234);
+    assert_eq!(
+        decoded.questions,
+        vec![Question {
+            name: "home.example.com".to_string(),
+            qtype: TYPE_AAAA
+        }]
+    );
+    assert!(!decoded.is_truncated());
+}
+
+#[test]
+fn test_decodes_compressed_names() {
+    // Answer to `a.example.com A` with a CNAME and an A record whose names point back
+    // into the question
+    let mut bytes = vec![
+        0xab, 0xcd, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0, // header
+        1, b'a', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 1, 0, 1,
+    ];
+    // a.example.com CNAME b.example.com (pointer to "example.com" at offset 14)
+    bytes.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 4, 1, b'b', 0xc0, 14]);
+    // b.example.com A 203.0.113.7 (pointer to the CNAME target at offset 43)
+    bytes.extend_from_slice(&[0xc0, 43, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 203, 0, 113, 7]);
+
+    let message = Message::decode(&bytes).unwrap();
+    assert_eq!(message.rcode(), RCODE_NO_ERROR);
+    assert_eq!(
+        message.answers[0].data,
+        RecordData::Cname("b.example.com".to_string())
+    );
+    assert_eq!(message.answers[1].name, "b.example.com");
+    assert_eq!(
+        message.answers[1].data,
+        RecordData::A("203.0.113.7".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_rejects_malformed_messages() {
+    let bytes = Message::query(1, "example.com", TYPE_A, true)
+        .encode()
+        .unwrap();
+    assert_eq!(
+        Message::decode(&bytes[..bytes.len() - 1]),
+        Err(DnsError::Truncated)
+    );
+
+    // A name pointing at itself
+    let looping = [0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0xc0, 12, 0, 1, 0, 1];
+    assert_eq!(Message::decode(&looping), Err(DnsError::PointerLoop));
+
+    assert!(Message::query(1, &"a".repeat(64), TYPE_A, true)
+        .encode()
+        .is_err());
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// How long a lock held by another run is waited for before giving up
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Replace a file's content by writing a sibling temp file and renaming it over the original.
+///
+/// Readers that watch the file (e.g. Traefik's file provider) never observe a half-written
+/// config, and a crash or full disk leaves either the old or the new content behind: the temp
+/// file is flushed to disk before the rename, and the directory after it. The original owner
+/// and permissions are carried over, and a symlink is followed so the link itself stays.
+pub(crate) async fn write_atomically(
+    path: &Path,
+    content: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = match fs::symlink_metadata(path).await {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path).await?,
+        _ => path.to_path_buf(),
+    };
+    let temp_path = temp_path_for(&path);
+
+    if let Err(e) = write_temp_file(&path, &temp_path, content).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&temp_path, &path).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e.into());
+    }
+    sync_parent_dir(&path).await;
+    Ok(())
+}
+
+/// Write and flush the temp file with the metadata of the file it replaces
+async fn write_temp_file(
+    path: &Path,
+    temp_path: &Path,
+    content: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(temp_path)
+        .await?;
+    file.write_all(content.as_bytes()).await?;
+
+    if let Ok(metadata) = fs::metadata(path).await {
+        fs::set_permissions(temp_path, metadata.permissions()).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // Only root can give a file away; other users keep writing files they own
+            if let Err(e) =
+                std::os::unix::fs::chown(temp_path, Some(metadata.uid()), Some(metadata.gid()))
+            {
+                eprintln!(
+                    "Warning: Failed to keep the owner of {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    file.sync_all().await?;
+    Ok(())
+}
+
+/// Flush a rename to disk; not every platform or filesystem can sync a directory, so failures
+/// are ignored
+async fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = fs::File::open(dir).await {
+            let _ = dir.sync_all().await;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Hidden temp file next to the target so the rename stays on the same filesystem
+fn temp_path_for(path: &Path) -> PathBuf {
+    let filename = path
+        .file_name()
+        .unwrap_or_else(|| std::ffi::OsStr::new("config"))
+        .to_string_lossy();
+    path.with_file_name(format!(".{}.ddns-tmp.{}", filename, std::process::id()))
+}
+
+/// Advisory lock on a lock file, held until dropped.
+///
+/// Runs of the updater that touch the same storage directory or config file take its lock,
+/// so a timer run and a manual run serialize instead of racing. The holder writes its process
+/// id into the lock file so a run that gives up can say who it waited for.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    _file: std::fs::File,
+}
+
+impl FileLock {
+    /// Take the lock, waiting up to `timeout` for another run to release it
+    pub async fn acquire(
+        path: &Path,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).await?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Failed to open lock file {}: {}", path.display(), e))?;
+
+        let started = Instant::now();
+        while !try_lock(&file)? {
+            if started.elapsed() >= timeout {
+                let holder = std::fs::read_to_string(path)
+                    .ok()
+                    .and_then(|pid| pid.trim().parse::<u32>().ok())
+                    .map(|pid| format!(" (pid {})", pid))
+                    .unwrap_or_default();
+                return Err(format!(
+                    "{} is locked by another ddns_updater run{}; gave up after {}s",
+                    path.display(),
+                    holder,
+                    timeout.as_secs()
+                )
+                .into());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let mut file = file;
+        record_holder(&mut file);
+        Ok(Self {
+            path: path.to_path_buf(),
+            _file: file,
+        })
+    }
+
+    /// Lock of a config file, kept in `lock_dir` rather than next to the config, where a
+    /// web server including `*` would pick it up
+    pub async fn for_config(
+        lock_dir: &Path,
+        config_path: &Path,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let config_path = fs::canonicalize(config_path)
+            .await
+            .unwrap_or_else(|_| config_path.to_path_buf());
+        let name = config_path
+            .to_string_lossy()
+            .replace('%', "%25")
+            .replace('/', "%2F");
+        Self::acquire(&lock_dir.join(format!("{}.lock", name)), timeout).await
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Exclusive lock without blocking; false while another open file holds it
+#[cfg(unix)]
+fn try_lock(file: &std::fs::File) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    use std::os::unix::io::AsRawFd;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let error = std::io::Error::last_os_error();
+    if error.kind() == std::io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(error.into())
+    }
+}
+
+/// Platforms without `flock` run unlocked
+#[cfg(not(unix))]
+fn try_lock(_file: &std::fs::File) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(true)
+}
+
+/// Replace the lock file's content with our process id; purely informational
+fn record_holder(file: &mut std::fs::File) {
+    use std::io::{Seek, Write};
+    let _ = file.set_len(0);
+    let _ = file.rewind();
+    let _ = write!(file, "{}", std::process::id());
+}
+
+#[cfg(test)]
+#[path = "files_tests.rs"]
+mod tests;
@@ -0,0 +1,75 @@
+use super::*;
+use tempfile::tempdir;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_write_atomically_keeps_mode_and_symlinks() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("site.conf");
+    let link = dir.path().join("enabled.conf");
+    std::fs::write(&target, "allow 203.0.113.7;\n").unwrap();
+    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    write_atomically(&link, "allow 203.0.113.8;\n")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&target).unwrap(),
+        "allow 203.0.113.8;\n"
+    );
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+    // No temp file is left behind
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_lock_is_exclusive_until_dropped() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("storage.lock");
+
+    let lock = FileLock::acquire(&path, LOCK_TIMEOUT).await.unwrap();
+    let error = FileLock::acquire(&path, Duration::from_millis(200))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("locked by another ddns_updater run"));
+    assert!(error.contains(&format!("pid {}", std::process::id())));
+
+    drop(lock);
+    FileLock::acquire(&path, Duration::from_millis(200))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_config_locks_live_in_the_lock_dir() {
+    let dir = tempdir().unwrap();
+    let config = dir.path().join("sites").join("site.conf");
+    std::fs::create_dir(dir.path().join("sites")).unwrap();
+    std::fs::write(&config, "").unwrap();
+    let lock_dir = dir.path().join("locks");
+
+    let lock = FileLock::for_config(&lock_dir, &config, LOCK_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(lock.path().parent(), Some(lock_dir.as_path()));
+    assert!(lock
+        .path()
+        .to_string_lossy()
+        .ends_with("%2Fsites%2Fsite.conf.lock"));
+    // Nothing is added next to the config file
+    assert_eq!(
+        std::fs::read_dir(dir.path().join("sites")).unwrap().count(),
+        1
+    );
+}
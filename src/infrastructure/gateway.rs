@@ -0,0 +1,426 @@
+use crate::domain::entities::GatewayProtocol;
+use crate::domain::ports::{DnsResolver, NetworkService};
+use crate::infrastructure::resolvers::SystemResolver;
+use async_trait::async_trait;
+use regex::Regex;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Port NAT-PMP and PCP servers listen on
+const NAT_PMP_PORT: u16 = 5351;
+/// SSDP multicast group and port used to discover UPnP devices
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const IGD_SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+const NAT_PMP_RESPONSE: u8 = 128;
+const PCP_VERSION: u8 = 2;
+const PCP_OPCODE_MAP: u8 = 1;
+const PCP_RESPONSE: u8 = 0x80;
+/// Lifetime of the short-lived mapping PCP needs to report the external address
+const PCP_MAPPING_LIFETIME: u32 = 30;
+const PROTOCOL_UDP: u8 = 17;
+
+/// Network service asking the local router for its external address (NAT-PMP, PCP or UPnP
+/// IGD), so no third-party IP echo service is involved
+pub struct GatewayNetworkService {
+    gateway: Option<IpAddr>,
+    protocols: Vec<GatewayProtocol>,
+    port: u16,
+    ssdp_address: SocketAddr,
+    timeout: Duration,
+    client: reqwest::Client,
+    resolver: Arc<dyn DnsResolver>,
+}
+
+impl GatewayNetworkService {
+    /// Gateway at the given address, or the default route's gateway if `None`
+    pub fn new(gateway: Option<IpAddr>) -> Self {
+        Self {
+            gateway,
+            protocols: GatewayProtocol::ALL.to_vec(),
+            port: NAT_PMP_PORT,
+            ssdp_address: SSDP_ADDRESS.parse().expect("valid SSDP address"),
+            timeout: Duration::from_secs(3),
+            client: reqwest::Client::new(),
+            resolver: Arc::new(SystemResolver),
+        }
+    }
+
+    /// Protocols to try, in order; empty means all
+    pub fn with_protocols(mut self, protocols: Vec<GatewayProtocol>) -> Self {
+        if !protocols.is_empty() {
+            self.protocols = protocols;
+        }
+        self
+    }
+
+    /// Port of the NAT-PMP/PCP server (5351 unless testing against a fake gateway)
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Where SSDP searches are sent (the multicast group unless testing)
+    pub fn with_ssdp_address(mut self, address: SocketAddr) -> Self {
+        self.ssdp_address = address;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Resolve hostnames with the given resolver instead of the system one
+    pub fn with_resolver(mut self, resolver: Arc<dyn DnsResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    fn gateway_address(&self) -> Result<SocketAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let gateway = self
+            .gateway
+            .or_else(|| default_gateway().map(IpAddr::V4))
+            .ok_or("No gateway configured and no default route found")?;
+        Ok(SocketAddr::new(gateway, self.port))
+    }
+
+    async fn external_address(
+        &self,
+        protocol: GatewayProtocol,
+    ) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        match protocol {
+            GatewayProtocol::NatPmp => self.nat_pmp_external_address().await,
+            GatewayProtocol::Pcp => self.pcp_external_address().await,
+            GatewayProtocol::Upnp => self.upnp_external_address().await,
+        }
+    }
+
+    /// NAT-PMP (RFC 6886) public address request, opcode 0
+    async fn nat_pmp_external_address(
+        &self,
+    ) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.exchange(self.gateway_address()?, &[0, 0]).await?;
+        if response.len() < 12 || response[0] != 0 || response[1] != NAT_PMP_RESPONSE {
+            return Err("Malformed NAT-PMP response".into());
+        }
+        let result = u16::from_be_bytes([response[2], response[3]]);
+        if result != 0 {
+            return Err(format!("NAT-PMP request failed with result code {}", result).into());
+        }
+        Ok(IpAddr::V4(Ipv4Addr::new(
+            response[8],
+            response[9],
+            response[10],
+            response[11],
+        )))
+    }
+
+    /// PCP (RFC 6887) has no address query, so this asks for a short-lived UDP mapping of an
+    /// unused port and reads the assigned external address, then deletes the mapping
+    async fn pcp_external_address(
+        &self,
+    ) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let gateway = self.gateway_address()?;
+        let socket = bind_for(gateway).await?;
+        socket.connect(gateway).await?;
+        let client = socket.local_addr()?;
+        let nonce = pcp_nonce();
+
+        let request = pcp_map_request(client, nonce, PCP_MAPPING_LIFETIME);
+        let response = self.exchange_on(&socket, &request).await?;
+        let external = parse_pcp_map_response(&response, nonce)?;
+
+        let delete = pcp_map_request(client, nonce, 0);
+        if let Err(e) = self.exchange_on(&socket, &delete).await {
+            eprintln!("DEBUG: Could not delete PCP mapping: {}", e);
+        }
+        Ok(external)
+    }
+
+    /// UPnP IGD: SSDP search, device description, then `GetExternalIPAddress` over SOAP
+    async fn upnp_external_address(
+        &self,
+    ) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let location = self.ssdp_search().await?;
+        eprintln!("DEBUG: UPnP gateway description at {}", location);
+
+        let description = self
+            .client
+            .get(location.clone())
+            .timeout(self.timeout)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let (service_type, control_url) = find_wan_service(&description)
+            .ok_or("UPnP gateway has no WANIPConnection or WANPPPConnection service")?;
+        let control_url = location.join(&control_url)?;
+
+        let body = format!(
+            concat!(
+                "<?xml version=\"1.0\"?>\r\n",
+                "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" ",
+                "s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">",
+                "<s:Body><u:GetExternalIPAddress xmlns:u=\"{}\"/></s:Body></s:Envelope>\r\n"
+            ),
+            service_type
+        );
+        let response = self
+            .client
+            .post(control_url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header(
+                "SOAPAction",
+                format!("\"{}#GetExternalIPAddress\"", service_type),
+            )
+            .timeout(self.timeout)
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("UPnP GetExternalIPAddress failed: {}", response.status()).into());
+        }
+
+        let text = response.text().await?;
+        let address = xml_values(&text, "NewExternalIPAddress")
+            .into_iter()
+            .next()
+            .ok_or("UPnP response has no NewExternalIPAddress")?;
+        Ok(address.trim().parse()?)
+    }
+
+    /// Location URL of the first Internet Gateway Device answering an SSDP search
+    async fn ssdp_search(&self) -> Result<url::Url, Box<dyn std::error::Error + Send + Sync>> {
+        let socket = bind_for(self.ssdp_address).await?;
+        let request = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+            SSDP_ADDRESS, IGD_SEARCH_TARGET
+        );
+        socket
+            .send_to(request.as_bytes(), self.ssdp_address)
+            .await?;
+
+        let search = async {
+            let mut buffer = vec![0u8; 2048];
+            loop {
+                let (len, _) = socket.recv_from(&mut buffer).await?;
+                let response = String::from_utf8_lossy(&buffer[..len]);
+                if let Some(location) = header_value(&response, "location") {
+                    return Ok::<_, Box<dyn std::error::Error + Send + Sync>>(url::Url::parse(
+                        location,
+                    )?);
+                }
+            }
+        };
+        tokio::time::timeout(self.timeout, search)
+            .await
+            .map_err(|_| "No UPnP gateway answered the SSDP search")?
+    }
+
+    async fn exchange(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let socket = bind_for(server).await?;
+        socket.connect(server).await?;
+        self.exchange_on(&socket, request).await
+    }
+
+    /// Send a request on a connected socket, resending it (as RFC 6886 asks) until answered
+    async fn exchange_on(
+        &self,
+        socket: &UdpSocket,
+        request: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut buffer = vec![0u8; 1100];
+        let mut wait = Duration::from_millis(250);
+        let deadline = tokio::time::Instant::now() + self.timeout;
+
+        while tokio::time::Instant::now() < deadline {
+            socket.send(request).await?;
+            match tokio::time::timeout(wait, socket.recv(&mut buffer)).await {
+                Ok(Ok(len)) => return Ok(buffer[..len].to_vec()),
+                Ok(Err(e)) => return Err(Box::new(e)),
+                Err(_) => wait *= 2,
+            }
+        }
+        Err("Gateway did not answer".into())
+    }
+}
+
+#[async_trait]
+impl NetworkService for GatewayNetworkService {
+    async fn get_public_ip(&self) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> =
+            "No gateway protocols configured".into();
+        for &protocol in &self.protocols {
+            match self.external_address(protocol).await {
+                Ok(ip) => {
+                    eprintln!("DEBUG: External address from {}: {}", protocol, ip);
+                    return Ok(ip);
+                }
+                Err(e) => {
+                    eprintln!("DEBUG: {} failed: {}", protocol, e);
+                    last_error = format!("{}: {}", protocol, e).into();
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn resolve_hostname(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        self.resolver.resolve(hostname).await
+    }
+
+    async fn is_reachable(
+        &self,
+        ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let connect = tokio::net::TcpStream::connect((ip, 80));
+        match tokio::time::timeout(Duration::from_secs(5), connect).await {
+            Ok(Ok(_)) => Ok(true),
+            _ => Ok(false),
+        }
+    }
+}
+
+async fn bind_for(server: SocketAddr) -> std::io::Result<UdpSocket> {
+    let local: SocketAddr = if server.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    UdpSocket::bind(local).await
+}
+
+/// Gateway of the IPv4 default route, from /proc/net/route
+fn default_gateway() -> Option<Ipv4Addr> {
+    std::fs::read_to_string("/proc/net/route")
+        .ok()
+        .and_then(|content| parse_default_gateway(&content))
+}
+
+/// The kernel prints addresses as hex of their in-memory (network order) bytes
+fn parse_default_gateway(content: &str) -> Option<Ipv4Addr> {
+    content.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        (gateway != 0).then(|| Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+fn pcp_nonce() -> [u8; 12] {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let mut nonce = [0u8; 12];
+    nonce.copy_from_slice(&nanos.to_be_bytes()[4..]);
+    nonce
+}
+
+/// PCP MAP request for the UDP port of `client`, letting the gateway pick the external side
+fn pcp_map_request(client: SocketAddr, nonce: [u8; 12], lifetime: u32) -> Vec<u8> {
+    let mut request = Vec::with_capacity(60);
+    request.extend_from_slice(&[PCP_VERSION, PCP_OPCODE_MAP, 0, 0]);
+    request.extend_from_slice(&lifetime.to_be_bytes());
+    request.extend_from_slice(&pcp_address(client.ip()));
+    request.extend_from_slice(&nonce);
+    request.extend_from_slice(&[PROTOCOL_UDP, 0, 0, 0]);
+    request.extend_from_slice(&client.port().to_be_bytes());
+    request.extend_from_slice(&[0, 0]); // suggested external port: any
+    request.extend_from_slice(&pcp_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED)));
+    request
+}
+
+/// Assigned external address of a PCP MAP response
+fn parse_pcp_map_response(
+    response: &[u8],
+    nonce: [u8; 12],
+) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+    if response.len() >= 4 && response[0] != PCP_VERSION {
+        // A NAT-PMP only gateway answers with its own version
+        return Err(format!("Gateway speaks version {}, not PCP", response[0]).into());
+    }
+    if response.len() < 60 || response[1] != PCP_RESPONSE | PCP_OPCODE_MAP {
+        return Err("Malformed PCP response".into());
+    }
+    if response[3] != 0 {
+        return Err(format!("PCP request failed with result code {}", response[3]).into());
+    }
+    if response[24..36] != nonce {
+        return Err("PCP response does not match the request".into());
+    }
+
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(&response[44..60]);
+    let address = Ipv6Addr::from(octets);
+    Ok(match address.to_ipv4_mapped() {
+        Some(ip) => IpAddr::V4(ip),
+        None => IpAddr::V6(address),
+    })
+}
+
+/// Addresses are always 16 bytes in PCP, IPv4 as IPv4-mapped IPv6
+fn pcp_address(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+/// Value of an HTTP-style header, by case-insensitive name
+fn header_value<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    response.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Text content of all elements with the given local name, ignoring namespace prefixes
+fn xml_values<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let pattern = format!(r"(?s)<(?:[\w-]+:)?{0}\b[^>]*>(.*?)</(?:[\w-]+:)?{0}>", tag);
+    let Ok(regex) = Regex::new(&pattern) else {
+        return Vec::new();
+    };
+    regex
+        .captures_iter(xml)
+        .filter_map(|captures| captures.get(1).map(|m| m.as_str()))
+        .collect()
+}
+
+/// Service type and control URL of the WAN connection service in a device description
+fn find_wan_service(description: &str) -> Option<(String, String)> {
+    xml_values(description, "service")
+        .into_iter()
+        .find_map(|service| {
+            let service_type = xml_values(service, "serviceType")
+                .into_iter()
+                .next()?
+                .trim();
+            if !service_type.contains(":WANIPConnection:")
+                && !service_type.contains(":WANPPPConnection:")
+            {
+                return None;
+            }
+            let control_url = xml_values(service, "controlURL").into_iter().next()?.trim();
+            Some((service_type.to_string(), control_url.to_string()))
+        })
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "gateway_tests.rs"]
+mod tests;
@@ -0,0 +1,261 @@
+use super::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Fake NAT-PMP/PCP gateway on 127.0.0.1 answering each request with `respond`; returns its port
+async fn fake_gateway(respond: fn(&[u8]) -> Option<Vec<u8>>) -> u16 {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = socket.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buffer = vec![0u8; 1100];
+        while let Ok((len, peer)) = socket.recv_from(&mut buffer).await {
+            if let Some(response) = respond(&buffer[..len]) {
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        }
+    });
+    port
+}
+
+fn nat_pmp_response(request: &[u8]) -> Option<Vec<u8>> {
+    if request != [0, 0] {
+        return None;
+    }
+    let mut response = vec![0, NAT_PMP_RESPONSE, 0, 0];
+    response.extend_from_slice(&1234u32.to_be_bytes());
+    response.extend_from_slice(&[203, 0, 113, 7]);
+    Some(response)
+}
+
+/// Answers MAP requests like a PCP server: echoes the request, assigns 198.51.100.9
+fn pcp_response(request: &[u8]) -> Option<Vec<u8>> {
+    if request.len() != 60 || request[0] != PCP_VERSION {
+        // NAT-PMP servers answer unknown versions with result 1 (unsupported version)
+        return Some(vec![0, request[1] | NAT_PMP_RESPONSE, 0, 1, 0, 0, 0, 0]);
+    }
+    let mut response = vec![PCP_VERSION, PCP_RESPONSE | request[1], 0, 0];
+    response.extend_from_slice(&request[4..8]);
+    response.extend_from_slice(&[0u8; 16]);
+    response.extend_from_slice(&request[24..44]);
+    response.extend_from_slice(&Ipv4Addr::new(198, 51, 100, 9).to_ipv6_mapped().octets());
+    Some(response)
+}
+
+/// Fake UPnP IGD: answers SSDP searches on UDP and serves the description and control URL over
+/// HTTP on the same port of 127.0.0.1; returns the SSDP address
+async fn fake_igd() -> SocketAddr {
+    let (udp, http) = loop {
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        if let Ok(http) = TcpListener::bind(udp.local_addr().unwrap()).await {
+            break (udp, http);
+        }
+    };
+    let addr = udp.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = vec![0u8; 2048];
+        while let Ok((len, peer)) = udp.recv_from(&mut buffer).await {
+            let request = String::from_utf8_lossy(&buffer[..len]).to_string();
+            assert!(request.starts_with("M-SEARCH * HTTP/1.1\r\n"));
+            assert!(request.contains(IGD_SEARCH_TARGET));
+            // Unrelated devices answer too
+            let other = "HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\n\r\n";
+            udp.send_to(other.as_bytes(), peer).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nST: {}\r\nLocation: http://{}/igd/desc.xml\r\n\r\n",
+                IGD_SEARCH_TARGET, addr
+            );
+            udp.send_to(response.as_bytes(), peer).await.unwrap();
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = http.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            let head_end = loop {
+                let len = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..len]);
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&request[..head_end]).to_lowercase();
+            let body_len: usize = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length: "))
+                .map(|value| value.trim().parse().unwrap())
+                .unwrap_or(0);
+            while request.len() < head_end + body_len {
+                let len = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..len]);
+            }
+            let body = String::from_utf8_lossy(&request[head_end..]);
+
+            let response_body = if head.starts_with("get /igd/desc.xml") {
+                DESCRIPTION.to_string()
+            } else {
+                assert!(head.starts_with("post /igd/ctl/ip"));
+                assert!(head.contains(
+                    "soapaction: \"urn:schemas-upnp-org:service:wanipconnection:1#getexternalipaddress\""
+                ));
+                assert!(body.contains("<u:GetExternalIPAddress"));
+                SOAP_RESPONSE.to_string()
+            };
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response_body.len(),
+                response_body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    addr
+}
+
+const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+        <controlURL>/igd/ctl/l3f</controlURL>
+      </service>
+    </serviceList>
+    <deviceList><device><deviceList><device>
+      <serviceList>
+        <service>
+          <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+          <serviceId>urn:upnp-org:serviceId:WANIPConn1</serviceId>
+          <controlURL>ctl/ip</controlURL>
+        </service>
+      </serviceList>
+    </device></deviceList></device></deviceList>
+  </device>
+</root>"#;
+
+const SOAP_RESPONSE: &str = r#"<?xml version="1.0"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+  <s:Body>
+    <u:GetExternalIPAddressResponse xmlns:u="urn:schemas-upnp-org:service:WANIPConnection:1">
+      <NewExternalIPAddress>192.0.2.44</NewExternalIPAddress>
+    </u:GetExternalIPAddressResponse>
+  </s:Body>
+</s:Envelope>"#;
+
+fn service(port: u16, protocols: Vec<GatewayProtocol>) -> GatewayNetworkService {
+    GatewayNetworkService::new(Some(LOCALHOST))
+        .with_port(port)
+        .with_protocols(protocols)
+        .with_timeout(Duration::from_secs(1))
+}
+
+#[tokio::test]
+async fn test_nat_pmp_external_address() {
+    let port = fake_gateway(nat_pmp_response).await;
+    let ip = service(port, vec![GatewayProtocol::NatPmp])
+        .get_public_ip()
+        .await
+        .unwrap();
+    assert_eq!(ip, "203.0.113.7".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_nat_pmp_error_result() {
+    let port =
+        fake_gateway(|_| Some(vec![0, NAT_PMP_RESPONSE, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0])).await;
+    let error = service(port, vec![GatewayProtocol::NatPmp])
+        .get_public_ip()
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("result code 3"));
+}
+
+#[tokio::test]
+async fn test_pcp_external_address() {
+    let port = fake_gateway(pcp_response).await;
+    let ip = service(port, vec![GatewayProtocol::Pcp])
+        .get_public_ip()
+        .await
+        .unwrap();
+    assert_eq!(ip, "198.51.100.9".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_pcp_on_a_nat_pmp_only_gateway_falls_back() {
+    // Only understands NAT-PMP, and rejects PCP with its version
+    let port = fake_gateway(|request| {
+        if request[0] == PCP_VERSION {
+            return Some(vec![0, request[1] | NAT_PMP_RESPONSE, 0, 1, 0, 0, 0, 0]);
+        }
+        nat_pmp_response(request)
+    })
+    .await;
+
+    let ip = service(port, vec![GatewayProtocol::Pcp, GatewayProtocol::NatPmp])
+        .get_public_ip()
+        .await
+        .unwrap();
+    assert_eq!(ip, "203.0.113.7".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_upnp_external_address() {
+    let ssdp = fake_igd().await;
+    let ip = GatewayNetworkService::new(None)
+        .with_protocols(vec![GatewayProtocol::Upnp])
+        .with_ssdp_address(ssdp)
+        .with_timeout(Duration::from_secs(2))
+        .get_public_ip()
+        .await
+        .unwrap();
+    assert_eq!(ip, "192.0.2.44".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_unanswered_gateway() {
+    // Bound but silent
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = silent.local_addr().unwrap().port();
+
+    let error = service(port, vec![GatewayProtocol::NatPmp])
+        .get_public_ip()
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("NAT-PMP"));
+}
+
+#[test]
+fn test_parse_default_gateway() {
+    let content = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+        eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+        eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n";
+    assert_eq!(
+        parse_default_gateway(content),
+        Some(Ipv4Addr::new(192, 168, 1, 1))
+    );
+    assert_eq!(parse_default_gateway("Iface\tDestination\tGateway\n"), None);
+}
+
+#[test]
+fn test_find_wan_service() {
+    assert_eq!(
+        find_wan_service(DESCRIPTION),
+        Some((
+            "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+            "ctl/ip".to_string()
+        ))
+    );
+    assert_eq!(
+        xml_values(SOAP_RESPONSE, "NewExternalIPAddress"),
+        vec!["192.0.2.44"]
+    );
+    assert_eq!(
+        header_value("HTTP/1.1 200 OK\r\nLOCATION: http://x/\r\n", "location"),
+        Some("http://x/")
+    );
+}
@@ -0,0 +1,275 @@
+use crate::domain::entities::IpFamily;
+use crate::domain::ports::{DnsResolver, NetworkService};
+use crate::infrastructure::resolvers::SystemResolver;
+use async_trait::async_trait;
+use futures::stream::{StreamExt, TryStreamExt};
+use netlink_packet_core::NetlinkPayload;
+use netlink_packet_route::address::Nla as AddressNla;
+use netlink_packet_route::link::nlas::Nla as LinkNla;
+use netlink_packet_route::{
+    AddressMessage, RtnlMessage, IFA_F_DADFAILED, IFA_F_DEPRECATED, IFA_F_TEMPORARY,
+    IFA_F_TENTATIVE,
+};
+use rtnetlink::constants::{RTMGRP_IPV4_IFADDR, RTMGRP_IPV6_IFADDR};
+use rtnetlink::Handle;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Network service reading the public address from a local interface (e.g. `ppp0` on a router)
+/// over rtnetlink, without any outbound request
+pub struct InterfaceNetworkService {
+    interface: String,
+    resolver: Arc<dyn DnsResolver>,
+}
+
+impl InterfaceNetworkService {
+    pub fn new(interface: String) -> Self {
+        Self {
+            interface,
+            resolver: Arc::new(SystemResolver),
+        }
+    }
+
+    /// Resolve hostnames with the given resolver instead of the system one
+    pub fn with_resolver(mut self, resolver: Arc<dyn DnsResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// All addresses currently assigned to the interface
+    async fn interface_addresses(
+        &self,
+    ) -> Result<Vec<InterfaceAddress>, Box<dyn std::error::Error + Send + Sync>> {
+        let (connection, handle, _) = rtnetlink::new_connection()?;
+        let connection = tokio::spawn(connection);
+
+        let result = async {
+            let index = link_index(&handle, &self.interface)
+                .await?
+                .ok_or_else(|| format!("Network interface {} not found", self.interface))?;
+            let mut messages = handle
+                .address()
+                .get()
+                .set_link_index_filter(index)
+                .execute();
+            let mut addresses = Vec::new();
+            while let Some(message) = messages.try_next().await? {
+                addresses.extend(InterfaceAddress::from_message(&message));
+            }
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(addresses)
+        }
+        .await;
+
+        connection.abort();
+        result
+    }
+
+    /// Notifications for every address added to or removed from the interface, so callers can
+    /// react to a new address instead of polling; the channel closes if the socket fails
+    pub fn watch_address_changes(
+        &self,
+    ) -> Result<mpsc::UnboundedReceiver<()>, Box<dyn std::error::Error + Send + Sync>> {
+        use netlink_sys::{AsyncSocket, SocketAddr};
+
+        let (mut connection, handle, mut messages) = rtnetlink::new_connection()?;
+        connection
+            .socket_mut()
+            .socket_mut()
+            .bind(&SocketAddr::new(0, RTMGRP_IPV4_IFADDR | RTMGRP_IPV6_IFADDR))?;
+        tokio::spawn(connection);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let interface = self.interface.clone();
+        tokio::spawn(async move {
+            while let Some((message, _)) = messages.next().await {
+                let index = match message.payload {
+                    NetlinkPayload::InnerMessage(RtnlMessage::NewAddress(address))
+                    | NetlinkPayload::InnerMessage(RtnlMessage::DelAddress(address)) => {
+                        address.header.index
+                    }
+                    _ => continue,
+                };
+                // The index of a PPP interface changes when it reconnects, so compare names
+                let name = link_name(&handle, index).await.unwrap_or_default();
+                if name.as_deref() == Some(interface.as_str()) && sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(receiver)
+    }
+}
+
+#[async_trait]
+impl NetworkService for InterfaceNetworkService {
+    async fn get_public_ip(&self) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let ips = self.get_public_ips().await?;
+        Ok(ips[0])
+    }
+
+    async fn get_public_ips(
+        &self,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let addresses = self.interface_addresses().await?;
+        eprintln!(
+            "DEBUG: Addresses on {}: {:?}",
+            self.interface,
+            addresses.iter().map(|a| a.ip).collect::<Vec<_>>()
+        );
+
+        let ips = select_global_addresses(&addresses);
+        if ips.is_empty() {
+            return Err(
+                format!("No global address on network interface {}", self.interface).into(),
+            );
+        }
+        Ok(ips)
+    }
+
+    async fn resolve_hostname(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        self.resolver.resolve(hostname).await
+    }
+
+    async fn is_reachable(
+        &self,
+        ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        // Plain TCP connect, as there may be no HTTP access from the router
+        let connect = tokio::net::TcpStream::connect((ip, 80));
+        match tokio::time::timeout(std::time::Duration::from_secs(5), connect).await {
+            Ok(Ok(_)) => Ok(true),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// An address of the interface with its `IFA_F_*` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InterfaceAddress {
+    ip: IpAddr,
+    flags: u32,
+}
+
+impl InterfaceAddress {
+    fn from_message(message: &AddressMessage) -> Option<Self> {
+        let mut local = None;
+        let mut address = None;
+        let mut flags = message.header.flags as u32;
+        for nla in &message.nlas {
+            match nla {
+                AddressNla::Local(bytes) => local = parse_address(bytes),
+                AddressNla::Address(bytes) => address = parse_address(bytes),
+                // The 32-bit attribute supersedes the 8 bits of the header
+                AddressNla::Flags(value) => flags = *value,
+                _ => {}
+            }
+        }
+        // On point-to-point links IFA_ADDRESS is the peer, IFA_LOCAL our own address
+        Some(Self {
+            ip: local.or(address)?,
+            flags,
+        })
+    }
+
+    /// Whether the address is usable and not about to go away
+    fn is_preferred(&self) -> bool {
+        self.flags & (IFA_F_DEPRECATED | IFA_F_TENTATIVE | IFA_F_DADFAILED) == 0
+    }
+
+    /// Privacy extension addresses (RFC 8981) rotate daily, so a stable address is preferred
+    fn is_temporary(&self) -> bool {
+        self.flags & IFA_F_TEMPORARY != 0
+    }
+}
+
+fn parse_address(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::new(
+            bytes[0], bytes[1], bytes[2], bytes[3],
+        ))),
+        16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(bytes);
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+/// Whether an address can be reached from the internet: no loopback, link-local, private,
+/// shared (CGNAT), unique local or multicast addresses
+fn is_global(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_private()
+                || ip.is_multicast()
+                || ip.is_broadcast()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (first & 0xffc0) == 0xfe80 // link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00 // unique local fc00::/7
+                || ip.to_ipv4_mapped().is_some())
+        }
+    }
+}
+
+/// The global address to use per family, IPv4 first: preferred over deprecated or tentative
+/// addresses, stable over temporary ones, otherwise in the kernel's order
+fn select_global_addresses(addresses: &[InterfaceAddress]) -> Vec<IpAddr> {
+    [IpFamily::V4, IpFamily::V6]
+        .into_iter()
+        .filter_map(|family| {
+            addresses
+                .iter()
+                .filter(|address| IpFamily::of(address.ip) == family && is_global(address.ip))
+                .min_by_key(|address| (!address.is_preferred(), address.is_temporary()))
+                .map(|address| address.ip)
+        })
+        .collect()
+}
+
+async fn link_index(
+    handle: &Handle,
+    name: &str,
+) -> Result<Option<u32>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut links = handle.link().get().match_name(name.to_string()).execute();
+    match links.try_next().await {
+        Ok(link) => Ok(link.map(|link| link.header.index)),
+        // The kernel answers ENODEV for unknown names
+        Err(rtnetlink::Error::NetlinkError(e)) if e.raw_code() == -19 => Ok(None),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+async fn link_name(
+    handle: &Handle,
+    index: u32,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut links = handle.link().get().match_index(index).execute();
+    let Some(link) = links.try_next().await? else {
+        return Ok(None);
+    };
+    Ok(link.nlas.into_iter().find_map(|nla| match nla {
+        LinkNla::IfName(name) => Some(name),
+        _ => None,
+    }))
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "interfaces_tests.rs"]
+mod tests;
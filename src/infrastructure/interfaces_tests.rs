@@ -0,0 +1,85 @@
+use super::*;
+use netlink_packet_route::IFA_F_PERMANENT;
+
+fn address(ip: &str, flags: u32) -> InterfaceAddress {
+    InterfaceAddress {
+        ip: ip.parse().unwrap(),
+        flags,
+    }
+}
+
+fn ips(values: &[&str]) -> Vec<IpAddr> {
+    values.iter().map(|v| v.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_only_global_addresses_are_candidates() {
+    for local in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.10.1",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fe80::1",
+        "fd12:3456::1",
+        "ff02::1",
+        "::ffff:203.0.113.7",
+    ] {
+        assert!(
+            !is_global(local.parse().unwrap()),
+            "{} is not global",
+            local
+        );
+    }
+    for global in ["203.0.113.7", "100.128.0.1", "2001:db8::7", "2a01:4f8::1"] {
+        assert!(is_global(global.parse().unwrap()), "{} is global", global);
+    }
+}
+
+#[test]
+fn test_selects_one_stable_global_address_per_family() {
+    let addresses = [
+        address("fe80::1", IFA_F_PERMANENT),
+        address("2001:db8::aaaa", IFA_F_TEMPORARY),
+        address("2001:db8::1", IFA_F_DEPRECATED),
+        address("2001:db8::7", 0),
+        address("192.168.1.1", IFA_F_PERMANENT),
+        address("203.0.113.7", IFA_F_PERMANENT),
+    ];
+    assert_eq!(
+        select_global_addresses(&addresses),
+        ips(&["203.0.113.7", "2001:db8::7"])
+    );
+
+    // A temporary address is still better than none, a deprecated one is the last resort
+    let addresses = [
+        address("2001:db8::1", IFA_F_DEPRECATED),
+        address("2001:db8::aaaa", IFA_F_TEMPORARY),
+    ];
+    assert_eq!(
+        select_global_addresses(&addresses),
+        ips(&["2001:db8::aaaa"])
+    );
+
+    assert!(select_global_addresses(&[address("10.0.0.1", 0)]).is_empty());
+}
+
+#[tokio::test]
+async fn test_reads_loopback_addresses_over_netlink() {
+    let service = InterfaceNetworkService::new("lo".to_string());
+    let addresses = service.interface_addresses().await.unwrap();
+    assert!(addresses
+        .iter()
+        .any(|a| a.ip == "127.0.0.1".parse::<IpAddr>().unwrap()));
+
+    // Loopback addresses are never the public IP
+    let error = service.get_public_ips().await.unwrap_err();
+    assert!(error.to_string().contains("No global address"));
+
+    let missing = InterfaceNetworkService::new("ddns-test-none".to_string());
+    let error = missing.get_public_ip().await.unwrap_err();
+    assert!(error.to_string().contains("not found"));
+}
@@ -1,11 +1,24 @@
 pub mod config_discovery;
+mod dns;
+pub mod files;
+pub mod gateway;
+#[cfg(target_os = "linux")]
+pub mod interfaces;
 pub mod network;
 pub mod notifications;
 pub mod repositories;
+pub mod resolvers;
+pub mod sqlite_repository;
 pub mod webservers;
 
 pub use config_discovery::*;
+pub use files::FileLock;
+pub use gateway::*;
+#[cfg(target_os = "linux")]
+pub use interfaces::*;
 pub use network::*;
 pub use notifications::*;
 pub use repositories::*;
+pub use resolvers::*;
+pub use sqlite_repository::*;
 pub use webservers::*;
@@ -1,76 +1,265 @@
-use crate::domain::ports::NetworkService;
+use crate::domain::entities::{IpFamily, PublicIpConfig};
+use crate::domain::ports::{DnsResolver, NetworkService};
+use crate::domain::value_objects::IpSource;
+use crate::infrastructure::resolvers::SystemResolver;
 use async_trait::async_trait;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
 
 /// HTTP-based network service implementation
 pub struct HttpNetworkService {
     client: reqwest::Client,
+    /// Clients bound to one address family, so dual-stack sources report that family's address
+    client_v4: reqwest::Client,
+    client_v6: reqwest::Client,
+    resolver: Arc<dyn DnsResolver>,
+    public_ip: PublicIpConfig,
 }
 
 impl HttpNetworkService {
     pub fn new() -> Self {
         Self {
             client: reqwest::Client::new(),
+            client_v4: bound_client(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            client_v6: bound_client(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+            resolver: Arc::new(SystemResolver),
+            public_ip: PublicIpConfig::default(),
         }
     }
+
+    /// Resolve hostnames with the given resolver instead of the system one
+    pub fn with_resolver(mut self, resolver: Arc<dyn DnsResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Detect the public IP with the given sources and quorum instead of the defaults
+    pub fn with_public_ip_config(mut self, public_ip: PublicIpConfig) -> Self {
+        self.public_ip = public_ip;
+        self
+    }
+
+    /// Families with sources pinned to them; empty if all sources serve either family
+    fn pinned_families(&self) -> Vec<IpFamily> {
+        [IpFamily::V4, IpFamily::V6]
+            .into_iter()
+            .filter(|&family| {
+                self.public_ip
+                    .sources
+                    .iter()
+                    .any(|source| source.family == Some(family))
+            })
+            .collect()
+    }
+
+    /// Detect the public address of one family (or of any, if `None`) from the configured sources
+    async fn detect_public_ip(
+        &self,
+        family: Option<IpFamily>,
+    ) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+        let sources: Vec<IpSource> = self
+            .public_ip
+            .sources
+            .iter()
+            .filter(|source| family.is_none_or(|family| source.serves(family)))
+            .cloned()
+            .collect();
+        let family_name = family.map_or("public IP".to_string(), |f| format!("{} address", f));
+        if sources.is_empty() {
+            return Err(format!("No sources configured to detect the {}", family_name).into());
+        }
+        let client = match family {
+            Some(IpFamily::V4) => self.client_v4.clone(),
+            Some(IpFamily::V6) => self.client_v6.clone(),
+            None => self.client.clone(),
+        };
+
+        let Some(quorum) = self.public_ip.quorum else {
+            // First valid answer wins
+            let mut last_error = None;
+            for source in &sources {
+                match fetch_public_ip(&client, source, family).await {
+                    Ok(ip) => return Ok(ip),
+                    Err(e) => {
+                        eprintln!("DEBUG: IP source {} failed: {}", source, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+            return Err(last_error.unwrap_or_else(|| {
+                format!("Failed to detect the {} from all sources", family_name).into()
+            }));
+        };
+
+        if quorum == 0 || quorum > sources.len() {
+            return Err(format!(
+                "Quorum of {} cannot be reached with {} sources for the {}",
+                quorum,
+                sources.len(),
+                family_name
+            )
+            .into());
+        }
+
+        // Ask all sources at once and count the votes for each address
+        let mut requests = tokio::task::JoinSet::new();
+        for source in sources.iter().cloned() {
+            let client = client.clone();
+            requests.spawn(async move {
+                let result = fetch_public_ip(&client, &source, family).await;
+                (source, result)
+            });
+        }
+        let mut votes: Vec<(IpAddr, usize)> = Vec::new();
+        while let Some(joined) = requests.join_next().await {
+            let (source, result) = joined?;
+            match result {
+                Ok(ip) => match votes.iter_mut().find(|(voted, _)| *voted == ip) {
+                    Some((_, count)) => *count += 1,
+                    None => votes.push((ip, 1)),
+                },
+                Err(e) => eprintln!("DEBUG: IP source {} failed: {}", source, e),
+            }
+        }
+        votes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        eprintln!(
+            "DEBUG: Public IP votes for the {}: {:?}",
+            family_name, votes
+        );
+
+        match votes.as_slice() {
+            [(ip, count), rest @ ..]
+                if *count >= quorum && rest.first().is_none_or(|(_, next)| next < count) =>
+            {
+                Ok(*ip)
+            }
+            _ => {
+                let votes: Vec<String> = votes
+                    .iter()
+                    .map(|(ip, count)| format!("{} ({})", ip, count))
+                    .collect();
+                Err(format!(
+                    "No {} was reported by {} of {} sources; answers: {}",
+                    family_name,
+                    quorum,
+                    sources.len(),
+                    if votes.is_empty() {
+                        "none".to_string()
+                    } else {
+                        votes.join(", ")
+                    }
+                )
+                .into())
+            }
+        }
+    }
+}
+
+fn bound_client(local_address: IpAddr) -> reqwest::Client {
+    reqwest::Client::builder()
+        .local_address(local_address)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// Ask one source for the public IP, checking it has the expected family
+async fn fetch_public_ip(
+    client: &reqwest::Client,
+    source: &IpSource,
+    family: Option<IpFamily>,
+) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
+    let response = client
+        .get(&source.url)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP status {}", response.status()).into());
+    }
+    let body = response.text().await?;
+
+    let text = match &source.json_field {
+        Some(field) => extract_json_field(&body, field)?,
+        None => body.trim().to_string(),
+    };
+    let ip: IpAddr = text
+        .parse()
+        .map_err(|_| format!("Not an IP address: {:?}", text))?;
+
+    if let Some(expected) = family.or(source.family) {
+        if IpFamily::of(ip) != expected {
+            return Err(format!("Expected an {} address, got {}", expected, ip).into());
+        }
+    }
+    Ok(ip)
+}
+
+/// Value of a `.`-separated field path (array elements by index) as a string
+fn extract_json_field(
+    body: &str,
+    field: &str,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut value: &serde_json::Value = &serde_json::from_str(body)?;
+    for key in field.split('.') {
+        value = match value {
+            serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => value.get(key),
+        }
+        .ok_or_else(|| format!("JSON response has no field {}", field))?;
+    }
+    match value {
+        serde_json::Value::String(text) => Ok(text.trim().to_string()),
+        _ => Err(format!("JSON field {} is not a string", field).into()),
+    }
 }
 
 #[async_trait]
 impl NetworkService for HttpNetworkService {
     async fn get_public_ip(&self) -> Result<IpAddr, Box<dyn std::error::Error + Send + Sync>> {
-        let endpoints = [
-            "https://api.ipify.org",
-            "https://ipinfo.io/ip",
-            "https://icanhazip.com",
-        ];
-
-        let mut last_error: Option<Box<dyn std::error::Error + Send + Sync>> = None;
-
-        for endpoint in &endpoints {
-            match self
-                .client
-                .get(*endpoint)
-                .timeout(std::time::Duration::from_secs(10))
-                .send()
-                .await
-            {
-                Ok(response) => match response.text().await {
-                    Ok(text) => {
-                        let ip_str = text.trim();
-                        match ip_str.parse::<IpAddr>() {
-                            Ok(ip) => return Ok(ip),
-                            Err(e) => {
-                                last_error = Some(Box::new(e));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        last_error = Some(Box::new(e));
-                    }
-                },
+        if self.pinned_families().is_empty() {
+            return self.detect_public_ip(None).await;
+        }
+        // Prefer IPv4 when sources are set up per family
+        let ips = self.get_public_ips().await?;
+        ips.first()
+            .copied()
+            .ok_or_else(|| "No public IP address detected".into())
+    }
+
+    async fn get_public_ips(
+        &self,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let families = self.pinned_families();
+        if families.is_empty() {
+            return Ok(vec![self.detect_public_ip(None).await?]);
+        }
+
+        // A family that can't be detected is left out, so its stored addresses are kept
+        let mut ips = Vec::new();
+        let mut last_error = None;
+        for family in families {
+            match self.detect_public_ip(Some(family)).await {
+                Ok(ip) => ips.push(ip),
                 Err(e) => {
-                    last_error = Some(Box::new(e));
+                    eprintln!(
+                        "DEBUG: Could not detect the public {} address: {}",
+                        family, e
+                    );
+                    last_error = Some(e);
                 }
             }
         }
-
-        Err(last_error.unwrap_or_else(|| {
-            Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to get public IP from all endpoints",
-            ))
-        }))
+        match last_error {
+            Some(e) if ips.is_empty() => Err(e),
+            _ => Ok(ips),
+        }
     }
 
     async fn resolve_hostname(
         &self,
         hostname: &str,
     ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
-        let addrs = tokio::net::lookup_host(format!("{}:80", hostname))
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
-        let ips: Vec<IpAddr> = addrs.map(|addr| addr.ip()).collect();
-        Ok(ips)
+        self.resolver.resolve(hostname).await
     }
 
     async fn is_reachable(
@@ -97,3 +286,8 @@ impl Default for HttpNetworkService {
         Self::new()
     }
 }
+
+// Include the test module
+#[cfg(test)]
+#[path = "network_tests.rs"]
+mod tests;
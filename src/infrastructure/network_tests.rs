@@ -0,0 +1,165 @@
+use super::*;
+use std::collections::HashMap;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// HTTP server on 127.0.0.1 answering each path with a fixed status and body; returns its URL
+async fn ip_server(routes: &[(&str, u16, &str)]) -> String {
+    let routes: HashMap<String, (u16, String)> = routes
+        .iter()
+        .map(|(path, status, body)| (path.to_string(), (*status, body.to_string())))
+        .collect();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let len = stream.read(&mut buffer).await.unwrap();
+                if len == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..len]);
+            }
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            let (status, body) = routes.get(path).cloned().unwrap_or((404, String::new()));
+            let response = format!(
+                "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+
+    url
+}
+
+fn service(sources: Vec<IpSource>, quorum: Option<usize>) -> HttpNetworkService {
+    HttpNetworkService::new().with_public_ip_config(PublicIpConfig { sources, quorum })
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_first_valid_answer_wins() {
+    let url = ip_server(&[
+        ("/down", 503, "203.0.113.66"),
+        ("/garbage", 200, "<html>"),
+        ("/ip", 200, "203.0.113.7\n"),
+    ])
+    .await;
+    let sources = ["/down", "/garbage", "/ip"]
+        .iter()
+        .map(|path| IpSource::plain(&format!("{}{}", url, path)))
+        .collect();
+
+    assert_eq!(
+        service(sources, None).get_public_ip().await.unwrap(),
+        ip("203.0.113.7")
+    );
+}
+
+#[tokio::test]
+async fn test_json_field_extraction() {
+    let url = ip_server(&[
+        ("/json", 200, r#"{"data": {"addresses": ["198.51.100.4"]}}"#),
+        ("/flat", 200, r#"{"ip": "198.51.100.5", "country": "NL"}"#),
+    ])
+    .await;
+
+    let nested = IpSource::plain(&format!("{}/json", url)).with_json_field("data.addresses.0");
+    assert_eq!(
+        service(vec![nested], None).get_public_ip().await.unwrap(),
+        ip("198.51.100.4")
+    );
+
+    let missing = IpSource::plain(&format!("{}/flat", url)).with_json_field("address");
+    assert!(service(vec![missing], None).get_public_ip().await.is_err());
+
+    assert_eq!(
+        extract_json_field(r#"{"ip": " 198.51.100.5 "}"#, "ip").unwrap(),
+        "198.51.100.5"
+    );
+    assert!(extract_json_field(r#"{"ip": 7}"#, "ip").is_err());
+}
+
+#[tokio::test]
+async fn test_quorum_rejects_a_misbehaving_source() {
+    let url = ip_server(&[
+        ("/a", 200, "203.0.113.7"),
+        ("/b", 200, "203.0.113.7"),
+        ("/evil", 200, "192.0.2.66"),
+    ])
+    .await;
+    let sources: Vec<IpSource> = ["/evil", "/a", "/b"]
+        .iter()
+        .map(|path| IpSource::plain(&format!("{}{}", url, path)))
+        .collect();
+
+    // The first source alone would have been believed
+    assert_eq!(
+        service(sources.clone(), None)
+            .get_public_ip()
+            .await
+            .unwrap(),
+        ip("192.0.2.66")
+    );
+    assert_eq!(
+        service(sources.clone(), Some(2))
+            .get_public_ip()
+            .await
+            .unwrap(),
+        ip("203.0.113.7")
+    );
+
+    let error = service(sources.clone(), Some(3))
+        .get_public_ip()
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("203.0.113.7 (2)"));
+    assert!(service(sources, Some(4)).get_public_ip().await.is_err());
+}
+
+#[tokio::test]
+async fn test_quorum_counts_failed_sources_as_missing_votes() {
+    let url = ip_server(&[("/a", 200, "203.0.113.7"), ("/down", 500, "")]).await;
+    let sources: Vec<IpSource> = ["/a", "/down"]
+        .iter()
+        .map(|path| IpSource::plain(&format!("{}{}", url, path)))
+        .collect();
+
+    assert!(service(sources.clone(), Some(2))
+        .get_public_ip()
+        .await
+        .is_err());
+    assert_eq!(
+        service(sources, Some(1)).get_public_ip().await.unwrap(),
+        ip("203.0.113.7")
+    );
+}
+
+#[tokio::test]
+async fn test_per_family_sources() {
+    let url = ip_server(&[("/v4", 200, "203.0.113.7"), ("/wrong", 200, "2001:db8::7")]).await;
+
+    // A source pinned to IPv4 that answers with an IPv6 address is not trusted
+    let wrong = IpSource::plain(&format!("{}/wrong", url)).with_family(IpFamily::V4);
+    assert!(service(vec![wrong.clone()], None)
+        .get_public_ips()
+        .await
+        .is_err());
+
+    let v4 = IpSource::plain(&format!("{}/v4", url)).with_family(IpFamily::V4);
+    let ips = service(vec![wrong, v4], None)
+        .get_public_ips()
+        .await
+        .unwrap();
+    assert_eq!(ips, vec![ip("203.0.113.7")]);
+}
@@ -115,6 +115,71 @@ impl Default for LogNotificationService {
     }
 }
 
+/// Webhook notification service: POSTs a JSON document for every event
+pub struct WebhookNotificationService {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotificationService {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    async fn post(
+        &self,
+        body: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .post(&self.url)
+            .timeout(std::time::Duration::from_secs(10))
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("Webhook {} returned HTTP {}", self.url, response.status()).into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationService for WebhookNotificationService {
+    async fn notify_ip_change(
+        &self,
+        hostname: &str,
+        old_ip: Option<IpAddr>,
+        new_ip: IpAddr,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.post(serde_json::json!({
+            "event": "ip_change",
+            "hostname": hostname,
+            "old_ip": old_ip,
+            "new_ip": new_ip,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        }))
+        .await
+    }
+
+    async fn notify_error(
+        &self,
+        error: &str,
+        context: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.post(serde_json::json!({
+            "event": "error",
+            "error": error,
+            "context": context,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        }))
+        .await
+    }
+}
+
 /// Composite notification service that can send to multiple services
 pub struct CompositeNotificationService {
     services: Vec<Box<dyn NotificationService>>,
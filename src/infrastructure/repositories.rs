@@ -1,12 +1,13 @@
 use std::fs;
 use std::net::IpAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
 use tokio::fs as async_fs;
 
-use crate::domain::entities::IpEntry;
+use crate::domain::entities::{EntryMigration, IpChange, IpEntry, LegacyFiles, IP_ENTRY_VERSION};
 use crate::domain::ports::IpRepository;
+use crate::infrastructure::files::write_atomically;
 
 /// File-based IP repository implementation
 pub struct FileIpRepository {
@@ -39,6 +40,237 @@ impl FileIpRepository {
     fn get_file_path(&self, hostname: &str) -> PathBuf {
         self.storage_dir.join(format!("{}.json", hostname))
     }
+
+    /// Entry of a hostname in the current layout, upgraded in memory if it is stored in an
+    /// older one; the file itself is upgraded by the next store or `migrate`
+    async fn load_entry(
+        &self,
+        hostname: &str,
+    ) -> Result<Option<IpEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let file_path = self.get_file_path(hostname);
+        if file_path.exists() {
+            let content = async_fs::read_to_string(&file_path).await?;
+            let (entry, _) =
+                migrate_entry(&content).map_err(|e| format!("{}: {}", file_path.display(), e))?;
+            return Ok(Some(entry));
+        }
+
+        let legacy_path = self.storage_dir.join(legacy_file_name(hostname));
+        if legacy_path.exists() {
+            return Ok(Some(legacy_entry(&legacy_path, hostname)?));
+        }
+        Ok(None)
+    }
+
+    /// Remove the legacy `<host>_ip.txt` file of a hostname once its JSON entry exists;
+    /// returns whether there was one
+    async fn remove_legacy_file(
+        &self,
+        hostname: &str,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match async_fs::remove_file(self.storage_dir.join(legacy_file_name(hostname))).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// JSON entries in the storage directory with the version they were stored with
+    async fn json_entries(
+        &self,
+    ) -> Result<Vec<(IpEntry, u32, PathBuf)>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut entries = Vec::new();
+        let mut dir = async_fs::read_dir(&self.storage_dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let content = async_fs::read_to_string(&path).await?;
+                if let Ok((entry, version)) = migrate_entry(&content) {
+                    entries.push((entry, version, path));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Stored entries in an older layout with their files: JSON files of an older version and
+    /// legacy `<host>_ip.txt` files, in the storage directory and those of `legacy`, sorted by
+    /// location
+    async fn outdated_entries(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<(EntryMigration, PathBuf)>, Box<dyn std::error::Error + Send + Sync>> {
+        let json_entries = self.json_entries().await?;
+        let mut hostnames = legacy.hostnames.clone();
+        hostnames.extend(
+            json_entries
+                .iter()
+                .map(|(entry, _, _)| entry.hostname.clone()),
+        );
+
+        let mut outdated: Vec<(String, u32, PathBuf)> = json_entries
+            .into_iter()
+            .map(|(entry, version, path)| (entry.hostname, version, path))
+            .collect();
+        for dir in std::iter::once(&self.storage_dir).chain(&legacy.dirs) {
+            outdated.extend(
+                legacy_files(dir)
+                    .into_iter()
+                    .filter_map(|path| Some((legacy_hostname(&path, &hostnames)?, 0, path))),
+            );
+        }
+
+        let mut migrations: Vec<(EntryMigration, PathBuf)> = outdated
+            .into_iter()
+            .filter(|(_, from_version, _)| *from_version < IP_ENTRY_VERSION)
+            .map(|(hostname, from_version, path)| {
+                let migration = EntryMigration {
+                    hostname,
+                    location: path.display().to_string(),
+                    from_version,
+                    to_version: IP_ENTRY_VERSION,
+                };
+                (migration, path)
+            })
+            .collect();
+        migrations.sort_by(|(a, _), (b, _)| a.location.cmp(&b.location));
+        Ok(migrations)
+    }
+}
+
+/// Stored entry layouts, oldest first:
+///
+/// - 0: `<host>_ip.txt` holding a single address, as written by the former `core::ip_storage`
+///   (dots in the hostname became underscores) relative to the working directory
+/// - 1: `<hostname>.json` without a `version` field
+/// - 2: `<hostname>.json` with a `version` field and the addresses of both families as lists
+///
+/// Upgrade a stored JSON entry to the current layout; returns it with the version it had.
+pub fn migrate_entry(
+    json: &str,
+) -> Result<(IpEntry, u32), Box<dyn std::error::Error + Send + Sync>> {
+    let mut value: serde_json::Value = serde_json::from_str(json)?;
+    let version = match value.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("Invalid entry version: {}", version))?,
+    };
+    if version > IP_ENTRY_VERSION {
+        return Err(format!(
+            "Entry version {} is newer than this build supports ({})",
+            version, IP_ENTRY_VERSION
+        )
+        .into());
+    }
+    if version < 2 {
+        upgrade_v1(&mut value)?;
+    }
+    Ok((serde_json::from_value(value)?, version))
+}
+
+/// Version 1 to 2: `ipv4` and `ipv6` become lists (filled from `ip` if neither is there) and
+/// the version is recorded
+fn upgrade_v1(
+    value: &mut serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let object = value
+        .as_object_mut()
+        .ok_or("Stored entry is not a JSON object")?;
+    for family in ["ipv4", "ipv6"] {
+        match object.get(family) {
+            Some(serde_json::Value::String(ip)) => {
+                let list = serde_json::json!([ip]);
+                object.insert(family.to_string(), list);
+            }
+            Some(serde_json::Value::Null) => {
+                object.remove(family);
+            }
+            _ => {}
+        }
+    }
+    if !object.contains_key("ipv4") && !object.contains_key("ipv6") {
+        let ip: IpAddr = object
+            .get("ip")
+            .and_then(|ip| ip.as_str())
+            .ok_or("Stored entry has no address")?
+            .parse()?;
+        let family = if ip.is_ipv4() { "ipv4" } else { "ipv6" };
+        object.insert(family.to_string(), serde_json::json!([ip.to_string()]));
+    }
+    object.insert("version".to_string(), serde_json::json!(2));
+    Ok(())
+}
+
+/// Name of the legacy `core::ip_storage` file of a hostname
+fn legacy_file_name(hostname: &str) -> String {
+    format!("{}_ip.txt", hostname.replace('.', "_"))
+}
+
+/// Hostname of a legacy `<host>_ip.txt` file: the one of `hostnames` the file belongs to, or
+/// else the name in the file as it is. An underscore may have been a dot or an underscore, so
+/// the dots of an unknown hostname can't be told.
+fn legacy_hostname(path: &Path, hostnames: &[String]) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let host = name.strip_suffix("_ip.txt")?;
+    if host.is_empty() {
+        return None;
+    }
+    let known = hostnames
+        .iter()
+        .find(|hostname| legacy_file_name(hostname) == name);
+    Some(known.map_or_else(|| host.to_string(), String::clone))
+}
+
+/// Legacy `<host>_ip.txt` files in a directory, none if it can't be read
+fn legacy_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(files) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    files
+        .flatten()
+        .map(|file| file.path())
+        .filter(|path| legacy_hostname(path, &[]).is_some())
+        .collect()
+}
+
+/// Entry of a hostname read from its legacy `<host>_ip.txt` file, dated by the file's
+/// modification time
+pub(crate) fn legacy_entry(
+    path: &Path,
+    hostname: &str,
+) -> Result<IpEntry, Box<dyn std::error::Error + Send + Sync>> {
+    let content = fs::read_to_string(path)?;
+    let ip: IpAddr = content
+        .trim()
+        .parse()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut entry = IpEntry::new(
+        ip,
+        hostname.to_string(),
+        Some(format!(
+            "Migrated from {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )),
+    );
+    if let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        entry.created_at = modified.into();
+        entry.updated_at = entry.created_at;
+    }
+    Ok(entry)
+}
+
+/// Entries of the legacy `<host>_ip.txt` files in a directory with their files, named after
+/// the one of `hostnames` they belong to if any; unreadable files are skipped
+pub(crate) fn legacy_entries(dir: &Path, hostnames: &[String]) -> Vec<(IpEntry, PathBuf)> {
+    legacy_files(dir)
+        .into_iter()
+        .filter_map(|path| {
+            let entry = legacy_entry(&path, &legacy_hostname(&path, hostnames)?).ok()?;
+            Some((entry, path))
+        })
+        .collect()
 }
 
 #[async_trait]
@@ -48,7 +280,18 @@ impl IpRepository for FileIpRepository {
         hostname: &str,
         ip: IpAddr,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let entry = IpEntry::new(ip, hostname.to_string(), None);
+        self.store_ips(hostname, &[ip]).await
+    }
+
+    async fn store_ips(
+        &self,
+        hostname: &str,
+        ips: &[IpAddr],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let existing = self.load_entry(hostname).await?;
+        let Some(entry) = updated_entry(hostname, existing, ips) else {
+            return Ok(());
+        };
         let file_path = self.get_file_path(hostname);
         let absolute_path = std::fs::canonicalize(&file_path).unwrap_or_else(|_| {
             // If file doesn't exist, construct absolute path manually
@@ -57,13 +300,14 @@ impl IpRepository for FileIpRepository {
                 .unwrap_or_else(|_| file_path.clone())
         });
         eprintln!(
-            "DEBUG: Storing IP {} for {} in JSON file: {}",
-            ip,
+            "DEBUG: Storing IPs {:?} for {} in JSON file: {}",
+            ips,
             hostname,
             absolute_path.display()
         );
         let json = serde_json::to_string_pretty(&entry)?;
-        async_fs::write(&file_path, json).await?;
+        write_atomically(&file_path, &json).await?;
+        self.remove_legacy_file(hostname).await?;
         let final_absolute_path =
             std::fs::canonicalize(&file_path).unwrap_or_else(|_| file_path.clone());
         eprintln!(
@@ -89,20 +333,13 @@ impl IpRepository for FileIpRepository {
             absolute_path.display()
         );
 
-        if !file_path.exists() {
+        let Some(entry) = self.load_entry(hostname).await? else {
             eprintln!(
                 "DEBUG: JSON file does not exist: {}",
                 absolute_path.display()
             );
             return Ok(None);
-        }
-
-        eprintln!(
-            "DEBUG: Loading IP from JSON file: {}",
-            absolute_path.display()
-        );
-        let content = async_fs::read_to_string(&file_path).await?;
-        let entry: IpEntry = serde_json::from_str(&content)?;
+        };
         eprintln!(
             "DEBUG: Loaded IP {} from JSON file: {}",
             entry.ip,
@@ -115,31 +352,25 @@ impl IpRepository for FileIpRepository {
         &self,
         hostname: &str,
     ) -> Result<Option<IpEntry>, Box<dyn std::error::Error + Send + Sync>> {
-        let file_path = self.get_file_path(hostname);
-
-        if !file_path.exists() {
-            return Ok(None);
-        }
-
-        let content = async_fs::read_to_string(file_path).await?;
-        let entry: IpEntry = serde_json::from_str(&content)?;
-        Ok(Some(entry))
+        self.load_entry(hostname).await
     }
 
     async fn list_all_entries(
         &self,
     ) -> Result<Vec<IpEntry>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut entries = Vec::new();
-        let mut dir = async_fs::read_dir(&self.storage_dir).await?;
-
-        while let Some(entry) = dir.next_entry().await? {
-            if let Some(ext) = entry.path().extension() {
-                if ext == "json" {
-                    let content = async_fs::read_to_string(entry.path()).await?;
-                    if let Ok(ip_entry) = serde_json::from_str::<IpEntry>(&content) {
-                        entries.push(ip_entry);
-                    }
-                }
+        let mut entries: Vec<IpEntry> = self
+            .json_entries()
+            .await?
+            .into_iter()
+            .map(|(entry, _, _)| entry)
+            .collect();
+        let hostnames: Vec<String> = entries.iter().map(|entry| entry.hostname.clone()).collect();
+        for (legacy, _) in legacy_entries(&self.storage_dir, &hostnames) {
+            if !entries
+                .iter()
+                .any(|entry| entry.hostname == legacy.hostname)
+            {
+                entries.push(legacy);
             }
         }
 
@@ -152,52 +383,119 @@ impl IpRepository for FileIpRepository {
         hostname: &str,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let file_path = self.get_file_path(hostname);
+        let removed_legacy = self.remove_legacy_file(hostname).await?;
 
         if file_path.exists() {
             async_fs::remove_file(file_path).await?;
             Ok(true)
         } else {
-            Ok(false)
+            Ok(removed_legacy)
         }
     }
 
+    async fn save_entry(
+        &self,
+        entry: &IpEntry,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string_pretty(entry)?;
+        write_atomically(&self.get_file_path(&entry.hostname), &json).await?;
+        self.remove_legacy_file(&entry.hostname).await?;
+        Ok(())
+    }
+
     /// Initialize DNS host file if it doesn't exist yet (FileIpRepository implementation)
     async fn initialize_host_file(
         &self,
         hostname: &str,
-        resolved_ip: IpAddr,
+        resolved_ips: &[IpAddr],
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let file_path = self.get_file_path(hostname);
 
         // Only create if file doesn't exist
-        if file_path.exists() {
+        if file_path.exists() || self.storage_dir.join(legacy_file_name(hostname)).exists() {
             return Ok(false);
         }
+        let Some(&first_ip) = resolved_ips.first() else {
+            return Ok(false);
+        };
 
-        // Create an initial entry with the resolved IP (or placeholder if resolution failed)
-        let comment = if resolved_ip.to_string() == "0.0.0.0" {
+        // Create an initial entry with the resolved IPs (or placeholder if resolution failed)
+        let comment = if resolved_ips.iter().all(|ip| ip.is_unspecified()) {
             "Initial DNS host file created at first startup (will be updated with real IP)"
                 .to_string()
         } else {
             "Initial DNS host file created at first startup with resolved IP".to_string()
         };
 
-        let initial_entry = IpEntry::new(
-            resolved_ip, // Use the resolved IP (real or placeholder)
-            hostname.to_string(),
-            Some(comment),
-        );
+        let mut initial_entry = IpEntry::new(first_ip, hostname.to_string(), Some(comment));
+        // Every resolved address, so a round-robin host's other records aren't taken for
+        // additions on the next run
+        initial_entry.update_ips(resolved_ips);
 
         let json = serde_json::to_string_pretty(&initial_entry)?;
-        async_fs::write(&file_path, json).await?;
+        write_atomically(&file_path, &json).await?;
 
         Ok(true)
     }
+
+    async fn pending_migrations(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let outdated = self.outdated_entries(legacy).await?;
+        Ok(outdated
+            .into_iter()
+            .map(|(migration, _)| migration)
+            .collect())
+    }
+
+    async fn migrate(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut migrations = Vec::new();
+        for (migration, path) in self.outdated_entries(legacy).await? {
+            let file_path = self.get_file_path(&migration.hostname);
+            // A legacy file next to a JSON entry is stale and only removed, the JSON entry wins
+            let entry = if file_path.exists() {
+                self.load_entry(&migration.hostname)
+                    .await?
+                    .ok_or_else(|| format!("{} disappeared while migrating", migration.location))?
+            } else {
+                legacy_entry(&path, &migration.hostname)?
+            };
+            let json = serde_json::to_string_pretty(&entry)?;
+            write_atomically(&file_path, &json).await?;
+            if migration.from_version == 0 {
+                match async_fs::remove_file(&path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            migrations.push(migration);
+        }
+        Ok(migrations)
+    }
+}
+
+/// The existing entry with `ips` stored, keeping its comment, owner, tags, creation time and
+/// the addresses of families not present in `ips`; a new entry if there is none
+pub(crate) fn updated_entry(
+    hostname: &str,
+    existing: Option<IpEntry>,
+    ips: &[IpAddr],
+) -> Option<IpEntry> {
+    let first = *ips.first()?;
+    let mut entry = existing.unwrap_or_else(|| IpEntry::new(first, hostname.to_string(), None));
+    entry.update_ips(ips);
+    Some(entry)
 }
 
 /// In-memory IP repository for testing
 pub struct InMemoryIpRepository {
     entries: std::sync::Arc<tokio::sync::RwLock<std::collections::HashMap<String, IpEntry>>>,
+    changes: std::sync::Arc<tokio::sync::RwLock<Vec<IpChange>>>,
 }
 
 impl InMemoryIpRepository {
@@ -206,6 +504,7 @@ impl InMemoryIpRepository {
             entries: std::sync::Arc::new(
                 tokio::sync::RwLock::new(std::collections::HashMap::new()),
             ),
+            changes: std::sync::Arc::new(tokio::sync::RwLock::new(Vec::new())),
         }
     }
 }
@@ -216,10 +515,19 @@ impl IpRepository for InMemoryIpRepository {
         &self,
         hostname: &str,
         ip: IpAddr,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.store_ips(hostname, &[ip]).await
+    }
+
+    async fn store_ips(
+        &self,
+        hostname: &str,
+        ips: &[IpAddr],
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let mut entries = self.entries.write().await;
-        let entry = IpEntry::new(ip, hostname.to_string(), None);
-        entries.insert(hostname.to_string(), entry);
+        if let Some(entry) = updated_entry(hostname, entries.get(hostname).cloned(), ips) {
+            entries.insert(hostname.to_string(), entry);
+        }
         Ok(())
     }
 
@@ -255,6 +563,37 @@ impl IpRepository for InMemoryIpRepository {
         let mut entries = self.entries.write().await;
         Ok(entries.remove(hostname).is_some())
     }
+
+    async fn save_entry(
+        &self,
+        entry: &IpEntry,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut entries = self.entries.write().await;
+        entries.insert(entry.hostname.clone(), entry.clone());
+        Ok(())
+    }
+
+    async fn record_change(
+        &self,
+        change: &IpChange,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.changes.write().await.push(change.clone());
+        Ok(())
+    }
+
+    async fn change_history(
+        &self,
+        hostname: Option<&str>,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Vec<IpChange>, Box<dyn std::error::Error + Send + Sync>> {
+        let changes = self.changes.read().await;
+        Ok(changes
+            .iter()
+            .filter(|change| hostname.is_none_or(|hostname| change.hostname == hostname))
+            .filter(|change| since.is_none_or(|since| change.changed_at >= since))
+            .cloned()
+            .collect())
+    }
 }
 
 impl Default for InMemoryIpRepository {
@@ -262,3 +601,7 @@ impl Default for InMemoryIpRepository {
         Self::new()
     }
 }
+
+#[cfg(test)]
+#[path = "repositories_tests.rs"]
+mod tests;
@@ -0,0 +1,233 @@
+use super::*;
+use crate::domain::entities::EntryMetadata;
+use tempfile::tempdir;
+
+const V1_ENTRY: &str = r#"{
+  "ip": "192.168.1.10",
+  "hostname": "home.example.com",
+  "comment": "kept",
+  "created_at": "2025-01-01T00:00:00Z",
+  "updated_at": "2025-01-02T00:00:00Z"
+}"#;
+
+#[test]
+fn test_migrate_entry_upgrades_unversioned_entries() {
+    let (entry, version) = migrate_entry(V1_ENTRY).unwrap();
+    assert_eq!(version, 1);
+    assert_eq!(entry.version, IP_ENTRY_VERSION);
+    assert_eq!(
+        entry.ipv4,
+        vec!["192.168.1.10".parse::<std::net::Ipv4Addr>().unwrap()]
+    );
+    assert!(entry.ipv6.is_empty());
+    assert_eq!(entry.comment.as_deref(), Some("kept"));
+
+    // Single addresses of both families become lists
+    let (entry, _) = migrate_entry(
+        r#"{"ip": "192.168.1.10", "ipv4": "192.168.1.10", "ipv6": "2001:db8::1",
+            "hostname": "home.example.com", "comment": null,
+            "created_at": "2025-01-01T00:00:00Z", "updated_at": "2025-01-01T00:00:00Z"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        entry.ipv6,
+        vec!["2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap()]
+    );
+
+    // Current entries are taken as they are, newer ones are refused
+    let current = serde_json::to_string(&entry).unwrap();
+    assert_eq!(migrate_entry(&current).unwrap(), (entry, IP_ENTRY_VERSION));
+    let newer = current.replacen(
+        &format!("\"version\":{}", IP_ENTRY_VERSION),
+        &format!("\"version\":{}", IP_ENTRY_VERSION + 1),
+        1,
+    );
+    assert!(migrate_entry(&newer)
+        .unwrap_err()
+        .to_string()
+        .contains("newer"));
+}
+
+#[tokio::test]
+async fn test_file_repository_migrates_older_layouts() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("home.example.com.json"), V1_ENTRY).unwrap();
+    fs::write(
+        temp_dir.path().join("office_example_com_ip.txt"),
+        "10.0.0.5\n",
+    )
+    .unwrap();
+    let repo = FileIpRepository::new(temp_dir.path().to_path_buf()).unwrap();
+
+    // Loading upgrades in memory only
+    let legacy = repo
+        .get_ip_entry("office.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(legacy.ip, "10.0.0.5".parse::<IpAddr>().unwrap());
+    let hostnames: Vec<String> = repo
+        .list_all_entries()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.hostname)
+        .collect();
+    // Without the hostname, the underscores of the file name can't be told from dots
+    assert_eq!(hostnames, vec!["home.example.com", "office_example_com"]);
+
+    let legacy_files = LegacyFiles {
+        dirs: Vec::new(),
+        hostnames: vec!["office.example.com".to_string()],
+    };
+    let pending = repo.pending_migrations(&legacy_files).await.unwrap();
+    assert_eq!(
+        pending
+            .iter()
+            .map(|migration| (migration.hostname.as_str(), migration.from_version))
+            .collect::<Vec<_>>(),
+        vec![("home.example.com", 1), ("office.example.com", 0)]
+    );
+
+    assert_eq!(repo.migrate(&legacy_files).await.unwrap(), pending);
+    assert!(repo
+        .pending_migrations(&legacy_files)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!temp_dir.path().join("office_example_com_ip.txt").exists());
+    let migrated = repo
+        .get_ip_entry("office.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(migrated.created_at, legacy.created_at);
+    let stored = fs::read_to_string(temp_dir.path().join("home.example.com.json")).unwrap();
+    let (entry, version) = migrate_entry(&stored).unwrap();
+    assert_eq!(version, IP_ENTRY_VERSION);
+    assert_eq!(entry.comment.as_deref(), Some("kept"));
+}
+
+#[test]
+fn test_legacy_hostnames_keep_underscores_unless_known() {
+    let path = Path::new("/var/lib/ddns-updater/my_host_example_com_ip.txt");
+    assert_eq!(
+        legacy_hostname(path, &[]).as_deref(),
+        Some("my_host_example_com")
+    );
+    let hostnames = vec!["example.com".to_string(), "my_host.example.com".to_string()];
+    assert_eq!(
+        legacy_hostname(path, &hostnames).as_deref(),
+        Some("my_host.example.com")
+    );
+    assert_eq!(legacy_hostname(Path::new("_ip.txt"), &hostnames), None);
+    assert_eq!(legacy_hostname(Path::new("my_host.json"), &hostnames), None);
+}
+
+#[tokio::test]
+async fn test_migrate_takes_over_legacy_files_of_another_directory() {
+    let storage_dir = tempdir().unwrap();
+    let working_dir = tempdir().unwrap();
+    fs::write(
+        working_dir.path().join("home_example_com_ip.txt"),
+        "192.168.1.10\n",
+    )
+    .unwrap();
+    fs::write(working_dir.path().join("unrelated.txt"), "kept").unwrap();
+    let repo = FileIpRepository::new(storage_dir.path().to_path_buf()).unwrap();
+    let legacy_files = LegacyFiles {
+        dirs: vec![working_dir.path().to_path_buf()],
+        hostnames: vec!["home.example.com".to_string()],
+    };
+
+    assert!(repo
+        .pending_migrations(&LegacyFiles::default())
+        .await
+        .unwrap()
+        .is_empty());
+    let pending = repo.pending_migrations(&legacy_files).await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].hostname, "home.example.com");
+    assert_eq!(pending[0].from_version, 0);
+
+    assert_eq!(repo.migrate(&legacy_files).await.unwrap(), pending);
+    assert!(!working_dir.path().join("home_example_com_ip.txt").exists());
+    assert!(working_dir.path().join("unrelated.txt").exists());
+    let entry = repo
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.ip, "192.168.1.10".parse::<IpAddr>().unwrap());
+    assert!(storage_dir.path().join("home.example.com.json").exists());
+}
+
+#[tokio::test]
+async fn test_store_keeps_comment_metadata_and_creation_time() {
+    let temp_dir = tempdir().unwrap();
+    let repo = FileIpRepository::new(temp_dir.path().to_path_buf()).unwrap();
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+    assert!(repo
+        .initialize_host_file("example.com", &[ip])
+        .await
+        .unwrap());
+
+    let mut entry = repo.get_ip_entry("example.com").await.unwrap().unwrap();
+    EntryMetadata {
+        comment: Some("Office VPN gateway".to_string()),
+        owner: Some("netops".to_string()),
+        tags: Some(vec![
+            "vpn".to_string(),
+            " office ".to_string(),
+            "vpn".to_string(),
+        ]),
+    }
+    .apply_to(&mut entry);
+    repo.save_entry(&entry).await.unwrap();
+
+    repo.store_ips(
+        "example.com",
+        &["10.0.0.1".parse().unwrap(), "2001:db8::1".parse().unwrap()],
+    )
+    .await
+    .unwrap();
+    let updated = repo.get_ip_entry("example.com").await.unwrap().unwrap();
+    assert_eq!(updated.ip, "10.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(updated.comment.as_deref(), Some("Office VPN gateway"));
+    assert_eq!(updated.owner.as_deref(), Some("netops"));
+    assert_eq!(updated.tags, vec!["office", "vpn"]);
+    assert_eq!(updated.created_at, entry.created_at);
+
+    // Empty values clear, fields not given are kept
+    let mut cleared = updated.clone();
+    EntryMetadata {
+        owner: Some(String::new()),
+        tags: Some(Vec::new()),
+        ..Default::default()
+    }
+    .apply_to(&mut cleared);
+    assert_eq!(cleared.owner, None);
+    assert!(cleared.tags.is_empty());
+    assert_eq!(cleared.comment, updated.comment);
+}
+
+#[tokio::test]
+async fn test_first_entry_holds_every_resolved_address() {
+    let temp_dir = tempdir().unwrap();
+    let repo = FileIpRepository::new(temp_dir.path().to_path_buf()).unwrap();
+    let ips: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.8".parse().unwrap(),
+    ];
+
+    assert!(repo
+        .initialize_host_file("pool.example.com", &ips)
+        .await
+        .unwrap());
+    let entry = repo
+        .get_ip_entry("pool.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.addresses(), ips);
+}
@@ -0,0 +1,453 @@
+use async_trait::async_trait;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+
+use super::dns::{
+    Message, RecordData, FLAG_RESPONSE, RCODE_NO_ERROR, RCODE_NXDOMAIN, TYPE_A, TYPE_AAAA, TYPE_NS,
+};
+use crate::domain::entities::DnsTransport;
+use crate::domain::ports::DnsResolver;
+
+/// Default DNS-over-HTTPS endpoint
+pub const DEFAULT_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
+
+/// CNAME links followed before giving up
+const MAX_CNAME_DEPTH: usize = 8;
+
+/// Resolver of the operating system (getaddrinfo), including caches such as systemd-resolved
+pub struct SystemResolver;
+
+#[async_trait]
+impl DnsResolver for SystemResolver {
+    async fn resolve(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let addrs = tokio::net::lookup_host(format!("{}:80", hostname))
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+}
+
+/// Queries nameservers directly over UDP or TCP, bypassing local caches.
+///
+/// In authoritative mode the configured (recursive) nameservers are only used to find the
+/// hostname's zone and its nameservers, which are then asked without recursion.
+pub struct NameserverResolver {
+    servers: Vec<SocketAddr>,
+    transport: DnsTransport,
+    authoritative: bool,
+    authoritative_port: u16,
+    timeout: Duration,
+}
+
+impl NameserverResolver {
+    /// Resolver using the given nameservers; empty means those from /etc/resolv.conf
+    pub fn new(servers: Vec<SocketAddr>) -> Self {
+        Self {
+            servers,
+            transport: DnsTransport::Udp,
+            authoritative: false,
+            authoritative_port: 53,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_transport(mut self, transport: DnsTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_authoritative(mut self, authoritative: bool) -> Self {
+        self.authoritative = authoritative;
+        self
+    }
+
+    /// Port the authoritative nameservers listen on (53 unless testing against a stub)
+    pub fn with_authoritative_port(mut self, port: u16) -> Self {
+        self.authoritative_port = port;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn recursive_servers(
+        &self,
+    ) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.servers.is_empty() {
+            return Ok(self.servers.clone());
+        }
+        let servers = system_nameservers();
+        if servers.is_empty() {
+            return Err("No nameservers configured and none found in /etc/resolv.conf".into());
+        }
+        Ok(servers)
+    }
+
+    /// Send one query and wait for the matching response
+    async fn exchange(
+        &self,
+        server: SocketAddr,
+        query: &Message,
+    ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        let request = query.encode()?;
+        let exchange = async {
+            let response = match self.transport {
+                DnsTransport::Udp => {
+                    let response = udp_exchange(server, &request, query.id).await?;
+                    if response.is_truncated() {
+                        eprintln!(
+                            "DEBUG: Truncated UDP answer from {}, retrying over TCP",
+                            server
+                        );
+                        tcp_exchange(server, &request).await?
+                    } else {
+                        response
+                    }
+                }
+                DnsTransport::Tcp => tcp_exchange(server, &request).await?,
+            };
+            if response.id != query.id || response.flags & FLAG_RESPONSE == 0 {
+                return Err(format!("Unexpected answer from nameserver {}", server).into());
+            }
+            Ok(response)
+        };
+
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| format!("Nameserver {} did not answer in time", server))?
+    }
+
+    /// Ask the servers in order until one gives a usable answer (NOERROR or NXDOMAIN)
+    async fn query(
+        &self,
+        servers: &[SocketAddr],
+        name: &str,
+        qtype: u16,
+        recursion_desired: bool,
+    ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> =
+            "No nameservers to query".into();
+
+        for &server in servers {
+            let query = Message::query(next_query_id(), name, qtype, recursion_desired);
+            match self.exchange(server, &query).await {
+                Ok(response)
+                    if response.rcode() == RCODE_NO_ERROR || response.rcode() == RCODE_NXDOMAIN =>
+                {
+                    return Ok(response);
+                }
+                Ok(response) => {
+                    last_error = format!(
+                        "Nameserver {} answered {} with response code {}",
+                        server,
+                        name,
+                        response.rcode()
+                    )
+                    .into();
+                }
+                Err(e) => last_error = e,
+            }
+            eprintln!("DEBUG: {}", last_error);
+        }
+
+        Err(last_error)
+    }
+
+    /// A and AAAA lookup of one name
+    async fn lookup(
+        &self,
+        servers: &[SocketAddr],
+        name: &str,
+        recursion_desired: bool,
+    ) -> Result<Lookup, Box<dyn std::error::Error + Send + Sync>> {
+        let mut responses = Vec::new();
+        for qtype in [TYPE_A, TYPE_AAAA] {
+            responses.push(self.query(servers, name, qtype, recursion_desired).await?);
+        }
+        Lookup::from_responses(name, &responses)
+    }
+
+    /// Nameservers of the closest enclosing zone of `name` that has NS records
+    async fn authoritative_servers(
+        &self,
+        name: &str,
+    ) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let recursive = self.recursive_servers()?;
+        let mut zone = name.trim_end_matches('.').to_lowercase();
+
+        loop {
+            let response = self.query(&recursive, &zone, TYPE_NS, true).await?;
+            let nameservers: Vec<&String> = response
+                .answers
+                .iter()
+                .filter(|record| record.name == zone)
+                .filter_map(|record| match &record.data {
+                    RecordData::Ns(host) => Some(host),
+                    _ => None,
+                })
+                .collect();
+
+            let mut servers = Vec::new();
+            for host in nameservers {
+                // Prefer glue records, otherwise look the nameserver up recursively
+                let mut addresses: Vec<IpAddr> = response
+                    .additional
+                    .iter()
+                    .filter(|record| record.name == *host)
+                    .filter_map(|record| record_address(&record.data))
+                    .collect();
+                if addresses.is_empty() {
+                    match self.lookup(&recursive, host, true).await {
+                        Ok(lookup) => addresses = lookup.addresses,
+                        Err(e) => eprintln!("DEBUG: Could not resolve nameserver {}: {}", host, e),
+                    }
+                }
+                servers.extend(
+                    addresses
+                        .into_iter()
+                        .map(|ip| SocketAddr::new(ip, self.authoritative_port)),
+                );
+            }
+            if !servers.is_empty() {
+                eprintln!(
+                    "DEBUG: Authoritative nameservers for {} (zone {}): {:?}",
+                    name, zone, servers
+                );
+                return Ok(servers);
+            }
+
+            zone = match zone.split_once('.') {
+                Some((_, parent)) if !parent.is_empty() => parent.to_string(),
+                _ => return Err(format!("No authoritative nameservers found for {}", name).into()),
+            };
+        }
+    }
+}
+
+#[async_trait]
+impl DnsResolver for NameserverResolver {
+    async fn resolve(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut name = hostname.trim_end_matches('.').to_lowercase();
+
+        for _ in 0..MAX_CNAME_DEPTH {
+            let lookup = if self.authoritative {
+                let servers = self.authoritative_servers(&name).await?;
+                self.lookup(&servers, &name, false).await?
+            } else {
+                self.lookup(&self.recursive_servers()?, &name, true).await?
+            };
+
+            match lookup.cname {
+                // An authoritative server only answers for its own zone, so an alias into
+                // another zone is followed with a new lookup
+                Some(target) if lookup.addresses.is_empty() => name = target,
+                _ => return Ok(lookup.addresses),
+            }
+        }
+
+        Err(format!("Too many CNAME redirections for {}", hostname).into())
+    }
+}
+
+/// DNS-over-HTTPS (RFC 8484) resolver, sending wire format queries with POST
+pub struct DohResolver {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl DohResolver {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    async fn query(
+        &self,
+        name: &str,
+        qtype: u16,
+    ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        // RFC 8484 recommends ID 0 so identical queries can be cached by HTTP caches
+        let query = Message::query(0, name, qtype, true);
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/dns-message")
+            .header("Accept", "application/dns-message")
+            .timeout(Duration::from_secs(10))
+            .body(query.encode()?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("DoH server {} returned {}", self.url, response.status()).into());
+        }
+        let message = Message::decode(&response.bytes().await?)?;
+        if message.rcode() != RCODE_NO_ERROR && message.rcode() != RCODE_NXDOMAIN {
+            return Err(format!(
+                "DoH server {} answered {} with response code {}",
+                self.url,
+                name,
+                message.rcode()
+            )
+            .into());
+        }
+        Ok(message)
+    }
+}
+
+#[async_trait]
+impl DnsResolver for DohResolver {
+    async fn resolve(
+        &self,
+        hostname: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let name = hostname.trim_end_matches('.').to_lowercase();
+        let mut responses = Vec::new();
+        for qtype in [TYPE_A, TYPE_AAAA] {
+            responses.push(self.query(&name, qtype).await?);
+        }
+        // The DoH server resolves recursively, so CNAME chains come with their addresses
+        Ok(Lookup::from_responses(&name, &responses)?.addresses)
+    }
+}
+
+/// Nameservers listed in /etc/resolv.conf
+pub fn system_nameservers() -> Vec<SocketAddr> {
+    std::fs::read_to_string("/etc/resolv.conf")
+        .map(|content| parse_resolv_conf(&content))
+        .unwrap_or_default()
+}
+
+/// `nameserver` entries of a resolv.conf, on port 53
+fn parse_resolv_conf(content: &str) -> Vec<SocketAddr> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("nameserver") {
+                return None;
+            }
+            // Drop a zone index such as `fe80::1%eth0`, which IpAddr doesn't parse
+            let address = words.next()?.split('%').next()?;
+            Some(SocketAddr::new(address.parse().ok()?, 53))
+        })
+        .collect()
+}
+
+/// Addresses found for a name, or the CNAME target to follow if there are none
+struct Lookup {
+    addresses: Vec<IpAddr>,
+    cname: Option<String>,
+}
+
+impl Lookup {
+    fn from_responses(
+        name: &str,
+        responses: &[Message],
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        if responses
+            .iter()
+            .all(|response| response.rcode() == RCODE_NXDOMAIN)
+        {
+            return Err(format!("{} does not exist (NXDOMAIN)", name).into());
+        }
+
+        let mut addresses = Vec::new();
+        let mut cname = None;
+        for response in responses {
+            for record in &response.answers {
+                match &record.data {
+                    RecordData::Cname(target) => {
+                        cname.get_or_insert_with(|| target.clone());
+                    }
+                    data => {
+                        if let Some(ip) = record_address(data) {
+                            if !addresses.contains(&ip) {
+                                addresses.push(ip);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Self { addresses, cname })
+    }
+}
+
+fn record_address(data: &RecordData) -> Option<IpAddr> {
+    match data {
+        RecordData::A(ip) => Some(IpAddr::V4(*ip)),
+        RecordData::Aaaa(ip) => Some(IpAddr::V6(*ip)),
+        _ => None,
+    }
+}
+
+/// Query IDs only need to be unpredictable enough to tell answers apart
+fn next_query_id() -> u16 {
+    static NEXT_ID: AtomicU16 = AtomicU16::new(0);
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos() as u16)
+        .unwrap_or(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed).wrapping_add(seed)
+}
+
+async fn udp_exchange(
+    server: SocketAddr,
+    request: &[u8],
+    id: u16,
+) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+    let local: SocketAddr = if server.is_ipv4() {
+        "0.0.0.0:0".parse()?
+    } else {
+        "[::]:0".parse()?
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(server).await?;
+    socket.send(request).await?;
+
+    let mut buffer = vec![0u8; 4096];
+    loop {
+        let len = socket.recv(&mut buffer).await?;
+        // Skip stray datagrams, e.g. late answers to an earlier query
+        match Message::decode(&buffer[..len]) {
+            Ok(response) if response.id == id => return Ok(response),
+            _ => continue,
+        }
+    }
+}
+
+async fn tcp_exchange(
+    server: SocketAddr,
+    request: &[u8],
+) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+    let mut stream = TcpStream::connect(server).await?;
+    let mut framed = Vec::with_capacity(request.len() + 2);
+    framed.extend_from_slice(&(request.len() as u16).to_be_bytes());
+    framed.extend_from_slice(request);
+    stream.write_all(&framed).await?;
+
+    let len = stream.read_u16().await? as usize;
+    let mut response = vec![0u8; len];
+    stream.read_exact(&mut response).await?;
+    Ok(Message::decode(&response)?)
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "resolvers_tests.rs"]
+mod tests;
@@ -0,0 +1,290 @@
+use super::*;
+use crate::infrastructure::dns::{Record, TYPE_CNAME};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+
+/// Builds the answer to a query; the flag tells whether it arrived over TCP
+type Responder = Arc<dyn Fn(&Message, bool) -> Message + Send + Sync>;
+
+/// Stub DNS server answering on the same UDP and TCP port of 127.0.0.1
+async fn stub_server(responder: Responder) -> SocketAddr {
+    let (udp, tcp) = loop {
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        if let Ok(tcp) = TcpListener::bind(udp.local_addr().unwrap()).await {
+            break (udp, tcp);
+        }
+    };
+    let addr = udp.local_addr().unwrap();
+
+    let udp_responder = responder.clone();
+    tokio::spawn(async move {
+        let mut buffer = vec![0u8; 512];
+        while let Ok((len, peer)) = udp.recv_from(&mut buffer).await {
+            let query = Message::decode(&buffer[..len]).unwrap();
+            let response = udp_responder(&query, false).encode().unwrap();
+            udp.send_to(&response, peer).await.unwrap();
+        }
+    });
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = tcp.accept().await {
+            let len = stream.read_u16().await.unwrap() as usize;
+            let mut request = vec![0u8; len];
+            stream.read_exact(&mut request).await.unwrap();
+            let query = Message::decode(&request).unwrap();
+            let response = responder(&query, true).encode().unwrap();
+            stream
+                .write_all(&(response.len() as u16).to_be_bytes())
+                .await
+                .unwrap();
+            stream.write_all(&response).await.unwrap();
+        }
+    });
+
+    addr
+}
+
+fn record(name: &str, data: RecordData) -> Record {
+    Record {
+        name: name.to_string(),
+        ttl: 60,
+        data,
+    }
+}
+
+/// Response to `query` with the records of its type among `records`
+fn answer(query: &Message, records: &[Record]) -> Message {
+    let question = &query.questions[0];
+    let answers = records
+        .iter()
+        .filter(|r| r.name == question.name)
+        .filter(|r| match r.data {
+            RecordData::A(_) => question.qtype == TYPE_A,
+            RecordData::Aaaa(_) => question.qtype == TYPE_AAAA,
+            RecordData::Ns(_) => question.qtype == TYPE_NS,
+            RecordData::Cname(_) => question.qtype != TYPE_CNAME,
+            _ => false,
+        })
+        .cloned()
+        .collect();
+    Message {
+        id: query.id,
+        flags: FLAG_RESPONSE | (query.flags & FLAG_RECURSION_DESIRED) | 0x0080,
+        questions: query.questions.clone(),
+        answers,
+        authority: Vec::new(),
+        additional: Vec::new(),
+    }
+}
+
+fn ips(values: &[&str]) -> Vec<IpAddr> {
+    values.iter().map(|v| v.parse().unwrap()).collect()
+}
+
+fn home_records() -> Vec<Record> {
+    vec![
+        record(
+            "home.example.com",
+            RecordData::A("203.0.113.7".parse().unwrap()),
+        ),
+        record(
+            "home.example.com",
+            RecordData::A("203.0.113.9".parse().unwrap()),
+        ),
+        record(
+            "home.example.com",
+            RecordData::Aaaa("2001:db8::7".parse().unwrap()),
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_direct_udp_and_tcp_queries() {
+    let server = stub_server(Arc::new(|query, _| answer(query, &home_records()))).await;
+
+    for transport in [DnsTransport::Udp, DnsTransport::Tcp] {
+        let resolver = NameserverResolver::new(vec![server]).with_transport(transport);
+        assert_eq!(
+            resolver.resolve("Home.Example.com").await.unwrap(),
+            ips(&["203.0.113.7", "203.0.113.9", "2001:db8::7"])
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_truncated_udp_answer_is_retried_over_tcp() {
+    let server = stub_server(Arc::new(|query, tcp| {
+        if tcp {
+            answer(query, &home_records())
+        } else {
+            let mut response = answer(query, &[]);
+            response.flags |= FLAG_TRUNCATED;
+            response
+        }
+    }))
+    .await;
+
+    let resolver = NameserverResolver::new(vec![server]);
+    assert_eq!(
+        resolver.resolve("home.example.com").await.unwrap(),
+        ips(&["203.0.113.7", "203.0.113.9", "2001:db8::7"])
+    );
+}
+
+#[tokio::test]
+async fn test_nxdomain_and_cname_chains() {
+    let server = stub_server(Arc::new(|query, _| {
+        let mut records = home_records();
+        records.push(record(
+            "alias.example.com",
+            RecordData::Cname("home.example.com".to_string()),
+        ));
+        let mut response = answer(query, &records);
+        if query.questions[0].name == "missing.example.com" {
+            response.flags |= RCODE_NXDOMAIN as u16;
+        }
+        response
+    }))
+    .await;
+    let resolver = NameserverResolver::new(vec![server]);
+
+    let error = resolver.resolve("missing.example.com").await.unwrap_err();
+    assert!(error.to_string().contains("NXDOMAIN"));
+
+    assert_eq!(
+        resolver.resolve("alias.example.com").await.unwrap(),
+        ips(&["203.0.113.7", "203.0.113.9", "2001:db8::7"])
+    );
+}
+
+#[tokio::test]
+async fn test_falls_back_to_next_nameserver() {
+    let failing = stub_server(Arc::new(|query, _| {
+        let mut response = answer(query, &[]);
+        response.flags |= 2; // SERVFAIL
+        response
+    }))
+    .await;
+    let working = stub_server(Arc::new(|query, _| answer(query, &home_records()))).await;
+
+    let resolver = NameserverResolver::new(vec![failing, working]);
+    assert_eq!(resolver.resolve("home.example.com").await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_authoritative_mode_bypasses_stale_cache() {
+    // Only answers non-recursive queries, like an authoritative server
+    let authoritative = stub_server(Arc::new(|query, _| {
+        assert_eq!(query.flags & FLAG_RECURSION_DESIRED, 0);
+        answer(
+            query,
+            &[record(
+                "home.example.com",
+                RecordData::A("203.0.113.8".parse().unwrap()),
+            )],
+        )
+    }))
+    .await;
+
+    // Caching resolver with an outdated address, delegating example.com to ns1 (with glue)
+    let recursive = stub_server(Arc::new(|query, _| {
+        let mut response = answer(
+            query,
+            &[
+                record(
+                    "home.example.com",
+                    RecordData::A("192.0.2.1".parse().unwrap()),
+                ),
+                record("example.com", RecordData::Ns("ns1.example.com".to_string())),
+            ],
+        );
+        if query.questions[0].qtype == TYPE_NS {
+            response.additional.push(record(
+                "ns1.example.com",
+                RecordData::A("127.0.0.1".parse().unwrap()),
+            ));
+        }
+        response
+    }))
+    .await;
+
+    let cached = NameserverResolver::new(vec![recursive]);
+    assert_eq!(
+        cached.resolve("home.example.com").await.unwrap(),
+        ips(&["192.0.2.1"])
+    );
+
+    let resolver = NameserverResolver::new(vec![recursive])
+        .with_authoritative(true)
+        .with_authoritative_port(authoritative.port());
+    assert_eq!(
+        resolver.resolve("home.example.com").await.unwrap(),
+        ips(&["203.0.113.8"])
+    );
+}
+
+#[tokio::test]
+async fn test_dns_over_https() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            // Read the request head, then the body announced by Content-Length
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            let head_end = loop {
+                let len = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..len]);
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&request[..head_end]).to_lowercase();
+            assert!(head.starts_with("post /dns-query"));
+            assert!(head.contains("content-type: application/dns-message"));
+            let body_len: usize = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            while request.len() < head_end + body_len {
+                let len = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..len]);
+            }
+
+            let query = Message::decode(&request[head_end..]).unwrap();
+            assert_eq!(query.id, 0);
+            let body = answer(&query, &home_records()).encode().unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(&body).await.unwrap();
+        }
+    });
+
+    let resolver = DohResolver::new(format!("http://{}/dns-query", addr));
+    assert_eq!(
+        resolver.resolve("home.example.com").await.unwrap(),
+        ips(&["203.0.113.7", "203.0.113.9", "2001:db8::7"])
+    );
+}
+
+#[test]
+fn test_parse_resolv_conf() {
+    let content = "# generated\nsearch example.com\nnameserver 192.0.2.53\nnameserver fe80::1%eth0\n  nameserver 2001:db8::53\nnameserver bogus\n";
+    assert_eq!(
+        parse_resolv_conf(content),
+        vec![
+            "192.0.2.53:53".parse::<SocketAddr>().unwrap(),
+            "[fe80::1]:53".parse().unwrap(),
+            "[2001:db8::53]:53".parse().unwrap(),
+        ]
+    );
+}
@@ -0,0 +1,427 @@
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::domain::entities::{EntryMigration, IpChange, IpEntry, LegacyFiles, IP_ENTRY_VERSION};
+use crate::domain::ports::IpRepository;
+use crate::infrastructure::repositories::{legacy_entries, migrate_entry, updated_entry};
+
+/// File name of the database in the storage directory
+pub const SQLITE_DATABASE: &str = "ddns-updater.db";
+
+/// A schema migration, given the directory of the JSON files to import
+type Migration = fn(&Transaction, Option<&Path>) -> rusqlite::Result<()>;
+
+/// Schema migrations; the database's `user_version` is the number of them applied
+const MIGRATIONS: &[Migration] = &[create_tables_and_import_json];
+
+/// SQLite IP repository: the current entry of every host plus an append-only history of its
+/// address changes
+pub struct SqliteIpRepository {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteIpRepository {
+    /// Open the database, creating it if needed, and bring its schema up to date. A new
+    /// database imports the `<hostname>.json` entries found in `json_dir`.
+    pub fn open(
+        db_path: &Path,
+        json_dir: Option<&Path>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(parent) = db_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut connection = Connection::open(db_path)?;
+        migrate(&mut connection, json_dir)
+            .map_err(|e| format!("Failed to migrate {}: {}", db_path.display(), e))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Database in the storage directory, importing the JSON entries stored there
+    pub fn in_storage_dir(
+        storage_dir: &Path,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::open(&storage_dir.join(SQLITE_DATABASE), Some(storage_dir))
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Apply the migrations the database hasn't seen yet, each in its own transaction
+fn migrate(connection: &mut Connection, json_dir: Option<&Path>) -> rusqlite::Result<()> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        migration(&transaction, json_dir)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+/// Version 1: host and history tables, filled with the existing JSON entries
+fn create_tables_and_import_json(
+    transaction: &Transaction,
+    json_dir: Option<&Path>,
+) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE hosts (
+             hostname TEXT PRIMARY KEY,
+             entry TEXT NOT NULL
+         );
+         CREATE TABLE ip_changes (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             hostname TEXT NOT NULL,
+             old_ips TEXT NOT NULL,
+             new_ips TEXT NOT NULL,
+             changed_at TEXT NOT NULL,
+             files TEXT NOT NULL,
+             outcome TEXT NOT NULL,
+             reloaded INTEGER NOT NULL,
+             error TEXT
+         );
+         CREATE INDEX ip_changes_by_host ON ip_changes (hostname, changed_at);",
+    )?;
+
+    let Some(entries) = json_dir.and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Ok(());
+    };
+    let mut hostnames = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Some((entry, _)) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| migrate_entry(&content).ok())
+        else {
+            continue;
+        };
+        transaction.execute(
+            "INSERT OR IGNORE INTO hosts (hostname, entry) VALUES (?1, ?2)",
+            params![entry.hostname, to_json(&entry)?],
+        )?;
+        hostnames.push(entry.hostname);
+    }
+    // Legacy `<host>_ip.txt` files only fill in hosts without a JSON entry
+    let legacy = json_dir.map(|dir| legacy_entries(dir, &hostnames));
+    for (entry, _) in legacy.unwrap_or_default() {
+        transaction.execute(
+            "INSERT OR IGNORE INTO hosts (hostname, entry) VALUES (?1, ?2)",
+            params![entry.hostname, to_json(&entry)?],
+        )?;
+    }
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(column: usize, json: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// Stored entry in the current layout, with the version it was stored with
+fn entry_from_json(column: usize, json: &str) -> rusqlite::Result<(IpEntry, u32)> {
+    migrate_entry(json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e)
+    })
+}
+
+/// Timestamps are stored in one fixed-width UTC format so they sort as text
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn load_entry(connection: &Connection, hostname: &str) -> rusqlite::Result<Option<IpEntry>> {
+    connection
+        .query_row(
+            "SELECT entry FROM hosts WHERE hostname = ?1",
+            [hostname],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?
+        .map(|json| Ok(entry_from_json(0, &json)?.0))
+        .transpose()
+}
+
+/// Every stored entry with the version it was stored with, by hostname
+fn load_entries(connection: &Connection) -> rusqlite::Result<Vec<(IpEntry, u32)>> {
+    let mut statement = connection.prepare("SELECT entry FROM hosts ORDER BY hostname")?;
+    let entries = statement
+        .query_map([], |row| entry_from_json(0, &row.get::<_, String>(0)?))?
+        .collect();
+    entries
+}
+
+/// Entries of the legacy files of `legacy` with the files, as migrations to the current layout;
+/// their hostnames are told by the known ones and those stored
+fn legacy_migrations(
+    connection: &Connection,
+    legacy: &LegacyFiles,
+) -> rusqlite::Result<Vec<(IpEntry, EntryMigration, PathBuf)>> {
+    let mut hostnames = legacy.hostnames.clone();
+    hostnames.extend(
+        load_entries(connection)?
+            .into_iter()
+            .map(|(entry, _)| entry.hostname),
+    );
+    let mut migrations: Vec<_> = legacy
+        .dirs
+        .iter()
+        .flat_map(|dir| legacy_entries(dir, &hostnames))
+        .map(|(entry, path)| {
+            let migration = EntryMigration {
+                hostname: entry.hostname.clone(),
+                location: path.display().to_string(),
+                from_version: 0,
+                to_version: IP_ENTRY_VERSION,
+            };
+            (entry, migration, path)
+        })
+        .collect();
+    migrations.sort_by(|(_, a, _), (_, b, _)| a.location.cmp(&b.location));
+    Ok(migrations)
+}
+
+/// Entries stored in an older layout, as migrations to the current one
+fn outdated_entries(connection: &Connection) -> rusqlite::Result<Vec<(IpEntry, EntryMigration)>> {
+    Ok(load_entries(connection)?
+        .into_iter()
+        .filter(|(_, version)| *version < IP_ENTRY_VERSION)
+        .map(|(entry, from_version)| {
+            let migration = EntryMigration {
+                hostname: entry.hostname.clone(),
+                location: format!("{} (hosts)", SQLITE_DATABASE),
+                from_version,
+                to_version: IP_ENTRY_VERSION,
+            };
+            (entry, migration)
+        })
+        .collect())
+}
+
+fn save_entry(connection: &Connection, entry: &IpEntry) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT INTO hosts (hostname, entry) VALUES (?1, ?2)
+         ON CONFLICT (hostname) DO UPDATE SET entry = excluded.entry",
+        params![entry.hostname, to_json(entry)?],
+    )?;
+    Ok(())
+}
+
+#[async_trait]
+impl IpRepository for SqliteIpRepository {
+    async fn store_ip(
+        &self,
+        hostname: &str,
+        ip: IpAddr,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.store_ips(hostname, &[ip]).await
+    }
+
+    async fn store_ips(
+        &self,
+        hostname: &str,
+        ips: &[IpAddr],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        // The existing entry is updated in place, so its comment and creation time survive
+        let existing = load_entry(&transaction, hostname)?;
+        if let Some(entry) = updated_entry(hostname, existing, ips) {
+            save_entry(&transaction, &entry)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn load_ip(
+        &self,
+        hostname: &str,
+    ) -> Result<Option<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.get_ip_entry(hostname).await?.map(|entry| entry.ip))
+    }
+
+    async fn get_ip_entry(
+        &self,
+        hostname: &str,
+    ) -> Result<Option<IpEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(load_entry(&self.connection(), hostname)?)
+    }
+
+    async fn list_all_entries(
+        &self,
+    ) -> Result<Vec<IpEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let entries = load_entries(&self.connection())?;
+        Ok(entries.into_iter().map(|(entry, _)| entry).collect())
+    }
+
+    async fn delete_entry(
+        &self,
+        hostname: &str,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let deleted = self
+            .connection()
+            .execute("DELETE FROM hosts WHERE hostname = ?1", [hostname])?;
+        Ok(deleted > 0)
+    }
+
+    async fn save_entry(
+        &self,
+        entry: &IpEntry,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(save_entry(&self.connection(), entry)?)
+    }
+
+    async fn initialize_host_file(
+        &self,
+        hostname: &str,
+        resolved_ips: &[IpAddr],
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(&first_ip) = resolved_ips.first() else {
+            return Ok(false);
+        };
+        let comment = if resolved_ips.iter().all(|ip| ip.is_unspecified()) {
+            "Initial DNS host entry created at first startup (will be updated with real IP)"
+        } else {
+            "Initial DNS host entry created at first startup with resolved IP"
+        };
+        let mut entry = IpEntry::new(first_ip, hostname.to_string(), Some(comment.to_string()));
+        entry.update_ips(resolved_ips);
+        let inserted = self.connection().execute(
+            "INSERT OR IGNORE INTO hosts (hostname, entry) VALUES (?1, ?2)",
+            params![hostname, to_json(&entry)?],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    async fn pending_migrations(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let connection = self.connection();
+        let outdated = outdated_entries(&connection)?;
+        let legacy = legacy_migrations(&connection, legacy)?;
+        Ok(outdated
+            .into_iter()
+            .map(|(_, migration)| migration)
+            .chain(legacy.into_iter().map(|(_, migration, _)| migration))
+            .collect())
+    }
+
+    async fn migrate(
+        &self,
+        legacy: &LegacyFiles,
+    ) -> Result<Vec<EntryMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        let mut migrations = Vec::new();
+        for (entry, migration) in outdated_entries(&transaction)? {
+            save_entry(&transaction, &entry)?;
+            migrations.push(migration);
+        }
+        // A legacy file of a host with an entry is stale and only removed, the entry wins
+        let legacy = legacy_migrations(&transaction, legacy)?;
+        for (entry, migration, _) in &legacy {
+            transaction.execute(
+                "INSERT OR IGNORE INTO hosts (hostname, entry) VALUES (?1, ?2)",
+                params![entry.hostname, to_json(entry)?],
+            )?;
+            migrations.push(migration.clone());
+        }
+        transaction.commit()?;
+        for (_, _, path) in legacy {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(migrations)
+    }
+
+    async fn record_change(
+        &self,
+        change: &IpChange,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.connection().execute(
+            "INSERT INTO ip_changes
+                 (hostname, old_ips, new_ips, changed_at, files, outcome, reloaded, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                change.hostname,
+                to_json(&change.old_ips)?,
+                to_json(&change.new_ips)?,
+                timestamp(&change.changed_at),
+                to_json(&change.files)?,
+                change.outcome.to_string(),
+                change.reloaded,
+                change.error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    async fn change_history(
+        &self,
+        hostname: Option<&str>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<IpChange>, Box<dyn std::error::Error + Send + Sync>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT hostname, old_ips, new_ips, changed_at, files, outcome, reloaded, error
+             FROM ip_changes
+             WHERE (?1 IS NULL OR hostname = ?1) AND (?2 IS NULL OR changed_at >= ?2)
+             ORDER BY changed_at, id",
+        )?;
+        let changes = statement
+            .query_map(params![hostname, since.as_ref().map(timestamp)], |row| {
+                let changed_at: String = row.get(3)?;
+                let outcome: String = row.get(5)?;
+                Ok(IpChange {
+                    hostname: row.get(0)?,
+                    old_ips: from_json(1, &row.get::<_, String>(1)?)?,
+                    new_ips: from_json(2, &row.get::<_, String>(2)?)?,
+                    changed_at: DateTime::parse_from_rfc3339(&changed_at)
+                        .map_err(|e| {
+                            rusqlite::Error::FromSqlConversionFailure(
+                                3,
+                                rusqlite::types::Type::Text,
+                                e.into(),
+                            )
+                        })?
+                        .with_timezone(&Utc),
+                    files: from_json(4, &row.get::<_, String>(4)?)?,
+                    outcome: outcome.parse().map_err(|e: String| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            5,
+                            rusqlite::types::Type::Text,
+                            e.into(),
+                        )
+                    })?,
+                    reloaded: row.get(6)?,
+                    error: row.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<IpChange>>>()?;
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+#[path = "sqlite_repository_tests.rs"]
+mod tests;
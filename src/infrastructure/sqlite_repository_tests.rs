@@ -0,0 +1,264 @@
+use super::*;
+use crate::domain::entities::{ChangeOutcome, ChangedFile};
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+fn change(hostname: &str, old: &str, new: &str, changed_at: &str) -> IpChange {
+    IpChange {
+        hostname: hostname.to_string(),
+        old_ips: vec![old.parse().unwrap()],
+        new_ips: vec![new.parse().unwrap()],
+        changed_at: changed_at.parse().unwrap(),
+        files: vec![ChangedFile {
+            path: PathBuf::from("/etc/nginx/site.conf"),
+            backup_path: Some(PathBuf::from("/etc/nginx/site.conf.bak.20260101_000000")),
+        }],
+        outcome: ChangeOutcome::Applied,
+        reloaded: true,
+        error: None,
+    }
+}
+
+#[tokio::test]
+async fn test_store_keeps_creation_time_and_comment() {
+    let temp_dir = tempdir().unwrap();
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+
+    assert!(repo
+        .initialize_host_file("example.com", &[ip])
+        .await
+        .unwrap());
+    assert!(!repo
+        .initialize_host_file("example.com", &[ip])
+        .await
+        .unwrap());
+    let created = repo.get_ip_entry("example.com").await.unwrap().unwrap();
+
+    repo.store_ips(
+        "example.com",
+        &["10.0.0.1".parse().unwrap(), "2001:db8::1".parse().unwrap()],
+    )
+    .await
+    .unwrap();
+    let updated = repo.get_ip_entry("example.com").await.unwrap().unwrap();
+    assert_eq!(updated.ip, "10.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(
+        updated.ipv6,
+        vec!["2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap()]
+    );
+    assert_eq!(updated.created_at, created.created_at);
+    assert_eq!(updated.comment, created.comment);
+
+    repo.store_ip("other.com", ip).await.unwrap();
+    let hostnames: Vec<String> = repo
+        .list_all_entries()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.hostname)
+        .collect();
+    assert_eq!(hostnames, vec!["example.com", "other.com"]);
+
+    assert!(repo.delete_entry("other.com").await.unwrap());
+    assert!(!repo.delete_entry("other.com").await.unwrap());
+    assert_eq!(repo.load_ip("other.com").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_change_history_is_filtered_by_host_and_time() {
+    let temp_dir = tempdir().unwrap();
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+
+    let first = change(
+        "a.example.com",
+        "10.0.0.1",
+        "10.0.0.2",
+        "2026-01-01T10:00:00Z",
+    );
+    let mut second = change(
+        "b.example.com",
+        "10.0.1.1",
+        "10.0.1.2",
+        "2026-01-02T10:00:00Z",
+    );
+    second.outcome = ChangeOutcome::RolledBack;
+    second.reloaded = false;
+    second.error = Some("nginx -t failed".to_string());
+    let third = change(
+        "a.example.com",
+        "10.0.0.2",
+        "10.0.0.3",
+        "2026-01-03T10:00:00Z",
+    );
+    for change in [&third, &first, &second] {
+        repo.record_change(change).await.unwrap();
+    }
+
+    let all = repo.change_history(None, None).await.unwrap();
+    assert_eq!(all, vec![first.clone(), second.clone(), third.clone()]);
+    assert_eq!(
+        repo.change_history(Some("a.example.com"), None)
+            .await
+            .unwrap(),
+        vec![first, third.clone()]
+    );
+    let since = "2026-01-02T00:00:00Z".parse().unwrap();
+    assert_eq!(
+        repo.change_history(Some("a.example.com"), Some(since))
+            .await
+            .unwrap(),
+        vec![third]
+    );
+    assert!(repo
+        .change_history(Some("c.example.com"), None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_new_database_imports_json_entries_once() {
+    let temp_dir = tempdir().unwrap();
+    let entry = IpEntry::new(
+        "192.168.1.1".parse().unwrap(),
+        "example.com".to_string(),
+        Some("imported".to_string()),
+    );
+    std::fs::write(
+        temp_dir.path().join("example.com.json"),
+        serde_json::to_string_pretty(&entry).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(temp_dir.path().join("broken.json"), "{").unwrap();
+
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+    assert_eq!(repo.list_all_entries().await.unwrap(), vec![entry.clone()]);
+    repo.delete_entry("example.com").await.unwrap();
+    drop(repo);
+
+    // Reopening an up-to-date database doesn't import again
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+    assert!(repo.list_all_entries().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_import_and_migrate_older_layouts() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(
+        temp_dir.path().join("office_example_com_ip.txt"),
+        "10.0.0.5\n",
+    )
+    .unwrap();
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+    // Without the hostname, the underscores of the file name can't be told from dots
+    let legacy = repo
+        .get_ip_entry("office_example_com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(legacy.ip, "10.0.0.5".parse::<IpAddr>().unwrap());
+
+    // An entry stored before versioning
+    repo.connection()
+        .execute(
+            "INSERT INTO hosts (hostname, entry) VALUES (?1, ?2)",
+            params![
+                "home.example.com",
+                r#"{"ip": "192.168.1.10", "hostname": "home.example.com", "comment": null,
+                    "created_at": "2025-01-01T00:00:00Z", "updated_at": "2025-01-01T00:00:00Z"}"#
+            ],
+        )
+        .unwrap();
+    let no_legacy_files = LegacyFiles::default();
+    let pending = repo.pending_migrations(&no_legacy_files).await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].hostname, "home.example.com");
+    assert_eq!(pending[0].from_version, 1);
+
+    assert_eq!(repo.migrate(&no_legacy_files).await.unwrap(), pending);
+    assert!(repo
+        .pending_migrations(&no_legacy_files)
+        .await
+        .unwrap()
+        .is_empty());
+    let entry = repo
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        entry.ipv4,
+        vec!["192.168.1.10".parse::<std::net::Ipv4Addr>().unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_imports_legacy_files_of_another_directory() {
+    let temp_dir = tempdir().unwrap();
+    let working_dir = tempdir().unwrap();
+    for (file, ip) in [
+        ("home_example_com_ip.txt", "192.168.1.10"),
+        ("office_example_com_ip.txt", "10.0.0.5"),
+    ] {
+        std::fs::write(working_dir.path().join(file), ip).unwrap();
+    }
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+    repo.store_ips("office.example.com", &["10.0.0.9".parse().unwrap()])
+        .await
+        .unwrap();
+    let legacy_files = LegacyFiles {
+        dirs: vec![working_dir.path().to_path_buf()],
+        hostnames: vec!["home.example.com".to_string()],
+    };
+
+    // The stored host tells the other file's hostname
+    let pending = repo.pending_migrations(&legacy_files).await.unwrap();
+    assert_eq!(
+        pending
+            .iter()
+            .map(|migration| migration.hostname.as_str())
+            .collect::<Vec<_>>(),
+        vec!["home.example.com", "office.example.com"]
+    );
+
+    assert_eq!(repo.migrate(&legacy_files).await.unwrap(), pending);
+    assert!(std::fs::read_dir(working_dir.path())
+        .unwrap()
+        .next()
+        .is_none());
+    let home = repo
+        .get_ip_entry("home.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(home.ip, "192.168.1.10".parse::<IpAddr>().unwrap());
+    // The stored entry wins over the stale legacy file
+    let office = repo
+        .get_ip_entry("office.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(office.ip, "10.0.0.9".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_first_entry_holds_every_resolved_address() {
+    let temp_dir = tempdir().unwrap();
+    let repo = SqliteIpRepository::in_storage_dir(temp_dir.path()).unwrap();
+    let ips: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "2001:db8::7".parse().unwrap(),
+    ];
+
+    assert!(repo
+        .initialize_host_file("pool.example.com", &ips)
+        .await
+        .unwrap());
+    let entry = repo
+        .get_ip_entry("pool.example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.addresses(), ips);
+}
@@ -0,0 +1,238 @@
+use std::net::IpAddr;
+
+use crate::domain::entities::IpFamily;
+use crate::domain::value_objects::{AddressDiff, IpNetwork};
+
+/// An address or network of an allow list as found by a handler's scanner
+pub(crate) struct AllowEntry {
+    pub network: IpNetwork,
+    /// The entry as written, used to keep bare vs. CIDR notation
+    pub text: String,
+    /// Entries sharing a directive, matcher line or middleware have the same group
+    pub group: usize,
+    /// The group is tagged with a `# DDNS: <hostname>` comment for the host being updated
+    pub tagged: bool,
+}
+
+/// A change to one allow list entry; handlers render these in their own syntax
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum EntryEdit {
+    Replace {
+        entry: usize,
+        value: String,
+    },
+    Remove {
+        entry: usize,
+    },
+    /// Add `value` right after the entry, in the same directive or list where possible
+    InsertAfter {
+        entry: usize,
+        value: String,
+    },
+}
+
+/// Work out how the entries change for an address diff.
+///
+/// Entries managing a removed address are rewritten to the added address of the same family
+/// (all of them, as the host may be listed in several places). If the group already lists the
+/// new value, the old entry is dropped instead. Leftover removed addresses are dropped and
+/// leftover added ones are inserted after the last entry of the same family that belongs to the
+/// hostname. Without any stored address of a family, a tagged group holding a single entry of
+/// that family is taken over (legacy setups).
+pub(crate) fn plan_edits(
+    entries: &[AllowEntry],
+    diff: &AddressDiff,
+    rewrite_prefix: bool,
+) -> Vec<EntryEdit> {
+    let mut edits = Vec::new();
+    let mut touched = vec![false; entries.len()];
+    // Entry values after the planned edits, to avoid duplicates within a group
+    let mut planned: Vec<Option<IpNetwork>> = entries.iter().map(|e| Some(e.network)).collect();
+    // Tagged entries adopted for a family without stored addresses
+    let mut taken_over: Vec<(IpFamily, usize)> = Vec::new();
+
+    for (old_ip, new_ip) in diff.replacements() {
+        for (index, entry) in entries.iter().enumerate() {
+            if touched[index] || !entry.network.manages(old_ip, rewrite_prefix) {
+                continue;
+            }
+            let Some(updated) = entry.network.updated_for(old_ip, new_ip, rewrite_prefix) else {
+                continue;
+            };
+            touched[index] = true;
+
+            if group_contains(entries, &planned, entry.group, updated) {
+                planned[index] = None;
+                edits.push(EntryEdit::Remove { entry: index });
+            } else {
+                planned[index] = Some(updated);
+                edits.push(EntryEdit::Replace {
+                    entry: index,
+                    value: updated.format_like(&entry.text),
+                });
+            }
+        }
+    }
+
+    for old_ip in diff.removals() {
+        for (index, entry) in entries.iter().enumerate() {
+            // Networks stay; they may still cover other addresses
+            if !touched[index] && entry.network == IpNetwork::host(old_ip) {
+                touched[index] = true;
+                planned[index] = None;
+                edits.push(EntryEdit::Remove { entry: index });
+            }
+        }
+    }
+
+    for new_ip in diff.additions() {
+        let host = IpNetwork::host(new_ip);
+        if planned
+            .iter()
+            .flatten()
+            .any(|network| network.manages(new_ip, rewrite_prefix))
+        {
+            continue;
+        }
+
+        // Entries of the hostname's other addresses that stay in place
+        let family = IpFamily::of(new_ip);
+        let anchors: Vec<IpAddr> = diff
+            .unchanged
+            .iter()
+            .chain(&diff.removed)
+            .filter(|ip| IpFamily::of(**ip) == family)
+            .copied()
+            .collect();
+        let anchor = (0..entries.len())
+            .rev()
+            .find(|&index| {
+                planned[index].is_some()
+                    && entries[index].network.family() == family
+                    && anchors
+                        .iter()
+                        .any(|ip| entries[index].network.manages(*ip, rewrite_prefix))
+            })
+            .or_else(|| {
+                taken_over
+                    .iter()
+                    .find(|(f, _)| *f == family)
+                    .map(|(_, i)| *i)
+            });
+
+        match anchor {
+            Some(anchor) => {
+                let anchor_entry = &entries[anchor];
+                let value = if anchor_entry.network.is_host() {
+                    host.format_like(&anchor_entry.text)
+                } else {
+                    new_ip.to_string()
+                };
+                planned.push(Some(host));
+                edits.push(EntryEdit::InsertAfter {
+                    entry: anchor,
+                    value,
+                });
+            }
+            None if !has_stored_addresses(diff, family) => {
+                let Some(index) = single_tagged_entry(entries, family) else {
+                    continue;
+                };
+                if touched[index] {
+                    continue;
+                }
+                let current = entries[index].network.address();
+                let Some(updated) =
+                    entries[index]
+                        .network
+                        .updated_for(current, new_ip, rewrite_prefix)
+                else {
+                    continue;
+                };
+                if updated == entries[index].network {
+                    continue;
+                }
+                touched[index] = true;
+                planned[index] = Some(updated);
+                taken_over.push((family, index));
+                edits.push(EntryEdit::Replace {
+                    entry: index,
+                    value: updated.format_like(&entries[index].text),
+                });
+            }
+            None => {}
+        }
+    }
+
+    edits
+}
+
+/// Apply `(start, end, replacement)` edits to the content; spans must not overlap
+pub(crate) fn apply_span_edits(content: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    // Stable sort keeps insertions at the same position in planning order
+    edits.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut output = String::with_capacity(content.len());
+    let mut position = 0;
+    for (start, end, replacement) in edits {
+        output.push_str(&content[position..start]);
+        output.push_str(&replacement);
+        position = end;
+    }
+    output.push_str(&content[position..]);
+    output
+}
+
+/// Byte range of the whole line around `start..end`, including its line break
+pub(crate) fn line_span(content: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[end..]
+        .find('\n')
+        .map_or(content.len(), |i| end + i + 1);
+    (line_start, line_end)
+}
+
+fn group_contains(
+    entries: &[AllowEntry],
+    planned: &[Option<IpNetwork>],
+    group: usize,
+    network: IpNetwork,
+) -> bool {
+    entries
+        .iter()
+        .zip(planned)
+        .any(|(entry, planned)| entry.group == group && *planned == Some(network))
+}
+
+fn has_stored_addresses(diff: &AddressDiff, family: IpFamily) -> bool {
+    diff.unchanged
+        .iter()
+        .chain(&diff.removed)
+        .any(|ip| IpFamily::of(*ip) == family)
+}
+
+/// The only entry of the family in the first tagged group that has exactly one
+fn single_tagged_entry(entries: &[AllowEntry], family: IpFamily) -> Option<usize> {
+    let mut groups: Vec<usize> = entries
+        .iter()
+        .filter(|entry| entry.tagged)
+        .map(|entry| entry.group)
+        .collect();
+    groups.dedup();
+
+    groups.into_iter().find_map(|group| {
+        let mut candidates = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.group == group && entry.network.family() == family);
+        match (candidates.next(), candidates.next()) {
+            (Some((index, _)), None) => Some(index),
+            _ => None,
+        }
+    })
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "allow_list_tests.rs"]
+mod tests;
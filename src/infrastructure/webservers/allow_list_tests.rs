@@ -0,0 +1,139 @@
+use super::*;
+
+fn ips(values: &[&str]) -> Vec<IpAddr> {
+    values.iter().map(|v| v.parse().unwrap()).collect()
+}
+
+/// Entries as `(value, group, tagged)`
+fn entries(values: &[(&str, usize, bool)]) -> Vec<AllowEntry> {
+    values
+        .iter()
+        .map(|&(text, group, tagged)| AllowEntry {
+            network: text.parse().unwrap(),
+            text: text.to_string(),
+            group,
+            tagged,
+        })
+        .collect()
+}
+
+#[test]
+fn test_replacement_updates_every_entry_and_avoids_duplicates() {
+    let entries = entries(&[
+        ("203.0.113.7", 0, false),
+        ("203.0.113.8", 0, false),
+        ("203.0.113.7/32", 1, false),
+    ]);
+    let diff = AddressDiff::replacement(
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.8".parse().unwrap(),
+    );
+
+    assert_eq!(
+        plan_edits(&entries, &diff, false),
+        vec![
+            EntryEdit::Remove { entry: 0 },
+            EntryEdit::Replace {
+                entry: 2,
+                value: "203.0.113.8/32".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_removal_keeps_networks() {
+    let entries = entries(&[("203.0.113.7", 0, false), ("203.0.113.0/24", 0, false)]);
+    let diff = AddressDiff::between(
+        &ips(&["203.0.113.7", "203.0.113.9"]),
+        &ips(&["203.0.113.9"]),
+    );
+
+    assert_eq!(
+        plan_edits(&entries, &diff, true),
+        vec![EntryEdit::Remove { entry: 0 }]
+    );
+}
+
+#[test]
+fn test_additions_follow_the_last_entry_of_the_family() {
+    let entries = entries(&[
+        ("203.0.113.7", 0, false),
+        ("2001:db8::7", 1, false),
+        ("203.0.113.9", 2, false),
+    ]);
+    let stored = ips(&["203.0.113.7", "203.0.113.9", "2001:db8::7"]);
+    let current = ips(&[
+        "203.0.113.7",
+        "203.0.113.9",
+        "203.0.113.10",
+        "2001:db8::7",
+        "2001:db8::8",
+    ]);
+
+    assert_eq!(
+        plan_edits(&entries, &AddressDiff::between(&stored, &current), false),
+        vec![
+            EntryEdit::InsertAfter {
+                entry: 2,
+                value: "203.0.113.10".to_string()
+            },
+            EntryEdit::InsertAfter {
+                entry: 1,
+                value: "2001:db8::8".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_additions_already_covered_are_skipped() {
+    let entries = entries(&[("203.0.113.7", 0, false), ("198.51.100.0/24", 1, false)]);
+    let diff = AddressDiff::between(
+        &ips(&["203.0.113.7"]),
+        &ips(&["203.0.113.7", "198.51.100.4"]),
+    );
+
+    assert!(plan_edits(&entries, &diff, true).is_empty());
+    assert_eq!(
+        plan_edits(&entries, &diff, false),
+        vec![EntryEdit::InsertAfter {
+            entry: 0,
+            value: "198.51.100.4".to_string()
+        }]
+    );
+}
+
+#[test]
+fn test_tagged_entry_is_taken_over_without_stored_addresses() {
+    let entries = entries(&[("192.0.2.1", 0, false), ("203.0.113.7", 1, true)]);
+    let diff = AddressDiff::between(&[], &ips(&["203.0.113.8", "203.0.113.9"]));
+
+    assert_eq!(
+        plan_edits(&entries, &diff, false),
+        vec![
+            EntryEdit::Replace {
+                entry: 1,
+                value: "203.0.113.8".to_string()
+            },
+            EntryEdit::InsertAfter {
+                entry: 1,
+                value: "203.0.113.9".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_apply_span_edits_and_line_span() {
+    let content = "a 1\nb 2\nc 3\n";
+    let (start, end) = line_span(content, 5, 6);
+    assert_eq!((start, end), (4, 8));
+
+    let edits = vec![
+        (start, end, String::new()),
+        (4, 4, "x 9\n".to_string()),
+        (2, 3, "7".to_string()),
+    ];
+    assert_eq!(apply_span_edits(content, edits), "a 7\nx 9\nc 3\n");
+}
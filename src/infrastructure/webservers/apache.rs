@@ -1,97 +1,81 @@
 use async_trait::async_trait;
-use regex::Regex;
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::process::Command;
 use tokio::fs;
 
-use crate::domain::entities::{WebServerConfig, WebServerType};
+use crate::domain::entities::{BackupFile, WebServerConfig, WebServerType};
 use crate::domain::ports::WebServerHandler;
+use crate::domain::value_objects::{AddressDiff, IpNetwork};
+use crate::infrastructure::files::write_atomically;
+
+use super::allow_list::{apply_span_edits, plan_edits, AllowEntry, EntryEdit};
+use super::comments::parse_ddns_tag;
 
 /// Apache web server handler
-pub struct ApacheHandler;
+///
+/// Updates the addresses in existing `Require ip` (2.4) and `Allow from` (2.2) directives.
+/// Apache does not allow trailing comments, so a directive is tagged for a hostname with a
+/// `# DDNS: <hostname>` comment on the line directly above it.
+pub struct ApacheHandler {
+    backup_dir: Option<PathBuf>,
+}
 
 impl ApacheHandler {
     pub fn new() -> Self {
-        Self
+        Self { backup_dir: None }
+    }
+
+    pub fn with_backup_dir(backup_dir: Option<PathBuf>) -> Self {
+        Self { backup_dir }
     }
 
     async fn backup_file(
         &self,
         config_path: &std::path::Path,
     ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let backup_path = config_path.with_extension(format!("bak.{}", timestamp));
-        fs::copy(config_path, &backup_path).await?;
-        Ok(backup_path)
+        super::backup::backup_file(config_path, self.backup_dir.as_deref()).await
+    }
+
+    /// New content of the config file with the diff applied; None if no managed entry changes
+    async fn apache_config_update(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let content = fs::read_to_string(&config.path).await?;
+        Ok(apply_access_diff(
+            &content,
+            hostname,
+            diff,
+            config.rewrite_prefix,
+        ))
     }
 
     async fn update_apache_config(
         &self,
-        config_path: &std::path::Path,
+        config: &WebServerConfig,
         hostname: &str,
-        old_ip: Option<IpAddr>,
-        new_ip: IpAddr,
+        diff: &AddressDiff,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let content = fs::read_to_string(config_path).await?;
-        let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-        let mut updated = false;
-
-        // Comment pattern for hostname identification
-        let hostname_comment = format!("# DDNS: {}", hostname);
-
-        // Remove old entries for this hostname
-        if let Some(old_ip) = old_ip {
-            let old_require_pattern = format!("Require ip {}", old_ip);
-            let has_hostname_comment = lines.iter().any(|l| l.contains(&hostname_comment));
-            lines.retain(|line| {
-                !line.trim().starts_with(&old_require_pattern) || !has_hostname_comment
-            });
-        }
-
-        // Find Directory or Location blocks and add new Require rule
-        let directory_regex = Regex::new(r"^\s*<Directory\s+.*>\s*$")?;
-        let location_regex = Regex::new(r"^\s*<Location\s+.*>\s*$")?;
-        let virtualhost_regex = Regex::new(r"^\s*<VirtualHost\s+.*>\s*$")?;
-
-        for i in 0..lines.len() {
-            if directory_regex.is_match(&lines[i])
-                || location_regex.is_match(&lines[i])
-                || virtualhost_regex.is_match(&lines[i])
-            {
-                // Find the corresponding closing tag
-                let tag_name = if lines[i].contains("<Directory") {
-                    "Directory"
-                } else if lines[i].contains("<Location") {
-                    "Location"
-                } else {
-                    "VirtualHost"
-                };
-
-                let closing_tag = format!("</{}>", tag_name);
-
-                for j in (i + 1)..lines.len() {
-                    if lines[j].contains(&closing_tag) {
-                        // Insert Require rule before closing tag
-                        let indent = "    "; // Standard Apache indentation
-                        lines.insert(
-                            j,
-                            format!("{}Require ip {} {}", indent, new_ip, hostname_comment),
-                        );
-                        updated = true;
-                        break;
-                    }
-                }
-                break;
+        match self.apache_config_update(config, hostname, diff).await? {
+            Some(new_content) => {
+                write_atomically(&config.path, &new_content).await?;
+                eprintln!(
+                    "DEBUG: Updated Apache access directives ({}) for hostname: {}",
+                    diff, hostname
+                );
+                Ok(true)
+            }
+            None => {
+                eprintln!(
+                    "DEBUG: No existing Apache access entry found for hostname: {}, not adding new entry",
+                    hostname
+                );
+                Ok(false)
             }
         }
-
-        if updated {
-            let new_content = lines.join("\n");
-            fs::write(config_path, new_content).await?;
-        }
-
-        Ok(updated)
     }
 }
 
@@ -101,11 +85,18 @@ impl WebServerHandler for ApacheHandler {
         &self,
         config: &WebServerConfig,
         hostname: &str,
-        old_ip: Option<IpAddr>,
-        new_ip: IpAddr,
+        diff: &AddressDiff,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        self.update_apache_config(&config.path, hostname, old_ip, new_ip)
-            .await
+        self.update_apache_config(config, hostname, diff).await
+    }
+
+    async fn preview_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.apache_config_update(config, hostname, diff).await
     }
 
     async fn validate_config(
@@ -130,10 +121,24 @@ impl WebServerHandler for ApacheHandler {
             }
         }
 
-        Err("Apache command not found (tried apache2ctl, apachectl, httpd)".into())
+        // If Apache is not installed, fall back to a structural check
+        let content = std::fs::read_to_string(&config.path)?;
+        let is_valid = validate_apache_structure(&content);
+        eprintln!(
+            "DEBUG: Fallback validation for {:?} (apache2ctl, apachectl, httpd not found): proper Apache structure: {}",
+            config.path.file_name(),
+            is_valid
+        );
+        Ok(is_valid)
     }
 
     async fn reload_server(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // In test mode, skip actual reload
+        if std::env::var("DDNS_TEST_MODE").is_ok() {
+            eprintln!("Test mode: Skipping apache reload");
+            return Ok(());
+        }
+
         // Try different service names for Apache
         let services = ["apache2", "httpd"];
 
@@ -159,6 +164,28 @@ impl WebServerHandler for ApacheHandler {
         self.backup_file(&config.path).await
     }
 
+    async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::list_backups(&config.path, self.backup_dir.as_deref()).await
+    }
+
+    async fn remove_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::remove_file(backup_path).await
+    }
+
+    async fn restore_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::restore_file(backup_path, &config.path).await
+    }
+
     async fn test_configuration(
         &self,
         config: &WebServerConfig,
@@ -172,25 +199,18 @@ impl WebServerHandler for ApacheHandler {
         ip: IpAddr,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let content = fs::read_to_string(&config.path).await?;
-        let ip_str = ip.to_string();
-
-        // Check if the IP exists in any Allow directive (Apache format)
-        for line in content.lines() {
-            let trimmed = line.trim();
-            // Apache uses "Allow from" or "Require ip" directives
-            if (trimmed.starts_with("Allow from ") || trimmed.starts_with("Require ip "))
-                && trimmed.contains(&ip_str)
-            {
-                eprintln!(
-                    "DEBUG: Found IP {} in Apache config line: {}",
-                    ip_str, trimmed
-                );
-                return Ok(true);
-            }
-        }
 
-        eprintln!("DEBUG: IP {} not found in Apache config file", ip_str);
-        Ok(false)
+        let found = find_access_directives(&content)
+            .iter()
+            .flat_map(|directive| &directive.addresses)
+            .any(|address| address.network.manages(ip, config.rewrite_prefix));
+
+        if found {
+            eprintln!("DEBUG: Found IP {} in Apache access directive", ip);
+        } else {
+            eprintln!("DEBUG: IP {} not found in Apache config file", ip);
+        }
+        Ok(found)
     }
 
     fn server_type(&self) -> WebServerType {
@@ -203,3 +223,231 @@ impl Default for ApacheHandler {
         Self::new()
     }
 }
+
+/// An address argument of an access directive
+struct AccessAddress {
+    /// Byte span of the address within the file
+    start: usize,
+    end: usize,
+    /// Address or CIDR network as written in the file
+    text: String,
+    network: IpNetwork,
+}
+
+/// A `Require ip` or `Allow from` directive that grants access
+struct AccessDirective {
+    addresses: Vec<AccessAddress>,
+    /// Hostname from a `# DDNS: <hostname>` comment on the preceding line (or trailing, legacy)
+    tagged_for: Option<String>,
+    /// Byte range of the directive's line(s), starting at the tag comment line if there is one
+    line_start: usize,
+    line_end: usize,
+}
+
+/// Apply an address diff to the access directives and return the new file content, or None
+/// if nothing changes.
+///
+/// Entries managing a removed address are rewritten: the address itself (also written as /32
+/// or /128), or with `rewrite_prefix` a network containing it, which moves to the new address'
+/// network of the same length. Added addresses join the directive of the hostname's other
+/// addresses; a directive left without addresses is deleted along with its tag comment.
+/// Without stored addresses, a directive tagged for the hostname is updated if it holds a
+/// single entry of the new address' family.
+fn apply_access_diff(
+    content: &str,
+    hostname: &str,
+    diff: &AddressDiff,
+    rewrite_prefix: bool,
+) -> Option<String> {
+    let directives = find_access_directives(content);
+
+    let mut addresses = Vec::new();
+    let mut entries = Vec::new();
+    for (group, directive) in directives.iter().enumerate() {
+        for address in &directive.addresses {
+            addresses.push(address);
+            entries.push(AllowEntry {
+                network: address.network,
+                text: address.text.clone(),
+                group,
+                tagged: directive.tagged_for.as_deref() == Some(hostname),
+            });
+        }
+    }
+
+    let edits = plan_edits(&entries, diff, rewrite_prefix);
+    if edits.is_empty() {
+        return None;
+    }
+
+    let removed_per_group = |group: usize| {
+        edits
+            .iter()
+            .filter(|edit| matches!(edit, EntryEdit::Remove { entry } if entries[*entry].group == group))
+            .count()
+    };
+
+    let mut span_edits = Vec::new();
+    let mut deleted_groups = Vec::new();
+    for edit in &edits {
+        match edit {
+            EntryEdit::Replace { entry, value } => {
+                let address = addresses[*entry];
+                span_edits.push((address.start, address.end, value.clone()));
+            }
+            EntryEdit::Remove { entry } => {
+                let group = entries[*entry].group;
+                let directive = &directives[group];
+                if removed_per_group(group) == directive.addresses.len() {
+                    // Apache rejects a `Require ip` without addresses, so the directive goes
+                    if !deleted_groups.contains(&group) {
+                        deleted_groups.push(group);
+                        span_edits.push((directive.line_start, directive.line_end, String::new()));
+                    }
+                } else {
+                    // Drop the address together with the whitespace in front of it
+                    let address = addresses[*entry];
+                    let start = content[..address.start].trim_end_matches([' ', '\t']).len();
+                    span_edits.push((start, address.end, String::new()));
+                }
+            }
+            EntryEdit::InsertAfter { entry, value } => {
+                let address = addresses[*entry];
+                span_edits.push((address.end, address.end, format!(" {}", value)));
+            }
+        }
+    }
+
+    let new_content = apply_span_edits(content, span_edits);
+    (new_content != content).then_some(new_content)
+}
+
+/// Collect all access-granting `Require ip` / `Allow from` directives.
+///
+/// `Require not ip` and `Require ip` inside `<RequireNone>` deny access and are skipped.
+fn find_access_directives(content: &str) -> Vec<AccessDirective> {
+    let mut result = Vec::new();
+    let mut sections: Vec<String> = Vec::new();
+    let mut previous_comment: Option<(String, usize)> = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let text = line.trim_end_matches(['\n', '\r']);
+        let trimmed = text.trim();
+
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            previous_comment = parse_ddns_tag(comment).map(|tag| (tag, line_start));
+            continue;
+        }
+        let tag = previous_comment.take();
+
+        if let Some(closing) = trimmed.strip_prefix("</") {
+            let name = closing.trim_end_matches('>').trim().to_lowercase();
+            if let Some(position) = sections.iter().rposition(|s| *s == name) {
+                sections.truncate(position);
+            }
+            continue;
+        }
+        if let Some(opening) = trimmed.strip_prefix('<') {
+            let name = opening
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            sections.push(name);
+            continue;
+        }
+
+        let (code, trailing_comment) = match text.find(" #") {
+            Some(index) => (&text[..index], parse_ddns_tag(&text[index + 2..])),
+            None => (text, None),
+        };
+        let indent = code.len() - code.trim_start().len();
+        let words: Vec<&str> = code.split_whitespace().collect();
+        let is_allow = match words.as_slice() {
+            [require, ip, ..] => {
+                (require.eq_ignore_ascii_case("require") && ip.eq_ignore_ascii_case("ip"))
+                    || (require.eq_ignore_ascii_case("allow") && ip.eq_ignore_ascii_case("from"))
+            }
+            _ => false,
+        };
+        if !is_allow || sections.iter().any(|s| s == "requirenone") {
+            continue;
+        }
+
+        let mut addresses = Vec::new();
+        let mut position = indent;
+        for (index, word) in words.iter().enumerate() {
+            let word_start = position + code[position..].find(word).unwrap_or(0);
+            position = word_start + word.len();
+            if index < 2 {
+                continue;
+            }
+            if let Ok(network) = word.parse::<IpNetwork>() {
+                addresses.push(AccessAddress {
+                    start: line_start + word_start,
+                    end: line_start + position,
+                    text: word.to_string(),
+                    network,
+                });
+            }
+        }
+
+        if !addresses.is_empty() {
+            let (tagged_for, start) = match tag {
+                Some((hostname, comment_start)) => (Some(hostname), comment_start),
+                None => (trailing_comment, line_start),
+            };
+            result.push(AccessDirective {
+                addresses,
+                tagged_for,
+                line_start: start,
+                line_end: offset,
+            });
+        }
+    }
+
+    result
+}
+
+/// Basic Apache config sanity check used when no Apache binary is available:
+/// every `<Section>` has a matching `</Section>`
+fn validate_apache_structure(content: &str) -> bool {
+    let mut sections: Vec<String> = Vec::new();
+    let mut has_directives = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        has_directives = true;
+
+        if let Some(closing) = trimmed.strip_prefix("</") {
+            let name = closing.trim_end_matches('>').trim().to_lowercase();
+            if sections.pop() != Some(name) {
+                return false;
+            }
+        } else if let Some(opening) = trimmed.strip_prefix('<') {
+            if !trimmed.ends_with('>') {
+                return false;
+            }
+            let name = opening
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            sections.push(name);
+        }
+    }
+
+    has_directives && sections.is_empty()
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "apache_tests.rs"]
+mod tests;
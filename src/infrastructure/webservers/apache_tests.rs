@@ -0,0 +1,264 @@
+use super::*;
+
+/// Diff for one address moving from `old_ip`, or appearing without a stored address
+fn change(old_ip: Option<IpAddr>, new_ip: IpAddr) -> AddressDiff {
+    match old_ip {
+        Some(old_ip) => AddressDiff::replacement(old_ip, new_ip),
+        None => AddressDiff {
+            added: vec![new_ip],
+            ..Default::default()
+        },
+    }
+}
+
+const APACHE_CONFIG: &str = "<VirtualHost *:80>
+    ServerName example.com
+    <Directory /var/www/admin>
+        <RequireAny>
+            # DDNS: office.example.com
+            Require ip 203.0.113.7
+            Require ip 10.0.0.0/8 198.51.100.4
+        </RequireAny>
+        <RequireNone>
+            Require ip 192.0.2.66
+        </RequireNone>
+        Require not ip 192.0.2.99
+    </Directory>
+    <Location /legacy>
+        Order deny,allow
+        Deny from all
+        Allow from 192.0.2.10
+    </Location>
+</VirtualHost>
+";
+
+fn managed_ips(content: &str) -> Vec<IpAddr> {
+    find_access_directives(content)
+        .iter()
+        .flat_map(|d| d.addresses.iter().map(|a| a.network.address()))
+        .collect()
+}
+
+#[test]
+fn test_finds_only_access_granting_directives() {
+    assert_eq!(
+        managed_ips(APACHE_CONFIG),
+        vec![
+            "203.0.113.7".parse::<IpAddr>().unwrap(),
+            "10.0.0.0".parse().unwrap(),
+            "198.51.100.4".parse().unwrap(),
+            "192.0.2.10".parse().unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn test_replaces_in_place_and_is_idempotent() {
+    let old_ip: IpAddr = "203.0.113.7".parse().unwrap();
+    let new_ip: IpAddr = "203.0.113.99".parse().unwrap();
+
+    let updated = apply_access_diff(
+        APACHE_CONFIG,
+        "office.example.com",
+        &change(Some(old_ip), new_ip),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains("            Require ip 203.0.113.99\n"));
+    assert_eq!(updated.lines().count(), APACHE_CONFIG.lines().count());
+    assert!(updated.ends_with("</VirtualHost>\n"));
+
+    // A second run with the same change finds nothing to do
+    assert!(apply_access_diff(
+        &updated,
+        "office.example.com",
+        &change(Some(old_ip), new_ip),
+        false
+    )
+    .is_none());
+}
+
+#[test]
+fn test_apache_22_allow_from() {
+    let updated = apply_access_diff(
+        APACHE_CONFIG,
+        "legacy.example.com",
+        &change(
+            Some("192.0.2.10".parse().unwrap()),
+            "192.0.2.11".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains("        Allow from 192.0.2.11\n"));
+}
+
+#[test]
+fn test_denied_addresses_are_not_managed() {
+    for denied in ["192.0.2.66", "192.0.2.99"] {
+        assert!(apply_access_diff(
+            APACHE_CONFIG,
+            "office.example.com",
+            &change(Some(denied.parse().unwrap()), "192.0.2.1".parse().unwrap()),
+            false
+        )
+        .is_none());
+    }
+}
+
+#[test]
+fn test_drops_old_address_when_new_one_is_already_listed() {
+    let content = "<Location />\n    Require ip 198.51.100.4 198.51.100.5\n</Location>\n";
+    let updated = apply_access_diff(
+        content,
+        "office.example.com",
+        &change(
+            Some("198.51.100.4".parse().unwrap()),
+            "198.51.100.5".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "<Location />\n    Require ip 198.51.100.5\n</Location>\n"
+    );
+}
+
+#[test]
+fn test_tagged_directive_without_stored_ip() {
+    let updated = apply_access_diff(
+        APACHE_CONFIG,
+        "office.example.com",
+        &change(None, "203.0.113.8".parse().unwrap()),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains("            Require ip 203.0.113.8\n"));
+
+    assert!(apply_access_diff(
+        APACHE_CONFIG,
+        "unknown.example.com",
+        &change(None, "203.0.113.8".parse().unwrap()),
+        false
+    )
+    .is_none());
+}
+
+#[test]
+fn test_cidr_host_entry_keeps_notation() {
+    let content = "<Location />\n    Require ip 203.0.113.7/32 10.0.0.0/8\n</Location>\n";
+    let updated = apply_access_diff(
+        content,
+        "office.example.com",
+        &change(
+            Some("203.0.113.7".parse().unwrap()),
+            "203.0.113.8".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains("Require ip 203.0.113.8/32 10.0.0.0/8\n"));
+}
+
+#[test]
+fn test_prefix_rewrite_only_when_enabled() {
+    let content = "<Location />\n    Require ip 2001:db8:aa:1200::/56\n</Location>\n";
+    let old_ip = "2001:db8:aa:1234::10".parse().unwrap();
+    let new_ip = "2001:db8:bb:1700::10".parse().unwrap();
+
+    assert!(apply_access_diff(
+        content,
+        "v6.example.com",
+        &change(Some(old_ip), new_ip),
+        false
+    )
+    .is_none());
+
+    let updated = apply_access_diff(
+        content,
+        "v6.example.com",
+        &change(Some(old_ip), new_ip),
+        true,
+    )
+    .unwrap();
+    assert!(updated.contains("    Require ip 2001:db8:bb:1700::/56\n"));
+
+    // A new address inside the configured network needs no change
+    let same_network = "2001:db8:aa:12ff::99".parse().unwrap();
+    assert!(apply_access_diff(
+        content,
+        "v6.example.com",
+        &change(Some(old_ip), same_network),
+        true
+    )
+    .is_none());
+}
+
+#[test]
+fn test_tagged_dual_stack_directive_updates_matching_family() {
+    let content = "# DDNS: home.example.com\nRequire ip 203.0.113.7 2001:db8::7\n";
+    let updated = apply_access_diff(
+        content,
+        "home.example.com",
+        &change(None, "2001:db8::8".parse().unwrap()),
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "# DDNS: home.example.com\nRequire ip 203.0.113.7 2001:db8::8\n"
+    );
+}
+
+#[test]
+fn test_validate_apache_structure() {
+    assert!(validate_apache_structure(APACHE_CONFIG));
+    assert!(!validate_apache_structure(
+        "<VirtualHost *:80>\n    <Directory />\n</VirtualHost>\n"
+    ));
+}
+
+#[test]
+fn test_address_set_changes() {
+    let content = "# DDNS: home.example.com\nRequire ip 203.0.113.7 203.0.113.9\n# DDNS: home.example.com\nRequire ip 2001:db8::7\n";
+    let stored: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.9".parse().unwrap(),
+        "2001:db8::7".parse().unwrap(),
+    ];
+    let current: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.10".parse().unwrap(),
+    ];
+
+    // Without an AAAA record left, its directive goes together with the tag comment
+    let updated = apply_access_diff(
+        content,
+        "home.example.com",
+        &AddressDiff::between(&stored, &current),
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "# DDNS: home.example.com\nRequire ip 203.0.113.7 203.0.113.10\n"
+    );
+
+    let more: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.10".parse().unwrap(),
+        "203.0.113.11".parse().unwrap(),
+    ];
+    let updated = apply_access_diff(
+        &updated,
+        "home.example.com",
+        &AddressDiff::between(&current, &more),
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "# DDNS: home.example.com\nRequire ip 203.0.113.7 203.0.113.10 203.0.113.11\n"
+    );
+}
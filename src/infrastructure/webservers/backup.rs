@@ -0,0 +1,140 @@
+use crate::domain::entities::BackupFile;
+use crate::infrastructure::files::write_atomically;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Format of the timestamp at the end of backup file names
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// Copy a config file to a timestamped backup.
+///
+/// The copy is written as `<name>.bak.<timestamp>`, next to the original file or inside the
+/// backup directory (created on demand); see `backup_name` for the name. Another backup within
+/// the same second gets a `_<n>` suffix.
+pub(crate) async fn backup_file(
+    config_path: &Path,
+    backup_dir: Option<&Path>,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let timestamp = chrono::Utc::now().format(TIMESTAMP_FORMAT);
+    let (dir, name) = backup_name(config_path, backup_dir)?;
+    if backup_dir.is_some() && !dir.exists() {
+        fs::create_dir_all(&dir).await?;
+    }
+    let backup_path = dir.join(format!("{}.bak.{}", name, timestamp));
+
+    // Backups made within the same second get a counter instead of replacing each other
+    let mut unique_path = backup_path.clone();
+    let mut counter = 1;
+    while fs::try_exists(&unique_path).await? {
+        unique_path = PathBuf::from(format!("{}_{}", backup_path.display(), counter));
+        counter += 1;
+    }
+
+    fs::copy(config_path, &unique_path).await?;
+    // The backup has to be on disk before the config file is rewritten
+    fs::File::open(&unique_path).await?.sync_all().await?;
+    Ok(unique_path)
+}
+
+/// Backups `backup_file` made of a config file, oldest first
+pub(crate) async fn list_backups(
+    config_path: &Path,
+    backup_dir: Option<&Path>,
+) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let (dir, name) = backup_name(config_path, backup_dir)?;
+    let prefix = format!("{}.bak.", name);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    let mut entries = fs::read_dir(&dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(suffix) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        // `<date>_<time>`, optionally followed by `_<n>`
+        let (timestamp, counter) = match suffix.match_indices('_').nth(1) {
+            Some((split, _)) => (&suffix[..split], suffix[split + 1..].parse::<u32>().ok()),
+            None => (suffix, Some(0)),
+        };
+        let (Ok(created_at), Some(counter)) = (
+            chrono::NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT),
+            counter,
+        ) else {
+            continue;
+        };
+        backups.push((
+            counter,
+            BackupFile {
+                path: entry.path(),
+                created_at: created_at.and_utc(),
+            },
+        ));
+    }
+    backups.sort_by_key(|(counter, backup)| (backup.created_at, *counter));
+    Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+}
+
+/// Directory and name (before `.bak.<timestamp>`) of a config file's backups.
+///
+/// Next to the original that's the file's full name, so `site.conf` and `site.yaml` keep
+/// separate backups. A backup directory may hold files of several directories, so there the
+/// name is the file's whole path with `/` escaped, e.g.
+/// `etc%2Fnginx%2Fsites-available%2Fsite.conf`.
+fn backup_name(
+    config_path: &Path,
+    backup_dir: Option<&Path>,
+) -> std::io::Result<(PathBuf, String)> {
+    match backup_dir {
+        Some(backup_dir) => {
+            let path = config_path
+                .canonicalize()
+                .or_else(|_| std::path::absolute(config_path))?;
+            let escaped = path
+                .to_string_lossy()
+                .trim_start_matches('/')
+                .replace('%', "%25")
+                .replace('/', "%2F");
+            Ok((backup_dir.to_path_buf(), escaped))
+        }
+        None => Ok((
+            config_path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."))
+                .to_path_buf(),
+            config_path
+                .file_name()
+                .unwrap_or_else(|| std::ffi::OsStr::new("config"))
+                .to_string_lossy()
+                .into_owned(),
+        )),
+    }
+}
+
+/// Delete a backup file
+pub(crate) async fn remove_file(
+    backup_path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    fs::remove_file(backup_path)
+        .await
+        .map_err(|e| format!("Failed to remove backup {}: {}", backup_path.display(), e).into())
+}
+
+/// Put a backup back in place of the config file, atomically so the web server never reads a
+/// half-restored file
+pub(crate) async fn restore_file(
+    backup_path: &Path,
+    config_path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let content = fs::read_to_string(backup_path)
+        .await
+        .map_err(|e| format!("Failed to read backup {}: {}", backup_path.display(), e))?;
+    write_atomically(config_path, &content).await
+}
+
+#[cfg(test)]
+#[path = "backup_tests.rs"]
+mod tests;
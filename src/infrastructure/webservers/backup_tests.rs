@@ -0,0 +1,99 @@
+use super::*;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_backups_are_listed_oldest_first_and_never_replaced() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("site.conf");
+    let backup_dir = dir.path().join("backups");
+
+    let mut made = Vec::new();
+    for version in ["one", "two", "three"] {
+        std::fs::write(&config_path, version).unwrap();
+        made.push(backup_file(&config_path, Some(&backup_dir)).await.unwrap());
+    }
+    // Unrelated files in the backup directory are ignored
+    let name = made[0].file_name().unwrap().to_string_lossy().into_owned();
+    let (prefix, _) = name.split_once(".bak.").unwrap();
+    std::fs::write(backup_dir.join("other.conf.bak.20240101_120000"), "").unwrap();
+    std::fs::write(backup_dir.join(format!("{}.bak.notes", prefix)), "").unwrap();
+    std::fs::write(
+        backup_dir.join(format!("{}.old.bak.20240101_120000", prefix)),
+        "",
+    )
+    .unwrap();
+
+    let listed: Vec<PathBuf> = list_backups(&config_path, Some(&backup_dir))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|backup| backup.path)
+        .collect();
+    assert_eq!(listed, made);
+    assert_eq!(std::fs::read_to_string(&made[0]).unwrap(), "one");
+    assert_eq!(std::fs::read_to_string(&made[2]).unwrap(), "three");
+
+    restore_file(&made[0], &config_path).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&config_path).unwrap(), "one");
+}
+
+#[tokio::test]
+async fn test_backups_next_to_the_config_file() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("site.conf");
+    std::fs::write(&config_path, "server {}").unwrap();
+
+    let backup = backup_file(&config_path, None).await.unwrap();
+    assert!(backup
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("site.conf.bak."));
+
+    let listed = list_backups(&config_path, None).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].path, backup);
+    assert!(
+        list_backups(&config_path, Some(&dir.path().join("missing")))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_files_sharing_a_name_keep_separate_backups() {
+    let dir = tempdir().unwrap();
+    let backup_dir = dir.path().join("backups");
+    let available = dir.path().join("sites-available");
+    let conf_d = dir.path().join("conf.d");
+    std::fs::create_dir(&available).unwrap();
+    std::fs::create_dir(&conf_d).unwrap();
+    let configs = [
+        available.join("site.conf"),
+        available.join("site.yaml"),
+        conf_d.join("site.conf"),
+    ];
+
+    for backup_dir in [None, Some(backup_dir.as_path())] {
+        let mut made = Vec::new();
+        for config_path in &configs {
+            std::fs::write(config_path, config_path.to_string_lossy().as_bytes()).unwrap();
+            made.push(backup_file(config_path, backup_dir).await.unwrap());
+        }
+        for (config_path, backup) in configs.iter().zip(&made) {
+            let listed = list_backups(config_path, backup_dir).await.unwrap();
+            assert_eq!(listed.len(), 1, "{}", config_path.display());
+            assert_eq!(&listed[0].path, backup);
+        }
+    }
+
+    // In a backup directory the name tells where the file was
+    let name = list_backups(&configs[2], Some(&backup_dir)).await.unwrap()[0]
+        .path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    assert!(name.contains("conf.d%2Fsite.conf.bak."), "{}", name);
+}
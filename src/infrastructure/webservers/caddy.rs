@@ -0,0 +1,568 @@
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tokio::fs;
+
+use crate::domain::entities::{BackupFile, WebServerConfig, WebServerType};
+use crate::domain::ports::WebServerHandler;
+use crate::domain::value_objects::{AddressDiff, IpNetwork};
+use crate::infrastructure::files::write_atomically;
+
+use super::allow_list::{apply_span_edits, plan_edits, AllowEntry, EntryEdit};
+use super::comments::{is_ddns_tag_for, split_comment};
+
+/// Default location of the top-level Caddyfile used for reloads
+const DEFAULT_MAIN_CONFIG: &str = "/etc/caddy/Caddyfile";
+
+/// Default address of the Caddy admin API
+const DEFAULT_ADMIN_ENDPOINT: &str = "http://localhost:2019";
+
+/// Matchers whose arguments are IP ranges
+const IP_MATCHERS: [&str; 2] = ["remote_ip", "client_ip"];
+
+/// Caddy web server handler
+///
+/// Manages the `remote_ip` / `client_ip` ranges of named matchers in a Caddyfile, e.g.
+///
+/// ```text
+/// @office {
+///     remote_ip 203.0.113.7 10.0.0.0/8 # DDNS: office.example.com
+/// }
+/// @vpn client_ip 198.51.100.4
+/// ```
+pub struct CaddyHandler {
+    backup_dir: Option<PathBuf>,
+    main_config: PathBuf,
+    admin_endpoint: String,
+}
+
+impl CaddyHandler {
+    pub fn new() -> Self {
+        Self::with_backup_dir(None)
+    }
+
+    pub fn with_backup_dir(backup_dir: Option<PathBuf>) -> Self {
+        Self {
+            backup_dir,
+            main_config: PathBuf::from(DEFAULT_MAIN_CONFIG),
+            admin_endpoint: DEFAULT_ADMIN_ENDPOINT.to_string(),
+        }
+    }
+
+    /// Top-level Caddyfile passed to `caddy reload` (defaults to /etc/caddy/Caddyfile)
+    pub fn with_main_config(mut self, main_config: PathBuf) -> Self {
+        self.main_config = main_config;
+        self
+    }
+
+    /// Admin API address used when the `caddy` binary is not available
+    pub fn with_admin_endpoint(mut self, admin_endpoint: String) -> Self {
+        self.admin_endpoint = admin_endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    async fn backup_file(
+        &self,
+        config_path: &Path,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::backup_file(config_path, self.backup_dir.as_deref()).await
+    }
+
+    /// New content of the config file with the diff applied; None if no managed entry changes
+    async fn caddy_config_update(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let content = fs::read_to_string(&config.path).await?;
+        Ok(apply_matcher_diff(
+            &content,
+            hostname,
+            diff,
+            config.rewrite_prefix,
+        ))
+    }
+
+    async fn update_caddy_config(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.caddy_config_update(config, hostname, diff).await? {
+            Some(new_content) => {
+                write_atomically(&config.path, &new_content).await?;
+                eprintln!(
+                    "DEBUG: Updated Caddy matcher ranges ({}) for hostname: {}",
+                    diff, hostname
+                );
+                Ok(true)
+            }
+            None => {
+                eprintln!(
+                    "DEBUG: No managed Caddy matcher range found for hostname: {}, not adding new entry",
+                    hostname
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Caddyfile to validate after editing `config_path`. An imported snippet (e.g. one holding
+    /// only a matcher) isn't a valid Caddyfile by itself, so the top-level Caddyfile that pulls
+    /// it in is validated instead; the edited file only when it is the top-level one or there
+    /// is none.
+    fn validation_target<'a>(&'a self, config_path: &'a Path) -> &'a Path {
+        let canonical = |path: &Path| std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+        if self.main_config.exists() && canonical(config_path) != canonical(&self.main_config) {
+            &self.main_config
+        } else {
+            config_path
+        }
+    }
+
+    /// Push the main Caddyfile to the admin API, which is what `caddy reload` does internally
+    async fn reload_via_admin_api(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let caddyfile = fs::read_to_string(&self.main_config).await?;
+        let response = reqwest::Client::new()
+            .post(format!("{}/load", self.admin_endpoint))
+            .header("Content-Type", "text/caddyfile")
+            .timeout(std::time::Duration::from_secs(10))
+            .body(caddyfile)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            Err(format!("admin API returned {}: {}", status, body.trim()).into())
+        }
+    }
+}
+
+#[async_trait]
+impl WebServerHandler for CaddyHandler {
+    async fn update_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.update_caddy_config(config, hostname, diff).await
+    }
+
+    async fn preview_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.caddy_config_update(config, hostname, diff).await
+    }
+
+    async fn validate_config(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        if !config.path.exists() {
+            return Ok(false);
+        }
+
+        let target = self.validation_target(&config.path);
+        match Command::new("caddy")
+            .arg("validate")
+            .arg("--config")
+            .arg(target)
+            .arg("--adapter")
+            .arg("caddyfile")
+            .output()
+        {
+            Ok(output) => {
+                if !output.status.success() {
+                    eprintln!(
+                        "caddy validate of {} failed: {}",
+                        target.display(),
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+                Ok(output.status.success())
+            }
+            Err(_e) => {
+                // If caddy is not installed, fall back to a structural check
+                let content = std::fs::read_to_string(&config.path)?;
+                let is_valid = validate_caddyfile_structure(&content);
+                eprintln!(
+                    "DEBUG: Fallback validation for {:?}: proper Caddyfile structure: {}",
+                    config.path.file_name(),
+                    is_valid
+                );
+                Ok(is_valid)
+            }
+        }
+    }
+
+    async fn reload_server(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // In test mode, skip actual reload
+        if std::env::var("DDNS_TEST_MODE").is_ok() {
+            eprintln!("Test mode: Skipping caddy reload");
+            return Ok(());
+        }
+
+        eprintln!("Reloading caddy configuration...");
+
+        // Method 1: caddy reload, which adapts the Caddyfile and posts it to the admin API
+        let last_error = match Command::new("caddy")
+            .arg("reload")
+            .arg("--config")
+            .arg(&self.main_config)
+            .arg("--adapter")
+            .arg("caddyfile")
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                eprintln!("✅ Caddy reloaded successfully using: caddy reload");
+                return Ok(());
+            }
+            Ok(output) => format!(
+                "caddy reload failed with exit code {}: {}",
+                output.status.code().unwrap_or(-1),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => format!("Could not execute 'caddy': {}", e),
+        };
+        eprintln!("⚠️  {}", last_error);
+
+        // Method 2: talk to the admin API directly
+        match self.reload_via_admin_api().await {
+            Ok(()) => {
+                eprintln!(
+                    "✅ Caddy reloaded successfully using admin API at {}",
+                    self.admin_endpoint
+                );
+                Ok(())
+            }
+            Err(e) => Err(format!(
+                "❌ Failed to reload caddy. {}; admin API at {}: {}",
+                last_error, self.admin_endpoint, e
+            )
+            .into()),
+        }
+    }
+
+    async fn create_backup(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        self.backup_file(&config.path).await
+    }
+
+    async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::list_backups(&config.path, self.backup_dir.as_deref()).await
+    }
+
+    async fn remove_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::remove_file(backup_path).await
+    }
+
+    async fn restore_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::restore_file(backup_path, &config.path).await
+    }
+
+    async fn test_configuration(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.validate_config(config).await
+    }
+
+    async fn check_ip_in_config(
+        &self,
+        config: &WebServerConfig,
+        ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let content = fs::read_to_string(&config.path).await?;
+
+        let found = find_ip_matcher_lines(&content)
+            .iter()
+            .flat_map(|line| &line.ranges)
+            .any(|range| range.network.manages(ip, config.rewrite_prefix));
+
+        if found {
+            eprintln!("DEBUG: Found IP {} in Caddy matcher", ip);
+        } else {
+            eprintln!("DEBUG: IP {} not found in Caddy config file", ip);
+        }
+        Ok(found)
+    }
+
+    fn server_type(&self) -> WebServerType {
+        WebServerType::Caddy
+    }
+}
+
+impl Default for CaddyHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An IP range argument of a `remote_ip` / `client_ip` matcher
+struct MatcherRange {
+    /// Byte offset of the range within the file
+    start: usize,
+    end: usize,
+    network: IpNetwork,
+}
+
+/// A `remote_ip` / `client_ip` line belonging to a named matcher
+struct MatcherLine {
+    ranges: Vec<MatcherRange>,
+    comment: Option<String>,
+    /// Byte range of the whole line including its line break
+    line_start: usize,
+    line_end: usize,
+    /// Number of the `@name { ... }` block the line is in, if any
+    block: Option<usize>,
+}
+
+/// Apply an address diff to the matcher ranges and return the new file content, or None if
+/// nothing changes.
+///
+/// Ranges managing a removed address are rewritten: the address itself (also written as /32 or
+/// /128), or with `rewrite_prefix` a network containing it, which moves to the new address'
+/// network of the same length. Added addresses join the line of the hostname's other addresses.
+/// Without stored addresses, a matcher line tagged with a `# DDNS: <hostname>` comment is
+/// updated if it holds a single range of the new address' family.
+fn apply_matcher_diff(
+    content: &str,
+    hostname: &str,
+    diff: &AddressDiff,
+    rewrite_prefix: bool,
+) -> Option<String> {
+    let lines = find_ip_matcher_lines(content);
+
+    let mut ranges = Vec::new();
+    let mut entries = Vec::new();
+    for (group, line) in lines.iter().enumerate() {
+        let tagged = line
+            .comment
+            .as_deref()
+            .is_some_and(|comment| is_ddns_tag_for(comment, hostname));
+        for range in &line.ranges {
+            ranges.push(range);
+            entries.push(AllowEntry {
+                network: range.network,
+                text: content[range.start..range.end].to_string(),
+                group,
+                tagged,
+            });
+        }
+    }
+
+    let edits = plan_edits(&entries, diff, rewrite_prefix);
+    let removed_per_group = |group: usize| {
+        edits
+            .iter()
+            .filter(|edit| matches!(edit, EntryEdit::Remove { entry } if entries[*entry].group == group))
+            .count()
+    };
+
+    // Another IP line of the same matcher block stays, so this one can be deleted
+    let keeps_other_line = |group: usize| {
+        lines.iter().enumerate().any(|(other, line)| {
+            other != group
+                && line.block == lines[group].block
+                && removed_per_group(other) < line.ranges.len()
+        })
+    };
+
+    let mut span_edits = Vec::new();
+    let mut deleted_groups = Vec::new();
+    for edit in &edits {
+        match edit {
+            EntryEdit::Replace { entry, value } => {
+                let range = ranges[*entry];
+                span_edits.push((range.start, range.end, value.clone()));
+            }
+            EntryEdit::Remove { entry } => {
+                let group = entries[*entry].group;
+                let line = &lines[group];
+                if removed_per_group(group) < line.ranges.len() {
+                    // Drop the range together with the whitespace in front of it
+                    let range = ranges[*entry];
+                    let start = content[..range.start].trim_end_matches([' ', '\t']).len();
+                    span_edits.push((start, range.end, String::new()));
+                } else if line.block.is_some() && keeps_other_line(group) {
+                    if !deleted_groups.contains(&group) {
+                        deleted_groups.push(group);
+                        span_edits.push((line.line_start, line.line_end, String::new()));
+                    }
+                } else {
+                    // An empty matcher would match every client
+                    eprintln!(
+                        "DEBUG: Keeping {} as the last range of its Caddy matcher",
+                        entries[*entry].text
+                    );
+                }
+            }
+            EntryEdit::InsertAfter { entry, value } => {
+                let range = ranges[*entry];
+                span_edits.push((range.end, range.end, format!(" {}", value)));
+            }
+        }
+    }
+
+    let new_content = apply_span_edits(content, span_edits);
+    (new_content != content).then_some(new_content)
+}
+
+/// Collect all IP matcher lines inside named matchers (`@name remote_ip ...` or `@name { ... }`)
+fn find_ip_matcher_lines(content: &str) -> Vec<MatcherLine> {
+    let mut result = Vec::new();
+    let mut depth = 0usize;
+    // Brace depth at which the currently open named matcher block was started
+    let mut matcher_block: Option<usize> = None;
+    let mut blocks_seen = 0usize;
+    let mut offset = 0usize;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let (code, comment) = split_comment(line.trim_end_matches(['\n', '\r']));
+        let tokens = tokenize(code);
+        let words: Vec<&str> = tokens.iter().map(|&(s, e)| &code[s..e]).collect();
+
+        let (args_start, block) = match words.first() {
+            Some(first) if first.starts_with('@') && words.get(1) != Some(&"{") => {
+                (ip_matcher_args(&words, 1), None)
+            }
+            // `@name { remote_ip ... }` on one line; the line holds the block's header, so it
+            // is never deleted as a whole
+            Some(first) if first.starts_with('@') => (ip_matcher_args(&words, 2), None),
+            Some(_) if matcher_block.is_some() => (ip_matcher_args(&words, 0), Some(blocks_seen)),
+            _ => (None, None),
+        };
+
+        if let Some(args_start) = args_start {
+            let ranges: Vec<MatcherRange> = tokens[args_start..]
+                .iter()
+                .filter_map(|&(s, e)| {
+                    let network = code[s..e].parse::<IpNetwork>().ok()?;
+                    Some(MatcherRange {
+                        start: line_start + s,
+                        end: line_start + e,
+                        network,
+                    })
+                })
+                .collect();
+
+            if !ranges.is_empty() {
+                result.push(MatcherLine {
+                    ranges,
+                    comment: comment.map(|c| c.trim().to_string()),
+                    line_start,
+                    line_end: offset,
+                    block,
+                });
+            }
+        }
+
+        for (index, word) in words.iter().enumerate() {
+            match *word {
+                "{" => {
+                    if index == 1 && words[0].starts_with('@') {
+                        matcher_block = Some(depth);
+                        blocks_seen += 1;
+                    }
+                    depth += 1;
+                }
+                "}" => {
+                    depth = depth.saturating_sub(1);
+                    if matcher_block == Some(depth) {
+                        matcher_block = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    result
+}
+
+/// Index of the first range argument if the words form a `[not] remote_ip|client_ip ...` matcher
+fn ip_matcher_args(words: &[&str], mut index: usize) -> Option<usize> {
+    if words.get(index) == Some(&"not") {
+        index += 1;
+    }
+    if IP_MATCHERS.contains(words.get(index)?) {
+        Some(index + 1)
+    } else {
+        None
+    }
+}
+
+/// Whitespace separated tokens as byte spans
+fn tokenize(code: &str) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in code.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                tokens.push((s, index));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, code.len()));
+    }
+    tokens
+}
+
+/// Basic Caddyfile sanity check used when the caddy binary is not available
+fn validate_caddyfile_structure(content: &str) -> bool {
+    let mut depth = 0i64;
+    let mut has_content = false;
+
+    for line in content.lines() {
+        let (code, _) = split_comment(line);
+        for (s, e) in tokenize(code) {
+            has_content = true;
+            match &code[s..e] {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth < 0 {
+                        return false;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    has_content && depth == 0
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "caddy_tests.rs"]
+mod tests;
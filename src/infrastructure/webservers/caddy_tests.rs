@@ -0,0 +1,228 @@
+use super::*;
+
+/// Diff for one address moving from `old_ip`, or appearing without a stored address
+fn change(old_ip: Option<IpAddr>, new_ip: IpAddr) -> AddressDiff {
+    match old_ip {
+        Some(old_ip) => AddressDiff::replacement(old_ip, new_ip),
+        None => AddressDiff {
+            added: vec![new_ip],
+            ..Default::default()
+        },
+    }
+}
+
+const CADDYFILE: &str = "example.com {
+\t@office {
+\t\tremote_ip 203.0.113.7 10.0.0.0/8 # DDNS: office.example.com
+\t}
+\t@vpn client_ip 198.51.100.4
+\trespond @office \"hi\"
+\treverse_proxy 203.0.113.7:8080
+}
+";
+
+#[test]
+fn test_finds_ranges_only_inside_named_matchers() {
+    let ranges: Vec<(IpAddr, u8)> = find_ip_matcher_lines(CADDYFILE)
+        .iter()
+        .flat_map(|line| {
+            line.ranges
+                .iter()
+                .map(|r| (r.network.address(), r.network.prefix_len()))
+        })
+        .collect();
+
+    assert_eq!(
+        ranges,
+        vec![
+            ("203.0.113.7".parse().unwrap(), 32),
+            ("10.0.0.0".parse().unwrap(), 8),
+            ("198.51.100.4".parse().unwrap(), 32),
+        ]
+    );
+}
+
+#[test]
+fn test_one_line_matcher_blocks() {
+    let caddyfile = "example.com {
+\t@office { remote_ip 203.0.113.7 10.0.0.0/8 } # DDNS: office.example.com
+\t@vpn { not client_ip 198.51.100.4 }
+\trespond @office \"hi\"
+}
+";
+    let ranges: Vec<IpAddr> = find_ip_matcher_lines(caddyfile)
+        .iter()
+        .flat_map(|line| line.ranges.iter().map(|r| r.network.address()))
+        .collect();
+    assert_eq!(
+        ranges,
+        ["203.0.113.7", "10.0.0.0", "198.51.100.4"].map(|ip| ip.parse::<IpAddr>().unwrap())
+    );
+
+    let updated = apply_matcher_diff(
+        caddyfile,
+        "office.example.com",
+        &change(
+            Some("203.0.113.7".parse().unwrap()),
+            "203.0.113.99".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+    assert!(updated
+        .contains("\t@office { remote_ip 203.0.113.99 10.0.0.0/8 } # DDNS: office.example.com\n"));
+    assert!(validate_caddyfile_structure(&updated));
+}
+
+#[test]
+fn test_replaces_old_ip_and_keeps_formatting() {
+    let updated = apply_matcher_diff(
+        CADDYFILE,
+        "office.example.com",
+        &change(
+            Some("203.0.113.7".parse().unwrap()),
+            "203.0.113.99".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+
+    assert!(updated.contains("\t\tremote_ip 203.0.113.99 10.0.0.0/8 # DDNS: office.example.com\n"));
+    // The upstream address outside the matcher must not be touched
+    assert!(updated.contains("reverse_proxy 203.0.113.7:8080"));
+    assert!(updated.ends_with("}\n"));
+}
+
+#[test]
+fn test_does_not_add_unknown_entries() {
+    assert!(apply_matcher_diff(
+        CADDYFILE,
+        "office.example.com",
+        &change(
+            Some("192.0.2.1".parse().unwrap()),
+            "192.0.2.2".parse().unwrap()
+        ),
+        false
+    )
+    .is_none());
+
+    // The tagged line has two ranges, so it is ambiguous without a stored IP
+    assert!(apply_matcher_diff(
+        CADDYFILE,
+        "office.example.com",
+        &change(None, "192.0.2.2".parse().unwrap()),
+        false
+    )
+    .is_none());
+}
+
+#[test]
+fn test_tagged_single_range_without_stored_ip() {
+    let content = "@home {\n    not remote_ip 192.0.2.10 # DDNS: home.example.com\n}\n";
+    let updated = apply_matcher_diff(
+        content,
+        "home.example.com",
+        &change(None, "192.0.2.20".parse().unwrap()),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        updated,
+        "@home {\n    not remote_ip 192.0.2.20 # DDNS: home.example.com\n}\n"
+    );
+}
+
+#[test]
+fn test_prefix_rewrite_keeps_prefix_length() {
+    let content = "@lan remote_ip 2001:db8:aa:1200::/56 10.0.0.0/8\n";
+    let old_ip = "2001:db8:aa:1234::1".parse().unwrap();
+    let new_ip = "2001:db8:cd:4201::1".parse().unwrap();
+
+    assert!(apply_matcher_diff(
+        content,
+        "lan.example.com",
+        &change(Some(old_ip), new_ip),
+        false
+    )
+    .is_none());
+
+    let updated = apply_matcher_diff(
+        content,
+        "lan.example.com",
+        &change(Some(old_ip), new_ip),
+        true,
+    )
+    .unwrap();
+    assert_eq!(updated, "@lan remote_ip 2001:db8:cd:4200::/56 10.0.0.0/8\n");
+}
+
+#[test]
+fn test_validate_caddyfile_structure() {
+    assert!(validate_caddyfile_structure(CADDYFILE));
+    assert!(!validate_caddyfile_structure("example.com {\n"));
+    assert!(!validate_caddyfile_structure("# only a comment\n"));
+}
+
+#[test]
+fn test_address_set_changes_never_empty_a_matcher() {
+    let content = "@home {\n\tremote_ip 203.0.113.7\n\tremote_ip 2001:db8::7\n}\n@vpn client_ip 198.51.100.4\n";
+    let stored: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "2001:db8::7".parse().unwrap(),
+    ];
+    let current: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.8".parse().unwrap(),
+    ];
+
+    let updated = apply_matcher_diff(
+        content,
+        "home.example.com",
+        &AddressDiff::between(&stored, &current),
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "@home {\n\tremote_ip 203.0.113.7 203.0.113.8\n}\n@vpn client_ip 198.51.100.4\n"
+    );
+
+    // The last range of a matcher stays, as an empty matcher would match every client
+    let vpn: Vec<IpAddr> = vec![
+        "198.51.100.4".parse().unwrap(),
+        "198.51.100.5".parse().unwrap(),
+    ];
+    let remaining: Vec<IpAddr> = vec!["198.51.100.5".parse().unwrap()];
+    assert!(apply_matcher_diff(
+        &updated,
+        "vpn.example.com",
+        &AddressDiff {
+            removed: vpn[..1].to_vec(),
+            added: Vec::new(),
+            unchanged: remaining
+        },
+        false
+    )
+    .is_none());
+}
+
+#[test]
+fn test_imported_snippets_are_validated_through_the_main_caddyfile() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let main_config = temp_dir.path().join("Caddyfile");
+    let snippet = temp_dir.path().join("office.caddy");
+    std::fs::write(&main_config, "import office.caddy\n").unwrap();
+    std::fs::write(&snippet, "@office remote_ip 203.0.113.7\n").unwrap();
+
+    let handler = CaddyHandler::new().with_main_config(main_config.clone());
+    assert_eq!(handler.validation_target(&snippet), main_config.as_path());
+    assert_eq!(
+        handler.validation_target(&main_config),
+        main_config.as_path()
+    );
+
+    // Without a top-level Caddyfile the edited file is validated by itself
+    let handler = CaddyHandler::new().with_main_config(temp_dir.path().join("missing"));
+    assert_eq!(handler.validation_target(&snippet), snippet.as_path());
+}
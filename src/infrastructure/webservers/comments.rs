@@ -0,0 +1,48 @@
+//! `#` comments and `# DDNS: <hostname>` tags, shared by the handlers of formats with `#`
+//! line comments (Caddyfile, Traefik YAML and TOML, Apache)
+
+/// Split a line into its code and comment part. `#` only starts a comment at the start of a
+/// token, so it can appear inside a word (`a#b`) or a quoted string.
+pub(crate) fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quote: Option<char> = None;
+    let mut token_start = true;
+    for (index, c) in line.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            token_start = false;
+            continue;
+        }
+        match c {
+            '#' if token_start => return (&line[..index], Some(&line[index + 1..])),
+            '"' | '\'' | '`' if token_start => quote = Some(c),
+            _ => {}
+        }
+        token_start = c.is_whitespace() || matches!(c, ',' | '[' | ']' | '{' | '}');
+    }
+    (line, None)
+}
+
+/// Extract the hostname from a `DDNS: <hostname>` or `DDNS for <hostname>` comment
+pub(crate) fn parse_ddns_tag(comment: &str) -> Option<String> {
+    let comment = comment.trim();
+    let hostname = comment
+        .strip_prefix("DDNS:")
+        .or_else(|| comment.strip_prefix("DDNS for "))?
+        .trim();
+    if hostname.is_empty() {
+        None
+    } else {
+        Some(hostname.to_string())
+    }
+}
+
+/// Whether a comment tags its directive for the hostname
+pub(crate) fn is_ddns_tag_for(comment: &str, hostname: &str) -> bool {
+    parse_ddns_tag(comment).is_some_and(|tag| tag == hostname)
+}
+
+#[cfg(test)]
+#[path = "comments_tests.rs"]
+mod tests;
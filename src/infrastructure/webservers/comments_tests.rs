@@ -0,0 +1,46 @@
+use super::*;
+
+#[test]
+fn test_split_comment_at_token_boundaries_only() {
+    assert_eq!(
+        split_comment("@office remote_ip 10.0.0.1 # DDNS: office.example.com"),
+        (
+            "@office remote_ip 10.0.0.1 ",
+            Some(" DDNS: office.example.com")
+        )
+    );
+    assert_eq!(split_comment("# whole line"), ("", Some(" whole line")));
+    assert_eq!(split_comment("color: a#b"), ("color: a#b", None));
+    assert_eq!(
+        split_comment(r##"rule: "Path(`/#x`)" # tag"##),
+        (r##"rule: "Path(`/#x`)" "##, Some(" tag"))
+    );
+    assert_eq!(
+        split_comment(r#"sourceRange = ["10.0.0.1"]# tag"#),
+        (r#"sourceRange = ["10.0.0.1"]"#, Some(" tag"))
+    );
+    // An apostrophe inside a word doesn't open a quote
+    assert_eq!(
+        split_comment("say don't # tag"),
+        ("say don't ", Some(" tag"))
+    );
+}
+
+#[test]
+fn test_parse_ddns_tag() {
+    assert_eq!(
+        parse_ddns_tag(" DDNS: office.example.com "),
+        Some("office.example.com".to_string())
+    );
+    assert_eq!(
+        parse_ddns_tag("DDNS for office.example.com"),
+        Some("office.example.com".to_string())
+    );
+    assert_eq!(parse_ddns_tag("DDNS:"), None);
+    assert_eq!(parse_ddns_tag("office allow list"), None);
+    assert!(is_ddns_tag_for(
+        "DDNS: office.example.com",
+        "office.example.com"
+    ));
+    assert!(!is_ddns_tag_for("DDNS: office.example.com", "example.com"));
+}
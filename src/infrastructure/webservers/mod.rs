@@ -1,5 +1,12 @@
+mod allow_list;
 pub mod apache;
+mod backup;
+pub mod caddy;
+mod comments;
 pub mod nginx;
+pub mod traefik;
 
 pub use apache::ApacheHandler;
+pub use caddy::CaddyHandler;
 pub use nginx::NginxHandler;
+pub use traefik::TraefikHandler;
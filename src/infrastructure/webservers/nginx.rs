@@ -4,8 +4,14 @@ use std::path::PathBuf;
 use std::process::Command;
 use tokio::fs;
 
-use crate::domain::entities::WebServerConfig;
+use crate::config::nginx::{line_access_directives, AccessDirective, AccessKind, NginxConfig};
+use crate::domain::entities::{BackupFile, WebServerConfig};
 use crate::domain::ports::WebServerHandler;
+use crate::domain::value_objects::{AddressDiff, IpNetwork};
+use crate::infrastructure::files::write_atomically;
+
+use super::allow_list::{apply_span_edits, line_span, plan_edits, AllowEntry, EntryEdit};
+use super::comments::is_ddns_tag_for;
 
 /// Nginx web server handler
 pub struct NginxHandler {
@@ -25,116 +31,122 @@ impl NginxHandler {
         &self,
         config_path: &std::path::Path,
     ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-
-        let backup_path = if let Some(backup_dir) = &self.backup_dir {
-            // Create backup directory if it doesn't exist
-            if !backup_dir.exists() {
-                fs::create_dir_all(backup_dir).await?;
-            }
-
-            // Create backup filename with original filename + timestamp
-            let filename = config_path
-                .file_name()
-                .unwrap_or_else(|| std::ffi::OsStr::new("config"))
-                .to_string_lossy();
-            backup_dir.join(format!("{}.bak.{}", filename, timestamp))
-        } else {
-            // Default behavior: same directory as original with .bak extension
-            config_path.with_extension(format!("bak.{}", timestamp))
-        };
-
-        fs::copy(config_path, &backup_path).await?;
-        Ok(backup_path)
+        super::backup::backup_file(config_path, self.backup_dir.as_deref()).await
     }
 
-    async fn update_nginx_config(
+    /// New content of the config file with the diff applied; None if no allow directive changes
+    async fn nginx_config_update(
         &self,
-        config_path: &std::path::Path,
+        config: &WebServerConfig,
         hostname: &str,
-        old_ip: Option<IpAddr>,
-        new_ip: IpAddr,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let content = fs::read_to_string(config_path).await?;
-        let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-        let mut updated = false;
-
-        // If we have an old IP, look for it and replace with new IP
-        if let Some(old_ip_addr) = old_ip {
-            // First pass: look for existing IP entries to replace
-            for (i, line) in lines.iter().enumerate() {
-                let trimmed = line.trim();
-
-                // Check if this line contains the old IP in an allow directive
-                if trimmed.starts_with("allow ") && trimmed.contains(&old_ip_addr.to_string()) {
-                    // Get the current indentation
-                    let indent = &line[..line.len() - line.trim_start().len()];
-
-                    // Preserve any existing comment
-                    let comment_part = if trimmed.contains('#') {
-                        let parts: Vec<&str> = trimmed.splitn(2, '#').collect();
-                        if parts.len() > 1 {
-                            format!(" # {}", parts[1].trim())
-                        } else {
-                            String::new()
-                        }
-                    } else {
-                        String::new()
-                    };
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let content = fs::read_to_string(&config.path).await?;
+        let allow_directives: Vec<AccessDirective> = access_directives(&config.path, &content)
+            .into_iter()
+            .filter(|d| d.kind == AccessKind::Allow)
+            .collect();
+
+        // Every allow directive holds one entry; `all` and other non-address values are skipped
+        let (directives, entries): (Vec<&AccessDirective>, Vec<AllowEntry>) = allow_directives
+            .iter()
+            .enumerate()
+            .filter_map(|(index, d)| {
+                let network = d.value.parse::<IpNetwork>().ok()?;
+                let tagged = d
+                    .comment
+                    .as_deref()
+                    .is_some_and(|comment| is_ddns_tag_for(comment, hostname));
+                Some((
+                    d,
+                    AllowEntry {
+                        network,
+                        text: d.value.clone(),
+                        group: index,
+                        tagged,
+                    },
+                ))
+            })
+            .unzip();
+
+        let edits = plan_edits(&entries, diff, config.rewrite_prefix);
+        if edits.is_empty() {
+            eprintln!(
+                "DEBUG: No allow directive to change for hostname: {} ({})",
+                hostname, diff
+            );
+            return Ok(None);
+        }
 
-                    // Replace this line with the new IP, preserving formatting and comments
-                    lines[i] = format!("{}allow {};{}", indent, new_ip, comment_part);
-                    updated = true;
+        let mut span_edits = Vec::new();
+        for edit in edits {
+            match edit {
+                EntryEdit::Replace { entry, value } => {
+                    let target = directives[entry];
                     eprintln!(
-                        "DEBUG: Replaced old IP {} with new IP {} for hostname: {}",
-                        old_ip_addr, new_ip, hostname
+                        "DEBUG: Replacing allow {} on line {} ({}) with {} for hostname: {}",
+                        target.value,
+                        target.line,
+                        describe_context(target),
+                        value,
+                        hostname
                     );
-                    break;
+                    // Only the address itself is rewritten; indentation, prefix length and
+                    // comments stay
+                    span_edits.push((target.value_span.start, target.value_span.end, value));
                 }
-            }
-        } else {
-            // If no old IP is stored, look for DDNS-managed entries (legacy support)
-            for (i, line) in lines.iter().enumerate() {
-                let trimmed = line.trim();
-
-                // Check if this is a DDNS-related allow entry for our hostname
-                if trimmed.starts_with("allow ")
-                    && (trimmed.contains(&format!("# DDNS: {}", hostname))
-                        || trimmed.contains(&format!("# DDNS for {}", hostname)))
-                {
-                    // Get the current indentation
-                    let indent = &line[..line.len() - line.trim_start().len()];
-
-                    // Replace this line with our new entry
-                    lines[i] = format!("{}allow {}; # DDNS: {}", indent, new_ip, hostname);
-                    updated = true;
+                EntryEdit::Remove { entry } => {
+                    let target = directives[entry];
+                    eprintln!(
+                        "DEBUG: Removing allow {} on line {} ({}) for hostname: {}",
+                        target.value,
+                        target.line,
+                        describe_context(target),
+                        hostname
+                    );
+                    let (start, end) = directive_extent(&content, target);
+                    span_edits.push((start, end, String::new()));
+                }
+                EntryEdit::InsertAfter { entry, value } => {
+                    let anchor = directives[entry];
                     eprintln!(
-                        "DEBUG: Replaced DDNS-commented entry with new IP {} for hostname: {}",
-                        new_ip, hostname
+                        "DEBUG: Adding allow {} after line {} ({}) for hostname: {}",
+                        value,
+                        anchor.line,
+                        describe_context(anchor),
+                        hostname
                     );
-                    break;
+                    let (position, line) = insertion_after(&content, anchor, &value);
+                    span_edits.push((position, position, line));
                 }
             }
         }
 
-        if updated {
-            let new_content = lines.join("\n");
-            eprintln!("DEBUG: About to write to config file: {:?}", config_path);
-            match fs::write(config_path, new_content).await {
-                Ok(()) => eprintln!("DEBUG: Successfully wrote config file"),
-                Err(e) => {
-                    eprintln!("DEBUG: Failed to write config file: {}", e);
-                    return Err(e.into());
-                }
+        let new_content = apply_span_edits(&content, span_edits);
+        Ok((new_content != content).then_some(new_content))
+    }
+
+    async fn update_nginx_config(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let config_path = &config.path;
+        let Some(new_content) = self.nginx_config_update(config, hostname, diff).await? else {
+            return Ok(false);
+        };
+
+        eprintln!("DEBUG: About to write to config file: {:?}", config_path);
+        match write_atomically(config_path, &new_content).await {
+            Ok(()) => eprintln!("DEBUG: Successfully wrote config file"),
+            Err(e) => {
+                eprintln!("DEBUG: Failed to write config file: {}", e);
+                return Err(e);
             }
-        } else {
-            eprintln!(
-                "DEBUG: No existing DDNS entry found for hostname: {}, not adding new entry",
-                hostname
-            );
         }
 
-        Ok(updated)
+        Ok(true)
     }
 }
 
@@ -144,11 +156,18 @@ impl WebServerHandler for NginxHandler {
         &self,
         config: &WebServerConfig,
         hostname: &str,
-        old_ip: Option<IpAddr>,
-        new_ip: IpAddr,
+        diff: &AddressDiff,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        self.update_nginx_config(&config.path, hostname, old_ip, new_ip)
-            .await
+        self.update_nginx_config(config, hostname, diff).await
+    }
+
+    async fn preview_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.nginx_config_update(config, hostname, diff).await
     }
 
     async fn validate_config(
@@ -280,6 +299,28 @@ impl WebServerHandler for NginxHandler {
         self.backup_file(&config.path).await
     }
 
+    async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::list_backups(&config.path, self.backup_dir.as_deref()).await
+    }
+
+    async fn remove_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::remove_file(backup_path).await
+    }
+
+    async fn restore_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::restore_file(backup_path, &config.path).await
+    }
+
     async fn test_configuration(
         &self,
         config: &WebServerConfig,
@@ -293,18 +334,26 @@ impl WebServerHandler for NginxHandler {
         ip: IpAddr,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let content = fs::read_to_string(&config.path).await?;
-        let ip_str = ip.to_string();
 
-        // Check if the IP exists in any allow directive
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("allow ") && trimmed.contains(&ip_str) {
-                eprintln!("DEBUG: Found IP {} in config line: {}", ip_str, trimmed);
+        // Check if the IP is managed by any allow directive
+        for directive in access_directives(&config.path, &content) {
+            let managed = directive.kind == AccessKind::Allow
+                && directive
+                    .value
+                    .parse::<IpNetwork>()
+                    .is_ok_and(|network| network.manages(ip, config.rewrite_prefix));
+            if managed {
+                eprintln!(
+                    "DEBUG: Found IP {} in config line {} ({})",
+                    ip,
+                    directive.line,
+                    describe_context(&directive)
+                );
                 return Ok(true);
             }
         }
 
-        eprintln!("DEBUG: IP {} not found in config file", ip_str);
+        eprintln!("DEBUG: IP {} not found in config file", ip);
         Ok(false)
     }
 
@@ -319,8 +368,86 @@ impl Default for NginxHandler {
     }
 }
 
+/// The `allow` / `deny` directives of a config file. A file the parser rejects (e.g. one with
+/// Lua or Perl blocks) is still handled, line by line, with a warning.
+fn access_directives(path: &std::path::Path, content: &str) -> Vec<AccessDirective> {
+    match NginxConfig::parse(content) {
+        Ok(config) => config.access_directives(),
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not parse {} ({}); matching allow directives line by line",
+                path.display(),
+                e
+            );
+            line_access_directives(content)
+        }
+    }
+}
+
+/// Human readable block context of a directive, e.g. `server > location /admin`
+fn describe_context(directive: &AccessDirective) -> String {
+    if directive.context.is_empty() {
+        return "top level".to_string();
+    }
+
+    directive
+        .context
+        .iter()
+        .map(|block| {
+            if block.args.is_empty() {
+                block.name.clone()
+            } else {
+                format!("{} {}", block.name, block.args.join(" "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+/// Range to delete for a directive: its whole line if nothing else is on it, otherwise just
+/// the directive and its trailing comment
+fn directive_extent(content: &str, directive: &AccessDirective) -> (usize, usize) {
+    let start = directive.span.start;
+    let end = directive
+        .comment_span
+        .map_or(directive.span.end, |span| span.end);
+    let (line_start, line_end) = line_span(content, start, end);
+    if content[line_start..start].trim().is_empty() && content[end..line_end].trim().is_empty() {
+        (line_start, line_end)
+    } else {
+        (start, end)
+    }
+}
+
+/// Position and text of a new `allow` line below `anchor`, with the same indentation and
+/// trailing comment
+fn insertion_after(content: &str, anchor: &AccessDirective, value: &str) -> (usize, String) {
+    let end = anchor.comment_span.map_or(anchor.span.end, |span| span.end);
+    let (line_start, line_end) = line_span(content, anchor.span.start, end);
+    let indent: String = content[line_start..anchor.span.start]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+
+    let mut line = format!("{}allow {};", indent, value);
+    if let Some(span) = anchor.comment_span {
+        line.push(' ');
+        line.push_str(&content[span.start..span.end]);
+    }
+    line.push('\n');
+    if !content[..line_end].ends_with('\n') {
+        line.insert(0, '\n');
+    }
+    (line_end, line)
+}
+
 /// Validate nginx configuration structure more strictly
 fn validate_nginx_structure(content: &str) -> bool {
+    // Constructs the parser doesn't know are left to the line checks below
+    if let Err(e) = NginxConfig::parse(content) {
+        eprintln!("DEBUG: Nginx config does not parse: {}", e);
+    }
+
     // Remove comments and empty lines for validation
     let lines: Vec<&str> = content
         .lines()
@@ -396,3 +523,8 @@ fn validate_nginx_structure(content: &str) -> bool {
 
     is_valid
 }
+
+// Include the test module
+#[cfg(test)]
+#[path = "nginx_tests.rs"]
+mod tests;
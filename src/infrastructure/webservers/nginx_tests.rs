@@ -0,0 +1,181 @@
+use super::*;
+use tempfile::tempdir;
+
+const NGINX_CONFIG: &str = "server {
+    listen 80;
+    location / {
+        allow 203.0.113.7/32; # DDNS: office.example.com
+        allow 2001:db8:aa:1200::/56;
+        deny all;
+    }
+}
+";
+
+async fn update(content: &str, old_ip: &str, new_ip: &str, rewrite_prefix: bool) -> (bool, String) {
+    update_with(
+        content,
+        &AddressDiff::replacement(old_ip.parse().unwrap(), new_ip.parse().unwrap()),
+        rewrite_prefix,
+    )
+    .await
+}
+
+async fn update_with(content: &str, diff: &AddressDiff, rewrite_prefix: bool) -> (bool, String) {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("site.conf");
+    std::fs::write(&path, content).unwrap();
+
+    let config = WebServerConfig::new(path.clone(), crate::domain::entities::WebServerType::Nginx)
+        .with_prefix_rewrite(rewrite_prefix);
+    let updated = NginxHandler::new()
+        .update_allow_list(&config, "office.example.com", diff)
+        .await
+        .unwrap();
+    (updated, std::fs::read_to_string(&path).unwrap())
+}
+
+#[tokio::test]
+async fn test_host_entry_in_cidr_notation() {
+    let (updated, content) = update(NGINX_CONFIG, "203.0.113.7", "203.0.113.8", false).await;
+    assert!(updated);
+    assert!(content.contains("        allow 203.0.113.8/32; # DDNS: office.example.com\n"));
+}
+
+#[tokio::test]
+async fn test_prefix_rewrite() {
+    let (updated, content) = update(
+        NGINX_CONFIG,
+        "2001:db8:aa:1234::1",
+        "2001:db8:ff:101::1",
+        false,
+    )
+    .await;
+    assert!(!updated);
+    assert_eq!(content, NGINX_CONFIG);
+
+    let (updated, content) = update(
+        NGINX_CONFIG,
+        "2001:db8:aa:1234::1",
+        "2001:db8:ff:101::1",
+        true,
+    )
+    .await;
+    assert!(updated);
+    assert!(content.contains("        allow 2001:db8:ff:100::/56;\n"));
+}
+
+#[tokio::test]
+async fn test_check_ip_in_config_matches_networks_only_with_rewrite() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("site.conf");
+    std::fs::write(&path, NGINX_CONFIG).unwrap();
+    let handler = NginxHandler::new();
+    let config = WebServerConfig::new(path, crate::domain::entities::WebServerType::Nginx);
+    let inside: IpAddr = "2001:db8:aa:1234::1".parse().unwrap();
+
+    assert!(handler
+        .check_ip_in_config(&config, "203.0.113.7".parse().unwrap())
+        .await
+        .unwrap());
+    assert!(!handler.check_ip_in_config(&config, inside).await.unwrap());
+    assert!(handler
+        .check_ip_in_config(&config.with_prefix_rewrite(true), inside)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_address_set_changes_add_and_remove_lines() {
+    let content = "server {
+    location / {
+        allow 203.0.113.7; # DDNS: office.example.com
+        allow 203.0.113.9; # DDNS: office.example.com
+        deny all;
+    }
+}
+";
+    let stored: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.9".parse().unwrap(),
+    ];
+    let current: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.10".parse().unwrap(),
+        "203.0.113.11".parse().unwrap(),
+    ];
+
+    let (updated, content) =
+        update_with(content, &AddressDiff::between(&stored, &current), false).await;
+    assert!(updated);
+    assert_eq!(
+        content,
+        "server {
+    location / {
+        allow 203.0.113.7; # DDNS: office.example.com
+        allow 203.0.113.10; # DDNS: office.example.com
+        allow 203.0.113.11; # DDNS: office.example.com
+        deny all;
+    }
+}
+"
+    );
+
+    // Shrinking the set drops the lines of the removed addresses
+    let (updated, content) = update_with(
+        &content,
+        &AddressDiff::between(&current, &stored[..1]),
+        false,
+    )
+    .await;
+    assert!(updated);
+    assert_eq!(
+        content,
+        "server {
+    location / {
+        allow 203.0.113.7; # DDNS: office.example.com
+        deny all;
+    }
+}
+"
+    );
+}
+
+/// The Lua block isn't nginx syntax, so the parser rejects the file
+const LUA_CONFIG: &str = "server {
+    listen 80;
+    location /api {
+        content_by_lua_block {
+            ngx.say(\"hello\")
+        }
+    }
+    location / {
+        allow 203.0.113.7; # DDNS: office.example.com
+        deny all;
+    }
+}
+";
+
+#[tokio::test]
+async fn test_unparseable_config_falls_back_to_lines() {
+    assert!(NginxConfig::parse(LUA_CONFIG).is_err());
+    assert!(crate::config::nginx::is_nginx_config_content(LUA_CONFIG));
+
+    let (updated, content) = update(LUA_CONFIG, "203.0.113.7", "203.0.113.8", false).await;
+    assert!(updated);
+    assert_eq!(
+        content,
+        LUA_CONFIG.replace("allow 203.0.113.7;", "allow 203.0.113.8;")
+    );
+
+    // Without a stored address the tagged entry takes the new address
+    let diff = AddressDiff {
+        added: vec!["203.0.113.9".parse().unwrap()],
+        ..Default::default()
+    };
+    let (updated, content) = update_with(LUA_CONFIG, &diff, false).await;
+    assert!(updated);
+    assert_eq!(
+        content,
+        LUA_CONFIG.replace("allow 203.0.113.7;", "allow 203.0.113.9;")
+    );
+}
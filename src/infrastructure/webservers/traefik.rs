@@ -0,0 +1,740 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::domain::entities::{BackupFile, WebServerConfig, WebServerType};
+use crate::domain::ports::WebServerHandler;
+use crate::domain::value_objects::{AddressDiff, IpNetwork};
+use crate::infrastructure::files::write_atomically;
+
+use super::allow_list::{apply_span_edits, line_span, plan_edits, AllowEntry, EntryEdit};
+use super::comments::{parse_ddns_tag, split_comment};
+
+/// Middleware types holding a `sourceRange` list (`ipWhiteList` is the pre-v3 name)
+const ALLOW_LIST_MIDDLEWARES: [&str; 2] = ["ipAllowList", "ipWhiteList"];
+
+/// Traefik web server handler for file-provider dynamic configuration
+///
+/// Manages the `sourceRange` entries of `ipAllowList` / `ipWhiteList` middlewares in YAML or
+/// TOML files. A middleware is tagged for a hostname with a `# DDNS: <hostname>` comment on
+/// (or directly above) its name, or on any line inside its definition:
+///
+/// ```text
+/// http:
+///   middlewares:
+///     office-allow: # DDNS: office.example.com
+///       ipAllowList:
+///         sourceRange:
+///           - "203.0.113.7"
+/// ```
+///
+/// Traefik watches these files and applies changes on its own, so no reload is needed.
+pub struct TraefikHandler {
+    backup_dir: Option<PathBuf>,
+}
+
+impl TraefikHandler {
+    pub fn new() -> Self {
+        Self { backup_dir: None }
+    }
+
+    pub fn with_backup_dir(backup_dir: Option<PathBuf>) -> Self {
+        Self { backup_dir }
+    }
+
+    async fn backup_file(
+        &self,
+        config_path: &Path,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::backup_file(config_path, self.backup_dir.as_deref()).await
+    }
+
+    /// New content of the config file with the diff applied; None if no managed entry changes
+    async fn traefik_config_update(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let content = fs::read_to_string(&config.path).await?;
+        let document = scan_document(&config.path, &content);
+        Ok(apply_source_range_diff(
+            &document,
+            &content,
+            hostname,
+            diff,
+            config.rewrite_prefix,
+        ))
+    }
+
+    async fn update_traefik_config(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match self.traefik_config_update(config, hostname, diff).await? {
+            Some(new_content) => {
+                // Traefik hot-reloads the file, so it must never see a partial write
+                write_atomically(&config.path, &new_content).await?;
+                eprintln!(
+                    "DEBUG: Updated Traefik sourceRange ({}) for hostname: {}",
+                    diff, hostname
+                );
+                Ok(true)
+            }
+            None => {
+                eprintln!(
+                    "DEBUG: No managed Traefik sourceRange entry found for hostname: {}, not adding new entry",
+                    hostname
+                );
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl WebServerHandler for TraefikHandler {
+    async fn update_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.update_traefik_config(config, hostname, diff).await
+    }
+
+    async fn preview_allow_list(
+        &self,
+        config: &WebServerConfig,
+        hostname: &str,
+        diff: &AddressDiff,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.traefik_config_update(config, hostname, diff).await
+    }
+
+    async fn validate_config(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        if !config.path.exists() {
+            return Ok(false);
+        }
+
+        // Traefik has no offline validation command for dynamic configuration
+        let content = fs::read_to_string(&config.path).await?;
+        let is_valid = match DocumentFormat::detect(&config.path, &content) {
+            DocumentFormat::Toml => content.parse::<toml::Table>().is_ok(),
+            DocumentFormat::Yaml => is_valid_yaml(&content),
+        } && scan_document(&config.path, &content)
+            .entries
+            .iter()
+            .all(|entry| is_valid_source_range(&entry.value));
+
+        eprintln!(
+            "DEBUG: Traefik validation for {:?}: {}",
+            config.path.file_name(),
+            is_valid
+        );
+        Ok(is_valid)
+    }
+
+    async fn reload_server(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // The file provider watches the dynamic configuration and reloads it automatically
+        eprintln!("Traefik picks up file provider changes automatically, no reload needed");
+        Ok(())
+    }
+
+    async fn create_backup(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        self.backup_file(&config.path).await
+    }
+
+    async fn list_backups(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<Vec<BackupFile>, Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::list_backups(&config.path, self.backup_dir.as_deref()).await
+    }
+
+    async fn remove_backup(
+        &self,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::remove_file(backup_path).await
+    }
+
+    async fn restore_backup(
+        &self,
+        config: &WebServerConfig,
+        backup_path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        super::backup::restore_file(backup_path, &config.path).await
+    }
+
+    async fn test_configuration(
+        &self,
+        config: &WebServerConfig,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.validate_config(config).await
+    }
+
+    async fn check_ip_in_config(
+        &self,
+        config: &WebServerConfig,
+        ip: IpAddr,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let content = fs::read_to_string(&config.path).await?;
+
+        let found = scan_document(&config.path, &content)
+            .entries
+            .iter()
+            .any(|entry| entry.manages(ip, config.rewrite_prefix));
+
+        if found {
+            eprintln!("DEBUG: Found IP {} in Traefik sourceRange", ip);
+        } else {
+            eprintln!("DEBUG: IP {} not found in Traefik config file", ip);
+        }
+        Ok(found)
+    }
+
+    fn server_type(&self) -> WebServerType {
+        WebServerType::Traefik
+    }
+}
+
+impl Default for TraefikHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check if content looks like a Traefik dynamic configuration (used by config discovery)
+pub fn is_traefik_dynamic_config(content: &str) -> bool {
+    let has_allow_list = ALLOW_LIST_MIDDLEWARES
+        .iter()
+        .any(|middleware| content.contains(middleware))
+        && content.contains("sourceRange");
+    let has_dynamic_root = content.lines().any(|line| {
+        let line = line.trim_end();
+        line == "http:" || line == "tcp:" || line.starts_with("[http.") || line.starts_with("[tcp.")
+    });
+    let has_dynamic_sections =
+        has_dynamic_root && (content.contains("middlewares") || content.contains("routers"));
+
+    has_allow_list || has_dynamic_sections
+}
+
+/// Serialization format of a dynamic configuration file
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DocumentFormat {
+    Yaml,
+    Toml,
+}
+
+impl DocumentFormat {
+    fn detect(path: &Path, content: &str) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => DocumentFormat::Toml,
+            Some("yml") | Some("yaml") => DocumentFormat::Yaml,
+            // Without an extension the content decides: YAML dynamic configs are never valid TOML
+            _ if content.parse::<toml::Table>().is_ok() => DocumentFormat::Toml,
+            _ => DocumentFormat::Yaml,
+        }
+    }
+}
+
+/// A `sourceRange` entry of an allow list middleware
+struct SourceRangeEntry {
+    middleware: String,
+    /// Byte span of the value within the file, excluding quotes
+    start: usize,
+    end: usize,
+    value: String,
+    /// A YAML `- value` line rather than an element of a `[...]` list
+    block_item: bool,
+}
+
+impl SourceRangeEntry {
+    fn network(&self) -> Option<IpNetwork> {
+        self.value.parse().ok()
+    }
+
+    fn manages(&self, ip: IpAddr, rewrite_prefix: bool) -> bool {
+        self.network()
+            .is_some_and(|network| network.manages(ip, rewrite_prefix))
+    }
+
+    /// The quote character around the value, if any
+    fn quote<'a>(&self, content: &'a str) -> &'a str {
+        let before = &content[..self.start];
+        if before.ends_with('"') || before.ends_with('\'') {
+            &before[before.len() - 1..]
+        } else {
+            ""
+        }
+    }
+
+    /// Byte span of the value including its quotes
+    fn raw_span(&self, content: &str) -> (usize, usize) {
+        let quote = self.quote(content).len();
+        (self.start - quote, self.end + quote)
+    }
+}
+
+/// The parts of a dynamic configuration file relevant for allow list updates
+#[derive(Default)]
+struct TraefikDocument {
+    entries: Vec<SourceRangeEntry>,
+    /// Hostnames tagged with `# DDNS: <hostname>` per middleware name
+    tags: HashMap<String, Vec<String>>,
+}
+
+impl TraefikDocument {
+    fn tag(&mut self, middleware: &str, comment: Option<&str>) {
+        if let Some(hostname) = comment.and_then(parse_ddns_tag) {
+            self.tags
+                .entry(middleware.to_string())
+                .or_default()
+                .push(hostname);
+        }
+    }
+
+    fn is_tagged(&self, middleware: &str, hostname: &str) -> bool {
+        self.tags
+            .get(middleware)
+            .is_some_and(|hostnames| hostnames.iter().any(|h| h == hostname))
+    }
+}
+
+/// Apply an address diff to the `sourceRange` entries and return the new file content, or None
+/// if nothing changes.
+///
+/// Middlewares tagged for the hostname take precedence; without one, all entries managing a
+/// stored address are updated, like the nginx `allow` handling. With `rewrite_prefix`, CIDR
+/// ranges that contain the old IP move to the new IP's network and keep their prefix length.
+/// Added addresses join the list holding the hostname's other addresses; a list is never emptied.
+fn apply_source_range_diff(
+    document: &TraefikDocument,
+    content: &str,
+    hostname: &str,
+    diff: &AddressDiff,
+    rewrite_prefix: bool,
+) -> Option<String> {
+    let any_tagged = document
+        .entries
+        .iter()
+        .any(|entry| document.is_tagged(&entry.middleware, hostname));
+
+    let mut middlewares: Vec<&str> = Vec::new();
+    let mut sources = Vec::new();
+    let mut entries = Vec::new();
+    for entry in &document.entries {
+        let tagged = document.is_tagged(&entry.middleware, hostname);
+        if any_tagged && !tagged {
+            continue;
+        }
+        let Some(network) = entry.network() else {
+            continue;
+        };
+        let group = match middlewares.iter().position(|m| *m == entry.middleware) {
+            Some(group) => group,
+            None => {
+                middlewares.push(&entry.middleware);
+                middlewares.len() - 1
+            }
+        };
+        sources.push(entry);
+        entries.push(AllowEntry {
+            network,
+            text: entry.value.clone(),
+            group,
+            tagged,
+        });
+    }
+
+    let edits = plan_edits(&entries, diff, rewrite_prefix);
+    let removed: Vec<usize> = edits
+        .iter()
+        .filter_map(|edit| match edit {
+            EntryEdit::Remove { entry } => Some(*entry),
+            _ => None,
+        })
+        .collect();
+
+    let mut span_edits = Vec::new();
+    for edit in &edits {
+        match edit {
+            EntryEdit::Replace { entry, value } => {
+                let source = sources[*entry];
+                span_edits.push((source.start, source.end, value.clone()));
+            }
+            EntryEdit::Remove { entry } => {
+                // All entries of the middleware's list in file order, which is how they were scanned
+                let list: Vec<usize> = (0..entries.len())
+                    .filter(|index| entries[*index].group == entries[*entry].group)
+                    .collect();
+                if list.iter().all(|index| removed.contains(index)) {
+                    eprintln!(
+                        "DEBUG: Keeping {} so the sourceRange of {} is not emptied",
+                        sources[*entry].value, sources[*entry].middleware
+                    );
+                    continue;
+                }
+                span_edits.push(removal_span(content, &sources, &list, &removed, *entry));
+            }
+            EntryEdit::InsertAfter { entry, value } => {
+                let anchor = sources[*entry];
+                let quote = anchor.quote(content);
+                let (_, raw_end) = anchor.raw_span(content);
+                if anchor.block_item {
+                    let (line_start, line_end) = line_span(content, anchor.start, anchor.end);
+                    let mut line =
+                        format!("{}{}{}\n", &content[line_start..anchor.start], value, quote);
+                    if !content[..line_end].ends_with('\n') {
+                        line.insert(0, '\n');
+                    }
+                    span_edits.push((line_end, line_end, line));
+                } else {
+                    let item = format!(", {}{}{}", quote, value, quote);
+                    span_edits.push((raw_end, raw_end, item));
+                }
+            }
+        }
+    }
+
+    let new_content = apply_span_edits(content, span_edits);
+    (new_content != content).then_some(new_content)
+}
+
+/// Range to delete for a removed entry, including its list separator.
+///
+/// Block items lose their line. Flow items take the separator before them, or the one after
+/// them while only removed items precede them, so adjacent removals never overlap.
+fn removal_span(
+    content: &str,
+    sources: &[&SourceRangeEntry],
+    list: &[usize],
+    removed: &[usize],
+    entry: usize,
+) -> (usize, usize, String) {
+    let source = sources[entry];
+    let (raw_start, raw_end) = source.raw_span(content);
+    if source.block_item {
+        let (line_start, line_end) = line_span(content, raw_start, raw_end);
+        return (line_start, line_end, String::new());
+    }
+
+    let position = list.iter().position(|index| *index == entry).unwrap_or(0);
+    if list[..position].iter().all(|index| removed.contains(index)) {
+        let (next_start, _) = sources[list[position + 1]].raw_span(content);
+        (raw_start, next_start, String::new())
+    } else {
+        let (_, previous_end) = sources[list[position - 1]].raw_span(content);
+        (previous_end, raw_end, String::new())
+    }
+}
+
+fn scan_document(path: &Path, content: &str) -> TraefikDocument {
+    match DocumentFormat::detect(path, content) {
+        DocumentFormat::Yaml => scan_yaml(content),
+        DocumentFormat::Toml => scan_toml(content),
+    }
+}
+
+/// Name of the middleware a key path points into (`http.middlewares.<name>...`)
+fn middleware_of(path: &[String]) -> Option<&str> {
+    if path.len() >= 3 && (path[0] == "http" || path[0] == "tcp") && path[1] == "middlewares" {
+        Some(&path[2])
+    } else {
+        None
+    }
+}
+
+/// Whether a key path is `<http|tcp>.middlewares.<name>.<ipAllowList|ipWhiteList>.sourceRange`
+fn is_source_range_path(path: &[String]) -> bool {
+    path.len() == 5
+        && middleware_of(path).is_some()
+        && ALLOW_LIST_MIDDLEWARES.contains(&path[3].as_str())
+        && path[4] == "sourceRange"
+}
+
+fn scan_yaml(content: &str) -> TraefikDocument {
+    let mut document = TraefikDocument::default();
+    // Mapping keys leading to the current line, with their indentation
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    // Middleware of a `sourceRange: [` list that continues on the next lines
+    let mut open_list: Option<String> = None;
+    let mut pending_tags: Vec<&str> = Vec::new();
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let (code, comment) = split_comment(line.trim_end_matches(['\n', '\r']));
+
+        if let Some(middleware) = open_list.clone() {
+            document.tag(&middleware, comment);
+            let closed = scan_flow_items(code, 0, |start, end| {
+                document.entries.push(SourceRangeEntry {
+                    middleware: middleware.clone(),
+                    start: line_start + start,
+                    end: line_start + end,
+                    value: code[start..end].to_string(),
+                    block_item: false,
+                });
+            });
+            if closed {
+                open_list = None;
+            }
+            continue;
+        }
+
+        let indent = code.len() - code.trim_start().len();
+        let trimmed = code.trim();
+
+        if trimmed.is_empty() {
+            pending_tags.extend(comment);
+            continue;
+        }
+
+        if trimmed.starts_with('-') {
+            // Sequence items may sit at the same indentation as their parent key
+            while stack.last().is_some_and(|(i, _)| *i > indent) {
+                stack.pop();
+            }
+        } else if let Some((key, rest)) = split_yaml_key(trimmed) {
+            while stack.last().is_some_and(|(i, _)| *i >= indent) {
+                stack.pop();
+            }
+            stack.push((indent, key));
+
+            let rest_start = code.len() - code.trim_start().len() + (trimmed.len() - rest.len());
+            let path: Vec<String> = stack.iter().map(|(_, k)| k.clone()).collect();
+            if is_source_range_path(&path) && rest.trim_start().starts_with('[') {
+                let middleware = path[2].clone();
+                let open = rest_start + rest.find('[').unwrap_or(0);
+                let closed = scan_flow_items(code, open + 1, |start, end| {
+                    document.entries.push(SourceRangeEntry {
+                        middleware: middleware.clone(),
+                        start: line_start + start,
+                        end: line_start + end,
+                        value: code[start..end].to_string(),
+                        block_item: false,
+                    });
+                });
+                if !closed {
+                    open_list = Some(middleware);
+                }
+            }
+        }
+
+        let path: Vec<String> = stack.iter().map(|(_, k)| k.clone()).collect();
+        if let Some(middleware) = middleware_of(&path).map(str::to_string) {
+            for tag in pending_tags.drain(..) {
+                document.tag(&middleware, Some(tag));
+            }
+            document.tag(&middleware, comment);
+
+            if is_source_range_path(&path) && trimmed.starts_with('-') {
+                let item_start = indent + 1;
+                if let Some((start, end)) = scalar_span(code, item_start) {
+                    document.entries.push(SourceRangeEntry {
+                        middleware,
+                        start: line_start + start,
+                        end: line_start + end,
+                        value: code[start..end].to_string(),
+                        block_item: true,
+                    });
+                }
+            }
+        }
+        pending_tags.clear();
+    }
+
+    document
+}
+
+fn scan_toml(content: &str) -> TraefikDocument {
+    let mut document = TraefikDocument::default();
+    let mut table: Vec<String> = Vec::new();
+    // Middleware of a `sourceRange = [` array that continues on the next lines
+    let mut open_array: Option<String> = None;
+    let mut pending_tags: Vec<&str> = Vec::new();
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let (code, comment) = split_comment(line.trim_end_matches(['\n', '\r']));
+
+        if let Some(middleware) = open_array.clone() {
+            document.tag(&middleware, comment);
+            let closed = scan_flow_items(code, 0, |start, end| {
+                document.entries.push(SourceRangeEntry {
+                    middleware: middleware.clone(),
+                    start: line_start + start,
+                    end: line_start + end,
+                    value: code[start..end].to_string(),
+                    block_item: false,
+                });
+            });
+            if closed {
+                open_array = None;
+            }
+            continue;
+        }
+
+        let trimmed = code.trim();
+        if trimmed.is_empty() {
+            pending_tags.extend(comment);
+            continue;
+        }
+
+        let key_path = if let Some(header) = trimmed.strip_prefix('[') {
+            table = split_dotted_key(header.trim_start_matches('[').trim_end_matches(']'));
+            table.clone()
+        } else if let Some((key, value)) = trimmed.split_once('=') {
+            let mut path = table.clone();
+            path.extend(split_dotted_key(key));
+
+            if is_source_range_path(&path) && value.trim_start().starts_with('[') {
+                let middleware = path[2].clone();
+                let open = code.find('=').unwrap_or(0) + 1 + value.find('[').unwrap_or(0);
+                let closed = scan_flow_items(code, open + 1, |start, end| {
+                    document.entries.push(SourceRangeEntry {
+                        middleware: middleware.clone(),
+                        start: line_start + start,
+                        end: line_start + end,
+                        value: code[start..end].to_string(),
+                        block_item: false,
+                    });
+                });
+                if !closed {
+                    open_array = Some(middleware);
+                }
+            }
+            path
+        } else {
+            table.clone()
+        };
+
+        if let Some(middleware) = middleware_of(&key_path).map(str::to_string) {
+            for tag in pending_tags.drain(..) {
+                document.tag(&middleware, Some(tag));
+            }
+            document.tag(&middleware, comment);
+        }
+        pending_tags.clear();
+    }
+
+    document
+}
+
+/// Split `key: rest` into the unquoted key and the rest of the line
+fn split_yaml_key(trimmed: &str) -> Option<(String, &str)> {
+    let (key, rest) = if let Some(quoted) = trimmed.strip_prefix(['"', '\'']) {
+        let quote = trimmed.chars().next()?;
+        let end = quoted.find(quote)?;
+        let rest = quoted[end + 1..].strip_prefix(':')?;
+        (quoted[..end].to_string(), rest)
+    } else {
+        let colon = trimmed.find(':')?;
+        let rest = &trimmed[colon + 1..];
+        if !(rest.is_empty() || rest.starts_with(' ')) {
+            return None;
+        }
+        (trimmed[..colon].trim().to_string(), rest)
+    };
+    Some((key, rest))
+}
+
+/// Split a TOML dotted key (`http.middlewares."office-allow".ipAllowList`) into its parts
+fn split_dotted_key(key: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+
+    for c in key.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('.', None) => parts.push(std::mem::take(&mut current).trim().to_string()),
+            (c, _) => current.push(c),
+        }
+    }
+    parts.push(current.trim().to_string());
+    parts
+}
+
+/// Call `item` with the span of each element of a `[a, "b", ...]` list starting at `from`.
+/// Returns whether the closing bracket was found on this line.
+fn scan_flow_items(code: &str, from: usize, mut item: impl FnMut(usize, usize)) -> bool {
+    let bytes = code.as_bytes();
+    let mut index = from;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b']' => return true,
+            b' ' | b'\t' | b',' => index += 1,
+            _ => match scalar_span(code, index) {
+                Some((start, end)) => {
+                    item(start, end);
+                    index = if bytes.get(end) == Some(&b'"') || bytes.get(end) == Some(&b'\'') {
+                        end + 1
+                    } else {
+                        end
+                    };
+                }
+                None => index += 1,
+            },
+        }
+    }
+    false
+}
+
+/// Span of a (possibly quoted) scalar starting at or after `from`, excluding the quotes
+fn scalar_span(code: &str, from: usize) -> Option<(usize, usize)> {
+    let rest = code.get(from..)?;
+    let start = from + (rest.len() - rest.trim_start().len());
+    let rest = &code[start..];
+    let first = rest.chars().next()?;
+
+    if first == '"' || first == '\'' {
+        let end = rest[1..].find(first)?;
+        Some((start + 1, start + 1 + end))
+    } else {
+        let end = rest
+            .find([',', ']', ' ', '\t'])
+            .unwrap_or(rest.trim_end().len());
+        if end == 0 {
+            None
+        } else {
+            Some((start, start + end))
+        }
+    }
+}
+
+/// `sourceRange` entries are single addresses or CIDR ranges
+fn is_valid_source_range(value: &str) -> bool {
+    value.parse::<IpNetwork>().is_ok()
+}
+
+/// Whether the content is a non-empty YAML document
+fn is_valid_yaml(content: &str) -> bool {
+    serde_yaml_ng::from_str::<serde_yaml_ng::Value>(content)
+        .is_ok_and(|document| !document.is_null())
+}
+
+// Include the test module
+#[cfg(test)]
+#[path = "traefik_tests.rs"]
+mod tests;
@@ -0,0 +1,280 @@
+use super::*;
+
+/// Diff for one address moving from `old_ip`, or appearing without a stored address
+fn change(old_ip: Option<IpAddr>, new_ip: IpAddr) -> AddressDiff {
+    match old_ip {
+        Some(old_ip) => AddressDiff::replacement(old_ip, new_ip),
+        None => AddressDiff {
+            added: vec![new_ip],
+            ..Default::default()
+        },
+    }
+}
+
+const YAML_CONFIG: &str = r#"http:
+  routers:
+    office:
+      rule: "Host(`office.example.com`)"
+      middlewares:
+        - office-allow
+  middlewares:
+    # DDNS: office.example.com
+    office-allow:
+      ipAllowList:
+        sourceRange:
+          - "203.0.113.7"
+          - 10.0.0.0/8
+    vpn-allow:
+      ipWhiteList:
+        sourceRange: ["198.51.100.4", "203.0.113.7"]
+"#;
+
+const TOML_CONFIG: &str = r#"[http.middlewares.office-allow.ipAllowList] # DDNS: office.example.com
+  sourceRange = [
+    "203.0.113.7",
+    "10.0.0.0/8",
+  ]
+
+[http.middlewares."vpn-allow".ipWhiteList]
+  sourceRange = ["198.51.100.4"] # DDNS: vpn.example.com
+"#;
+
+fn values(document: &TraefikDocument) -> Vec<(&str, &str)> {
+    document
+        .entries
+        .iter()
+        .map(|entry| (entry.middleware.as_str(), entry.value.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_scan_yaml_block_and_flow_sequences() {
+    let document = scan_yaml(YAML_CONFIG);
+
+    assert_eq!(
+        values(&document),
+        vec![
+            ("office-allow", "203.0.113.7"),
+            ("office-allow", "10.0.0.0/8"),
+            ("vpn-allow", "198.51.100.4"),
+            ("vpn-allow", "203.0.113.7"),
+        ]
+    );
+    assert!(document.is_tagged("office-allow", "office.example.com"));
+    assert!(!document.is_tagged("vpn-allow", "office.example.com"));
+}
+
+#[test]
+fn test_scan_toml_multiline_arrays_and_quoted_keys() {
+    let document = scan_toml(TOML_CONFIG);
+
+    assert_eq!(
+        values(&document),
+        vec![
+            ("office-allow", "203.0.113.7"),
+            ("office-allow", "10.0.0.0/8"),
+            ("vpn-allow", "198.51.100.4"),
+        ]
+    );
+    assert!(document.is_tagged("office-allow", "office.example.com"));
+    assert!(document.is_tagged("vpn-allow", "vpn.example.com"));
+}
+
+#[test]
+fn test_tagged_middleware_takes_precedence() {
+    let document = scan_yaml(YAML_CONFIG);
+    let updated = apply_source_range_diff(
+        &document,
+        YAML_CONFIG,
+        "office.example.com",
+        &change(
+            Some("203.0.113.7".parse().unwrap()),
+            "203.0.113.99".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+
+    assert!(updated.contains("          - \"203.0.113.99\"\n"));
+    // The untagged middleware sharing the old IP is left alone
+    assert!(updated.contains(r#"sourceRange: ["198.51.100.4", "203.0.113.7"]"#));
+}
+
+#[test]
+fn test_untagged_entries_replaced_by_old_ip() {
+    let document = scan_yaml(YAML_CONFIG);
+    let updated = apply_source_range_diff(
+        &document,
+        YAML_CONFIG,
+        "other.example.com",
+        &change(
+            Some("198.51.100.4".parse().unwrap()),
+            "198.51.100.5".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+
+    assert!(updated.contains(r#"sourceRange: ["198.51.100.5", "203.0.113.7"]"#));
+}
+
+#[test]
+fn test_single_tagged_entry_without_stored_ip() {
+    let document = scan_toml(TOML_CONFIG);
+    let updated = apply_source_range_diff(
+        &document,
+        TOML_CONFIG,
+        "vpn.example.com",
+        &change(None, "198.51.100.5".parse().unwrap()),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains(r#"sourceRange = ["198.51.100.5"] # DDNS: vpn.example.com"#));
+
+    // Two entries in the tagged middleware make the target ambiguous
+    assert!(apply_source_range_diff(
+        &document,
+        TOML_CONFIG,
+        "office.example.com",
+        &change(None, "198.51.100.5".parse().unwrap()),
+        false
+    )
+    .is_none());
+}
+
+#[test]
+fn test_cidr_ranges_follow_the_old_ip() {
+    let content = "http:\n  middlewares:\n    home-allow:\n      ipAllowList:\n        sourceRange:\n          - 203.0.113.7/32\n          - 2001:db8:aa:1200::/56\n";
+    let document = scan_yaml(content);
+
+    let updated = apply_source_range_diff(
+        &document,
+        content,
+        "home.example.com",
+        &change(
+            Some("203.0.113.7".parse().unwrap()),
+            "203.0.113.8".parse().unwrap(),
+        ),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains("          - 203.0.113.8/32\n"));
+
+    let old_ip = "2001:db8:aa:1234::1".parse().unwrap();
+    let new_ip = "2001:db8:ab:5501::1".parse().unwrap();
+    assert!(apply_source_range_diff(
+        &document,
+        content,
+        "home.example.com",
+        &change(Some(old_ip), new_ip),
+        false
+    )
+    .is_none());
+    let updated = apply_source_range_diff(
+        &document,
+        content,
+        "home.example.com",
+        &change(Some(old_ip), new_ip),
+        true,
+    )
+    .unwrap();
+    assert!(updated.contains("          - 2001:db8:ab:5500::/56\n"));
+}
+
+#[test]
+fn test_is_traefik_dynamic_config() {
+    assert!(is_traefik_dynamic_config(YAML_CONFIG));
+    assert!(is_traefik_dynamic_config(TOML_CONFIG));
+    assert!(!is_traefik_dynamic_config(
+        "server {\n    location / {\n        proxy_pass http://backend;\n    }\n}\n"
+    ));
+}
+
+#[test]
+fn test_validate_structures() {
+    assert!(is_valid_yaml(YAML_CONFIG));
+    assert!(!is_valid_yaml("http:\n\tmiddlewares:\n"));
+    assert!(!is_valid_yaml("# only a comment\n"));
+    assert!(TOML_CONFIG.parse::<toml::Table>().is_ok());
+    assert!(is_valid_source_range("2001:db8::/56"));
+    assert!(!is_valid_source_range("not-an-ip"));
+    assert!(!is_valid_source_range("10.0.0.0/33"));
+}
+
+#[test]
+fn test_address_set_changes_in_flow_lists() {
+    let content = "[http.middlewares.home.ipAllowList]\n  sourceRange = [\"203.0.113.7\", \"203.0.113.9\", \"10.0.0.0/8\"] # DDNS: home.example.com\n";
+    let document = scan_toml(content);
+    let stored: Vec<IpAddr> = vec![
+        "203.0.113.7".parse().unwrap(),
+        "203.0.113.9".parse().unwrap(),
+    ];
+    let current: Vec<IpAddr> = vec![
+        "203.0.113.9".parse().unwrap(),
+        "198.51.100.4".parse().unwrap(),
+        "198.51.100.5".parse().unwrap(),
+    ];
+
+    let updated = apply_source_range_diff(
+        &document,
+        content,
+        "home.example.com",
+        &AddressDiff::between(&stored, &current),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains(
+        r#"sourceRange = ["198.51.100.4", "203.0.113.9", "198.51.100.5", "10.0.0.0/8"] # DDNS"#
+    ));
+
+    let document = scan_toml(&updated);
+    let updated = apply_source_range_diff(
+        &document,
+        &updated,
+        "home.example.com",
+        &AddressDiff::between(&current, &current[1..2]),
+        false,
+    )
+    .unwrap();
+    assert!(updated.contains(r#"sourceRange = ["198.51.100.4", "10.0.0.0/8"] # DDNS"#));
+}
+
+const MULTILINE_FLOW_YAML: &str = r#"http:
+  middlewares:
+    # DDNS: office.example.com
+    office-allow:
+      ipAllowList:
+        sourceRange: [
+          "203.0.113.7",
+          "10.0.0.0/8"
+        ]
+"#;
+
+#[test]
+fn test_flow_lists_spanning_lines() {
+    assert!(is_valid_yaml(MULTILINE_FLOW_YAML));
+    let document = scan_yaml(MULTILINE_FLOW_YAML);
+    assert_eq!(
+        values(&document),
+        vec![
+            ("office-allow", "203.0.113.7"),
+            ("office-allow", "10.0.0.0/8")
+        ]
+    );
+}
+
+#[test]
+fn test_format_detection_without_extension() {
+    let path = Path::new("dynamic");
+    assert_eq!(
+        DocumentFormat::detect(path, TOML_CONFIG),
+        DocumentFormat::Toml
+    );
+    assert_eq!(
+        DocumentFormat::detect(path, YAML_CONFIG),
+        DocumentFormat::Yaml
+    );
+    // ` = ` in a YAML rule or comment doesn't make it TOML
+    let yaml = "http:\n  routers:\n    # a = b\n    office:\n      rule: \"Path(`/a = b`)\"\n";
+    assert_eq!(DocumentFormat::detect(path, yaml), DocumentFormat::Yaml);
+}
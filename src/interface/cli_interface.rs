@@ -1,11 +1,22 @@
 use std::path::PathBuf;
+use std::time::Instant;
 use tokio::runtime::Runtime;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, Signal, SignalKind};
 
-use crate::application::{AppConfig, DdnsApplication, MultiConfigResult};
+use crate::application::{
+    AppConfig, CheckSchedule, DdnsApplication, HostConfig, HostJob, MultiConfigResult, Scheduler,
+};
+use crate::cli::{
+    BackupsCommand, Command, DaemonArgs, EntryCommand, HistoryArgs, HistoryFormat, StorageCommand,
+};
+use crate::domain::entities::{EntryMetadata, IpEntry, PublicIpBackend, StorageBackend};
 use crate::domain::services::UpdateResult;
+use crate::interface::diff::unified_diff;
+use crate::interface::history::history_table;
 
 /// CLI interface for the DDNS updater using clean architecture
 pub struct CliInterface;
@@ -93,6 +104,108 @@ impl CliInterface {
             std::process::exit(1);
         };
 
+        // Hosts to process, from --config-file or --host
+        let hosts = match args.host_configs().await {
+            Ok(hosts) => hosts,
+            // The history, entries and storage maintenance only touch the storage, so they don't need
+            // any config files
+            Err(_)
+                if matches!(
+                    args.command,
+                    Some(Command::History(_) | Command::Storage(_) | Command::Entry(_))
+                ) =>
+            {
+                Vec::new()
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        // Create application instance
+        let app = DdnsApplication::new(Self::app_config(&args, storage_dir.clone(), hosts))?;
+
+        if let Some(Command::Backups(backups)) = &args.command {
+            return Self::run_backups(&app, &backups.action, args.dry_run).await;
+        }
+        if let Some(Command::History(history)) = &args.command {
+            return Self::run_history(&app, history, args.storage_backend()).await;
+        }
+        if let Some(Command::Storage(storage)) = &args.command {
+            return Self::run_storage(&app, &storage.action).await;
+        }
+        if let Some(Command::Entry(entry)) = &args.command {
+            return Self::run_entry(&app, &entry.action).await;
+        }
+
+        // Initialize DNS host files on first startup (if they don't exist yet); a dry run stores
+        // nothing
+        for host in app.hosts().iter().filter(|_| !args.dry_run) {
+            if let Err(e) = Self::initialize_dns_host_file(&app, &host.hostname, args.verbose).await
+            {
+                if args.verbose {
+                    eprintln!("Warning: Failed to initialize DNS host file: {}", e);
+                }
+                // Don't exit on initialization failure - just continue
+            }
+        }
+
+        if args.verbose {
+            println!("DDNS Updater - Multi-Server Allow List Manager (verbose mode)");
+            for host in app.hosts() {
+                println!("Host: {}", host.hostname);
+                if host.config_paths.len() == 1 {
+                    println!("Using configuration: {}", host.config_paths[0].display());
+                } else {
+                    println!("Processing {} configuration files", host.config_paths.len());
+                }
+            }
+            println!("Address source: {}", args.address_source());
+            println!("Storage: {}", args.storage_backend());
+        } else {
+            println!("DDNS Updater - Multi-Server Allow List Manager");
+        }
+
+        if args.dry_run {
+            if args.command.is_some() {
+                eprintln!("Error: --dry-run can't be used with the daemon");
+                std::process::exit(1);
+            }
+            return Self::run_dry_run(&app).await;
+        }
+
+        if let Some(Command::Daemon(daemon)) = args.command.clone() {
+            return Self::run_daemon(app, args, daemon, storage_dir).await;
+        }
+
+        // Execute DDNS update for all hosts and their configurations
+        let mut has_errors = false;
+        for host in app.hosts() {
+            if app.hosts().len() > 1 {
+                println!("\n🌐 {}", host.hostname);
+            }
+            let multi_result = app.update_host(host).await?;
+
+            // Display results
+            Self::display_results(&host.hostname, &multi_result, args.verbose).await;
+            has_errors |= multi_result.has_errors();
+        }
+
+        // Exit with error code if there were configuration errors
+        if has_errors {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    /// Application configuration from the arguments, for the given hosts
+    fn app_config(
+        args: &crate::cli::Args,
+        storage_dir: PathBuf,
+        hosts: Vec<HostConfig>,
+    ) -> AppConfig {
         // Determine backup directory
         let backup_dir = if let Some(dir) = args.backup_dir.as_ref() {
             Some(dir.clone())
@@ -103,62 +216,408 @@ impl CliInterface {
             None // Use default behavior (same directory as config)
         };
 
-        let app_config = AppConfig::new()
+        AppConfig::new()
             .with_verbose(args.verbose)
             .with_storage_dir(storage_dir)
+            .with_storage_backend(args.storage_backend())
             .with_backup_dir(backup_dir)
-            .with_no_reload(args.no_reload);
-
-        // Create application instance
-        let app = DdnsApplication::new(app_config)?;
+            .with_no_reload(args.no_reload)
+            .with_prefix_rewrite(args.rewrite_prefix)
+            .with_resolver(args.resolver_config())
+            .with_public_ip(args.public_ip_config())
+            .with_address_source(args.address_source())
+            .with_public_ip_backend(args.public_ip_backend())
+            .with_backup_retention(args.backup_retention_days, args.max_backups)
+            .with_hosts(hosts)
+    }
 
-        // Initialize DNS host file on first startup (if it doesn't exist yet)
-        if let Err(e) = Self::initialize_dns_host_file(&app, &args).await {
-            if args.verbose {
-                eprintln!("Warning: Failed to initialize DNS host file: {}", e);
+    /// List, compare or restore backups of the hosts' config files
+    async fn run_backups(
+        app: &DdnsApplication,
+        action: &BackupsCommand,
+        dry_run: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match action {
+            BackupsCommand::List => {
+                for host in app.hosts() {
+                    for config in app.config_backups(host).await? {
+                        println!("📄 {} ({})", config.config_path.display(), host.hostname);
+                        if config.backups.is_empty() {
+                            println!("   No backups");
+                        }
+                        for backup in config.backups.iter().rev() {
+                            let change = if backup.change.is_empty() {
+                                "no address change".to_string()
+                            } else {
+                                backup.change.to_string()
+                            };
+                            println!(
+                                "   {}  {}  {}",
+                                backup.file.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                                backup.file.path.display(),
+                                change
+                            );
+                        }
+                    }
+                }
+            }
+            BackupsCommand::Diff { backup } => {
+                let change = app.backup_diff(backup).await?;
+                let diff = unified_diff(
+                    &change.path.display().to_string(),
+                    &change.original,
+                    &change.updated,
+                );
+                if diff.is_empty() {
+                    println!("ℹ️  {} matches {}", backup.display(), change.path.display());
+                } else {
+                    print!("{}", diff);
+                }
+            }
+            BackupsCommand::Restore { backup } => {
+                let (config_path, previous) = app.restore_backup(backup).await?;
+                println!(
+                    "✅ Restored {} from {}",
+                    config_path.display(),
+                    backup.display()
+                );
+                println!("   Previous version saved as {}", previous.display());
+            }
+            BackupsCommand::Prune => {
+                let verb = if dry_run { "Would remove" } else { "Removed" };
+                let mut count = 0;
+                for host in app.hosts() {
+                    for (config_path, pruned) in app.prune_backups(host, dry_run).await? {
+                        for backup in pruned {
+                            println!(
+                                "🗑️  {} {} ({})",
+                                verb,
+                                backup.path.display(),
+                                config_path.display()
+                            );
+                            count += 1;
+                        }
+                    }
+                }
+                println!("{} {} backup(s)", verb, count);
             }
-            // Don't exit on initialization failure - just continue
         }
+        Ok(())
+    }
 
-        if args.verbose {
-            println!("DDNS Updater - Multi-Server Allow List Manager (verbose mode)");
-            println!("Host: {}", args.host);
-        } else {
-            println!("DDNS Updater - Multi-Server Allow List Manager");
+    /// Print the recorded address changes, oldest first
+    async fn run_history(
+        app: &DdnsApplication,
+        history: &HistoryArgs,
+        storage: StorageBackend,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let since = history
+            .since
+            .map(|age| chrono::Duration::from_std(age).map(|age| chrono::Utc::now() - age))
+            .transpose()
+            .map_err(|_| "--since is too far back")?;
+        let changes = app.history(history.host.as_deref(), since).await?;
+        if changes.is_empty() && storage == StorageBackend::Json {
+            eprintln!("Note: address changes are only recorded with --storage sqlite");
         }
 
-        // Get configuration paths
-        let config_paths = match args.get_nginx_config_paths() {
-            Ok(paths) => {
-                if args.verbose {
-                    if paths.len() == 1 {
-                        println!("Using configuration: {}", paths[0].display());
-                    } else {
-                        println!("Processing {} configuration files", paths.len());
-                    }
+        match history.format {
+            HistoryFormat::Table => print!("{}", history_table(&changes)),
+            HistoryFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+        }
+        Ok(())
+    }
+
+    /// Show stored entries or change the comment, owner or tags of one
+    async fn run_entry(
+        app: &DdnsApplication,
+        action: &EntryCommand,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match action {
+            EntryCommand::Show { hostname } => {
+                let entries = app.entries(hostname.as_deref()).await?;
+                if let (Some(hostname), true) = (hostname, entries.is_empty()) {
+                    return Err(format!("No stored entry for {}", hostname).into());
+                }
+                for entry in &entries {
+                    Self::display_entry(entry);
                 }
-                paths
             }
-            Err(e) => {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
+            EntryCommand::Set {
+                hostname,
+                comment,
+                owner,
+                tags,
+            } => {
+                let metadata = EntryMetadata {
+                    comment: comment.clone(),
+                    owner: owner.clone(),
+                    tags: tags.clone(),
+                };
+                if metadata.is_empty() {
+                    return Err("Nothing to set; pass --comment, --owner or --tags".into());
+                }
+                let entry = app.update_entry_metadata(hostname, &metadata).await?;
+                println!("✅ Updated the stored entry of {}", hostname);
+                Self::display_entry(&entry);
             }
-        };
-
-        // Execute DDNS update for all configurations
-        let multi_result = app.update_ddns_multiple(&args.host, config_paths).await?;
+        }
+        Ok(())
+    }
 
-        // Display results
-        Self::display_results(&args.host, &multi_result, args.verbose).await;
+    fn display_entry(entry: &IpEntry) {
+        let addresses: Vec<String> = entry.addresses().iter().map(|ip| ip.to_string()).collect();
+        println!("🌐 {}", entry.hostname);
+        println!("   Addresses: {}", addresses.join(", "));
+        if let Some(comment) = &entry.comment {
+            println!("   Comment:   {}", comment);
+        }
+        if let Some(owner) = &entry.owner {
+            println!("   Owner:     {}", owner);
+        }
+        if !entry.tags.is_empty() {
+            println!("   Tags:      {}", entry.tags.join(", "));
+        }
+        println!(
+            "   Created:   {}",
+            entry.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        println!(
+            "   Updated:   {}",
+            entry.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
 
-        // Exit with error code if there were configuration errors
-        if multi_result.has_errors() {
+    /// Upgrade the stored entries written in an older layout. With --check only list them,
+    /// exiting with 1 if there are any.
+    async fn run_storage(
+        app: &DdnsApplication,
+        action: &StorageCommand,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let StorageCommand::Migrate { check, from } = action;
+        if *check {
+            let pending = app.pending_migrations(from.as_deref()).await?;
+            for migration in &pending {
+                println!(
+                    "⬆️  {}: {} (version {} → {})",
+                    migration.hostname,
+                    migration.location,
+                    migration.from_version,
+                    migration.to_version
+                );
+            }
+            if pending.is_empty() {
+                println!("✅ All stored entries are up to date");
+                return Ok(());
+            }
+            println!("{} stored entry(ies) need migrating", pending.len());
             std::process::exit(1);
         }
 
+        let migrated = app.migrate_storage(from.as_deref()).await?;
+        for migration in &migrated {
+            println!(
+                "⬆️  Migrated {}: {} (version {} → {})",
+                migration.hostname,
+                migration.location,
+                migration.from_version,
+                migration.to_version
+            );
+        }
+        println!("Migrated {} stored entry(ies)", migrated.len());
+        Ok(())
+    }
+
+    /// Print what an update of every host would change as unified diffs, without changing
+    /// anything. Exits with 2 when changes would be made and 1 on errors.
+    async fn run_dry_run(
+        app: &DdnsApplication,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut has_errors = false;
+        let mut has_changes = false;
+        for host in app.hosts() {
+            if app.hosts().len() > 1 {
+                println!("\n🌐 {}", host.hostname);
+            }
+            let result = match app.preview_host(host).await {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("❌ Error checking {}: {}", host.hostname, e);
+                    has_errors = true;
+                    continue;
+                }
+            };
+
+            for (config_path, error) in &result.errors {
+                println!("❌ Error in config: {}: {}", config_path.display(), error);
+            }
+            if result.diff.is_empty() {
+                println!("ℹ️  No IP change detected for {}", host.hostname);
+            } else if result.changes.is_empty() {
+                println!(
+                    "ℹ️  IP change detected for {} ({}), but no config file would change",
+                    host.hostname, result.diff
+                );
+            } else {
+                println!("🔍 Would update {}: {}", host.hostname, result.diff);
+                for change in &result.changes {
+                    print!(
+                        "{}",
+                        unified_diff(
+                            &change.path.display().to_string(),
+                            &change.original,
+                            &change.updated
+                        )
+                    );
+                }
+            }
+            has_errors |= !result.errors.is_empty();
+            has_changes |= !result.changes.is_empty();
+        }
+
+        if has_errors {
+            std::process::exit(1);
+        }
+        if has_changes {
+            std::process::exit(2);
+        }
         Ok(())
     }
 
+    /// Keep checking the hosts on their schedules until SIGTERM or Ctrl-C.
+    ///
+    /// The application, and with it the HTTP clients and resolver, is reused for every check.
+    /// SIGHUP reloads the whole configuration: the arguments are parsed again, re-reading
+    /// --config-file and the files of --config-dir, and the application is rebuilt from them
+    /// before checking every host right away. If the new configuration is invalid, the daemon
+    /// keeps running with the current one.
+    async fn run_daemon(
+        mut app: DdnsApplication,
+        mut args: crate::cli::Args,
+        mut daemon: DaemonArgs,
+        storage_dir: PathBuf,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut signals = DaemonSignals::new()?;
+        let mut address_changes = InterfaceWatch::watch(&args.public_ip_backend());
+        let mut scheduler = Scheduler::new(Self::daemon_jobs(&app, &daemon), Instant::now());
+        for host in app.hosts() {
+            println!(
+                "🕒 Running as daemon: checking {} every {}s",
+                host.hostname,
+                host.interval.unwrap_or(daemon.interval).as_secs()
+            );
+        }
+
+        loop {
+            for job in scheduler.due(Instant::now()) {
+                let hostname = &job.host.hostname;
+                let success = match app.update_host(&job.host).await {
+                    Ok(multi_result) => {
+                        Self::display_results(hostname, &multi_result, args.verbose).await;
+                        !multi_result.has_errors()
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Check of {} failed: {}", hostname, e);
+                        false
+                    }
+                };
+                if let Some(delay) = scheduler.record(hostname, success, Instant::now()) {
+                    if !success {
+                        println!(
+                            "⏳ Retrying {} in {}s ({} failed checks in a row)",
+                            hostname,
+                            delay.as_secs(),
+                            scheduler.failures(hostname)
+                        );
+                    } else if args.verbose {
+                        println!("   Next check of {} in {}s", hostname, delay.as_secs());
+                    }
+                }
+            }
+
+            let Some(next_due) = scheduler.next_due() else {
+                return Err("No hosts to check".into());
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(next_due.into()) => {}
+                _ = address_changes.recv() => {
+                    println!("🔌 Address change on the interface, checking now");
+                    scheduler.wake_all(Instant::now());
+                }
+                received = signals.recv() => match received {
+                    DaemonSignal::Terminate => {
+                        println!("🛑 Stopping daemon");
+                        return Ok(());
+                    }
+                    DaemonSignal::Reload => {
+                        println!("🔄 Reloading the configuration");
+                        match Self::reload_daemon(storage_dir.clone()).await {
+                            Ok((new_args, new_daemon, new_app)) => {
+                                (args, daemon, app) = (new_args, new_daemon, new_app);
+                                address_changes = InterfaceWatch::watch(&args.public_ip_backend());
+                                for host in app.hosts() {
+                                    if let Err(e) = Self::initialize_dns_host_file(
+                                        &app,
+                                        &host.hostname,
+                                        args.verbose,
+                                    )
+                                    .await
+                                    {
+                                        if args.verbose {
+                                            eprintln!(
+                                                "Warning: Failed to initialize DNS host file: {}",
+                                                e
+                                            );
+                                        }
+                                    }
+                                }
+                                scheduler =
+                                    Scheduler::new(Self::daemon_jobs(&app, &daemon), Instant::now());
+                            }
+                            Err(e) => {
+                                eprintln!("❌ Failed to reload, keeping the current configuration: {}", e);
+                            }
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Parse the arguments and config file again and build a new application from them, for
+    /// SIGHUP. The storage directory doesn't come from the arguments, so it is kept.
+    async fn reload_daemon(
+        storage_dir: PathBuf,
+    ) -> Result<
+        (crate::cli::Args, DaemonArgs, DdnsApplication),
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        let args = crate::cli::Args::reparse_args()?;
+        let Some(Command::Daemon(daemon)) = args.command.clone() else {
+            return Err("the arguments no longer run the daemon".into());
+        };
+        let hosts = args.host_configs().await?;
+        let app = DdnsApplication::new(Self::app_config(&args, storage_dir, hosts))?;
+        Ok((args, daemon, app))
+    }
+
+    /// Hosts checked by the daemon, on their own interval or --interval
+    fn daemon_jobs(app: &DdnsApplication, daemon: &DaemonArgs) -> Vec<HostJob> {
+        app.hosts()
+            .iter()
+            .map(|host| HostJob {
+                host: host.clone(),
+                schedule: match host.interval {
+                    Some(interval) => CheckSchedule {
+                        interval,
+                        ..daemon.schedule()
+                    },
+                    None => daemon.schedule(),
+                },
+            })
+            .collect()
+    }
+
     /// Display the results of DDNS updates
     async fn display_results(hostname: &str, multi_result: &MultiConfigResult, verbose: bool) {
         // Display errors first
@@ -173,32 +632,35 @@ impl CliInterface {
 
         let mut updated_count = 0;
         let mut no_change_count = 0;
+        let mut rolled_back_count = 0;
 
         for result in &multi_result.successes {
             match result {
                 UpdateResult::Updated {
-                    old_ip,
-                    new_ip,
-                    backup_path,
-                    ..
+                    diff, backup_path, ..
                 } => {
                     updated_count += 1;
-                    match old_ip {
-                        Some(old) => {
-                            println!("✅ Updated {}: {} → {}", hostname, old, new_ip);
-                        }
-                        None => {
-                            println!("✅ Added {}: {}", hostname, new_ip);
-                        }
-                    }
+                    println!("✅ Updated {}: {}", hostname, diff);
                     if verbose {
                         println!("   Backup created: {}", backup_path.display());
                     }
                 }
-                UpdateResult::NoChange { ip } => {
+                UpdateResult::NoChange { ips } => {
                     no_change_count += 1;
                     if verbose {
-                        println!("ℹ️  No change needed for {}: {}", hostname, ip);
+                        let ips: Vec<String> = ips.iter().map(|ip| ip.to_string()).collect();
+                        println!("ℹ️  No change needed for {}: {}", hostname, ips.join(", "));
+                    }
+                }
+                UpdateResult::RolledBack {
+                    reason,
+                    backup_path,
+                    ..
+                } => {
+                    rolled_back_count += 1;
+                    println!("↩️  Rolled back {}: {}", hostname, reason);
+                    if verbose {
+                        println!("   Restored from: {}", backup_path.display());
                     }
                 }
             }
@@ -210,6 +672,9 @@ impl CliInterface {
             println!("\n📊 Summary:");
             println!("   Updated: {}", updated_count);
             println!("   No change: {}", no_change_count);
+            if rolled_back_count > 0 {
+                println!("   Rolled back: {}", rolled_back_count);
+            }
             println!("   Errors: {}", multi_result.errors.len());
             println!("   Total processed: {}", multi_result.total_processed());
 
@@ -223,20 +688,21 @@ impl CliInterface {
     /// This resolves the hostname and creates a JSON file with the actual IP
     async fn initialize_dns_host_file(
         app: &DdnsApplication,
-        args: &crate::cli::Args,
+        hostname: &str,
+        verbose: bool,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Initialize in both production and test mode (test mode uses ./test_storage/)
 
         // Get the IP repository from the application to call the initialization method
-        match app.initialize_host_file(&args.host).await {
+        match app.initialize_host_file(hostname).await {
             Ok(was_created) => {
-                if was_created && args.verbose {
+                if was_created && verbose {
                     let location = if std::env::var("DDNS_TEST_MODE").is_ok() {
-                        format!("./test_storage/{}.json", args.host)
+                        format!("./test_storage/{}.json", hostname)
                     } else {
-                        format!("/var/lib/ddns-updater/{}.json", args.host)
+                        format!("/var/lib/ddns-updater/{}.json", hostname)
                     };
-                    println!("🆕 Created initial DNS host file for: {}", args.host);
+                    println!("🆕 Created initial DNS host file for: {}", hostname);
                     println!("   Location: {}", location);
                     println!("   Initialized with DNS data.");
                 }
@@ -249,3 +715,99 @@ impl CliInterface {
         Ok(())
     }
 }
+
+/// Signals the daemon reacts to
+enum DaemonSignal {
+    /// SIGTERM or Ctrl-C: stop after the running check
+    Terminate,
+    /// SIGHUP: reload the configuration
+    Reload,
+}
+
+/// Address change notifications of the --interface address source, which wake the daemon
+/// right away instead of at the next interval. Other sources, and other platforms than Linux,
+/// never notify.
+struct InterfaceWatch {
+    receiver: Option<tokio::sync::mpsc::UnboundedReceiver<()>>,
+}
+
+impl InterfaceWatch {
+    /// Wait this long after a change before checking, as an interface coming up reports
+    /// several address events at once
+    const SETTLE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+    fn watch(backend: &PublicIpBackend) -> Self {
+        #[cfg(target_os = "linux")]
+        if let PublicIpBackend::Interface { name } = backend {
+            match crate::infrastructure::InterfaceNetworkService::new(name.clone())
+                .watch_address_changes()
+            {
+                Ok(receiver) => {
+                    println!("👀 Watching {} for address changes", name);
+                    return Self {
+                        receiver: Some(receiver),
+                    };
+                }
+                Err(e) => eprintln!(
+                    "Warning: Cannot watch {} for address changes, only checking on the interval: {}",
+                    name, e
+                ),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = backend;
+        Self { receiver: None }
+    }
+
+    /// Wait for the next change, then for the burst of events around it to settle
+    async fn recv(&mut self) {
+        let Some(receiver) = self.receiver.as_mut() else {
+            return std::future::pending().await;
+        };
+        if receiver.recv().await.is_none() {
+            eprintln!("Warning: Lost the interface address watch, only checking on the interval");
+            self.receiver = None;
+            return std::future::pending().await;
+        }
+        tokio::time::sleep(Self::SETTLE_DELAY).await;
+        while receiver.try_recv().is_ok() {}
+    }
+}
+
+/// Listeners for the daemon's signals, registered once so none is missed during a check
+struct DaemonSignals {
+    #[cfg(unix)]
+    terminate: Signal,
+    #[cfg(unix)]
+    hangup: Signal,
+}
+
+impl DaemonSignals {
+    #[cfg(unix)]
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {})
+    }
+
+    #[cfg(unix)]
+    async fn recv(&mut self) -> DaemonSignal {
+        tokio::select! {
+            _ = self.terminate.recv() => DaemonSignal::Terminate,
+            _ = self.hangup.recv() => DaemonSignal::Reload,
+            _ = tokio::signal::ctrl_c() => DaemonSignal::Terminate,
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) -> DaemonSignal {
+        let _ = tokio::signal::ctrl_c().await;
+        DaemonSignal::Terminate
+    }
+}
@@ -0,0 +1,117 @@
+/// Lines of context shown around every change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Unified diff of two versions of a file, as printed by `diff -u`; empty if they are equal
+pub fn unified_diff(path: &str, original: &str, updated: &str) -> String {
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = updated.lines().collect();
+    let ops = diff_lines(&old, &new);
+    if ops.iter().all(|(op, _, _)| *op == Op::Equal) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", path, path);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _, _))| *op != Op::Equal)
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut index = 0;
+    while index < changes.len() {
+        // Changes closer than twice the context share a hunk
+        let start = changes[index].saturating_sub(CONTEXT);
+        let mut last = changes[index];
+        while index + 1 < changes.len() && changes[index + 1] <= last + 2 * CONTEXT + 1 {
+            index += 1;
+            last = changes[index];
+        }
+        let end = (last + CONTEXT + 1).min(ops.len());
+        index += 1;
+
+        let hunk = &ops[start..end];
+        let old_count = hunk.iter().filter(|(op, _, _)| *op != Op::Insert).count();
+        let new_count = hunk.iter().filter(|(op, _, _)| *op != Op::Delete).count();
+        let (old_start, new_start) = (hunk[0].1, hunk[0].2);
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for &(op, old_index, new_index) in hunk {
+            match op {
+                Op::Equal => output.push_str(&format!(" {}\n", old[old_index])),
+                Op::Delete => output.push_str(&format!("-{}\n", old[old_index])),
+                Op::Insert => output.push_str(&format!("+{}\n", new[new_index])),
+            }
+        }
+    }
+    output
+}
+
+/// `start,count` of a hunk, with the 1-based start; an empty range starts at the line before
+fn hunk_range(start: usize, count: usize) -> String {
+    let start = if count == 0 { start } else { start + 1 };
+    if count == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, count)
+    }
+}
+
+/// Edit script turning `old` into `new`, each step with the old and new line index it is at.
+/// The common start and end are matched first so the quadratic part only covers the changes.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<(Op, usize, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // Longest common subsequence lengths of the suffixes of both middles
+    let mut lcs = vec![vec![0usize; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(Op, usize, usize)> = (0..prefix).map(|i| (Op::Equal, i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            ops.push((Op::Equal, prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if i < old_mid.len() && (j == new_mid.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Delete, prefix + i, prefix + j));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, prefix + i, prefix + j));
+            j += 1;
+        }
+    }
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    ops.extend((0..suffix).map(|k| (Op::Equal, old_end + k, new_end + k)));
+    ops
+}
+
+#[cfg(test)]
+#[path = "diff_tests.rs"]
+mod tests;
@@ -0,0 +1,34 @@
+use super::*;
+
+#[test]
+fn test_equal_files_have_no_diff() {
+    assert_eq!(unified_diff("site.conf", "a\nb\n", "a\nb\n"), "");
+}
+
+#[test]
+fn test_changed_line_with_context() {
+    let original = "server {\n    listen 80;\n    allow 203.0.113.7;\n    deny all;\n}\n";
+    let updated = "server {\n    listen 80;\n    allow 203.0.113.8;\n    deny all;\n}\n";
+    assert_eq!(
+        unified_diff("/etc/nginx/site.conf", original, updated),
+        "--- /etc/nginx/site.conf\n+++ /etc/nginx/site.conf\n@@ -1,5 +1,5 @@\n server {\n     listen 80;\n-    allow 203.0.113.7;\n+    allow 203.0.113.8;\n     deny all;\n }\n"
+    );
+}
+
+#[test]
+fn test_distant_changes_get_their_own_hunks() {
+    let original: Vec<String> = (1..=20).map(|n| format!("line {}", n)).collect();
+    let mut updated = original.clone();
+    updated[1] = "changed 2".to_string();
+    updated.insert(18, "added".to_string());
+    let diff = unified_diff(
+        "f",
+        &(original.join("\n") + "\n"),
+        &(updated.join("\n") + "\n"),
+    );
+
+    let headers: Vec<&str> = diff.lines().filter(|line| line.starts_with("@@")).collect();
+    assert_eq!(headers, vec!["@@ -1,5 +1,5 @@", "@@ -16,5 +16,6 @@"]);
+    assert!(diff.contains("-line 2\n+changed 2\n"));
+    assert!(diff.contains(" line 18\n+added\n line 19\n"));
+}
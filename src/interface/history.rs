@@ -0,0 +1,80 @@
+use crate::domain::entities::{ChangeOutcome, IpChange};
+use std::net::IpAddr;
+
+/// Address changes as a table, one line per change with its edited files and any error below
+pub fn history_table(changes: &[IpChange]) -> String {
+    let rows: Vec<[String; 5]> = changes
+        .iter()
+        .map(|change| {
+            [
+                change.changed_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                change.hostname.clone(),
+                format!(
+                    "{} → {}",
+                    format_ips(&change.old_ips),
+                    format_ips(&change.new_ips)
+                ),
+                match change.outcome {
+                    ChangeOutcome::Applied => "applied".to_string(),
+                    ChangeOutcome::RolledBack => "rolled back".to_string(),
+                },
+                if change.reloaded { "yes" } else { "no" }.to_string(),
+            ]
+        })
+        .collect();
+
+    let header = ["CHANGED (UTC)", "HOST", "CHANGE", "OUTCOME", "RELOADED"].map(String::from);
+    let mut widths = header.clone().map(|title| title.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = table_line(&header, &widths);
+    for (change, row) in changes.iter().zip(&rows) {
+        output.push_str(&table_line(row, &widths));
+        for file in &change.files {
+            match &file.backup_path {
+                Some(backup) => output.push_str(&format!(
+                    "    {} (backup: {})\n",
+                    file.path.display(),
+                    backup.display()
+                )),
+                None => output.push_str(&format!("    {}\n", file.path.display())),
+            }
+        }
+        if let Some(error) = &change.error {
+            output.push_str(&format!("    error: {}\n", error));
+        }
+    }
+    output
+}
+
+/// Cells padded to their column widths; the last column isn't padded
+fn table_line(cells: &[String; 5], widths: &[usize; 5]) -> String {
+    let mut line = String::new();
+    for (index, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if index + 1 == cells.len() {
+            line.push_str(cell);
+        } else {
+            line.push_str(&format!("{:<width$}  ", cell, width = width));
+        }
+    }
+    line.push('\n');
+    line
+}
+
+fn format_ips(ips: &[IpAddr]) -> String {
+    if ips.is_empty() {
+        return "-".to_string();
+    }
+    ips.iter()
+        .map(|ip| ip.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+#[path = "history_tests.rs"]
+mod tests;
@@ -0,0 +1,52 @@
+use super::*;
+use crate::domain::entities::ChangedFile;
+use std::path::PathBuf;
+
+#[test]
+fn test_history_table_lists_files_and_errors() {
+    let applied = IpChange {
+        hostname: "home.example.com".to_string(),
+        old_ips: vec!["203.0.113.7".parse().unwrap()],
+        new_ips: vec![
+            "203.0.113.8".parse().unwrap(),
+            "2001:db8::8".parse().unwrap(),
+        ],
+        changed_at: "2026-01-02T03:04:05Z".parse().unwrap(),
+        files: vec![ChangedFile {
+            path: PathBuf::from("/etc/nginx/site.conf"),
+            backup_path: Some(PathBuf::from("/etc/nginx/site.conf.bak.20260102_030405")),
+        }],
+        outcome: ChangeOutcome::Applied,
+        reloaded: true,
+        error: None,
+    };
+    let rolled_back = IpChange {
+        hostname: "office.example.com".to_string(),
+        old_ips: Vec::new(),
+        new_ips: vec!["198.51.100.1".parse().unwrap()],
+        changed_at: "2026-01-03T00:00:00Z".parse().unwrap(),
+        files: Vec::new(),
+        outcome: ChangeOutcome::RolledBack,
+        reloaded: false,
+        error: Some("nginx reload failed: exit status 1".to_string()),
+    };
+
+    assert_eq!(
+        history_table(&[applied, rolled_back]),
+        "\
+CHANGED (UTC)        HOST                CHANGE                                  OUTCOME      RELOADED
+2026-01-02 03:04:05  home.example.com    203.0.113.7 → 203.0.113.8, 2001:db8::8  applied      yes
+    /etc/nginx/site.conf (backup: /etc/nginx/site.conf.bak.20260102_030405)
+2026-01-03 00:00:00  office.example.com  - → 198.51.100.1                        rolled back  no
+    error: nginx reload failed: exit status 1
+"
+    );
+}
+
+#[test]
+fn test_empty_history_has_only_the_header() {
+    assert_eq!(
+        history_table(&[]),
+        "CHANGED (UTC)  HOST  CHANGE  OUTCOME  RELOADED\n"
+    );
+}
@@ -1,3 +1,7 @@
 pub mod cli_interface;
+pub mod diff;
+pub mod history;
 
 pub use cli_interface::*;
+pub use diff::*;
+pub use history::*;
@@ -26,7 +26,7 @@ if [[ "$1" == "--help" || "$1" == "-h" ]]; then
     echo "  Config Dir:     /etc/nginx/sites-available"
     echo "  Pattern:        *.conf"
     echo "  Backup Dir:     /var/backups/nginx"
-    echo "  Backup Cleanup: Enabled (3 days retention)"
+    echo "  Backup Retention: 30 days"
     echo "  Interval:       5 minutes"
     echo "  Verbose:        Yes"
     echo "  Auto Reload:    Yes"
@@ -102,8 +102,7 @@ else
     CONFIG_DIR="/etc/nginx/sites-available"
     PATTERN="*.conf"
     BACKUP_DIR="/var/backups/nginx"
-    CLEANUP_ENABLED=true
-    CLEANUP_DAYS=30
+    RETENTION_DAYS=30
     INTERVAL="5min"
     VERBOSE_FLAG="--verbose"
     RELOAD_FLAG=""
@@ -142,35 +141,28 @@ fi
 read -p "Enter backup directory [/var/backups/nginx]: " BACKUP_DIR
 BACKUP_DIR=${BACKUP_DIR:-/var/backups/nginx}
 
-# Ask for backup cleanup settings
+# Ask how long backups are kept; the updater prunes older ones after every edit
 echo ""
-echo "Backup Cleanup Configuration:"
-read -p "Enable automatic cleanup of old backup files? [Y/n]: " ENABLE_CLEANUP
-if [[ "${ENABLE_CLEANUP,,}" =~ ^(n|no)$ ]]; then
-    CLEANUP_ENABLED=false
-    CLEANUP_DAYS=3
-else
-    CLEANUP_ENABLED=true
-    echo "  1) Keep backups for 1 day"
-    echo "  2) Keep backups for 3 days (recommended)"
-    echo "  3) Keep backups for 7 days"
-    echo "  4) Keep backups for 14 days"
-    echo "  5) Custom retention period"
-    read -p "Select retention period (1-5) [2]: " CLEANUP_CHOICE
-    CLEANUP_CHOICE=${CLEANUP_CHOICE:-2}
-    
-    case $CLEANUP_CHOICE in
-        1) CLEANUP_DAYS=1 ;;
-        2) CLEANUP_DAYS=3 ;;
-        3) CLEANUP_DAYS=7 ;;
-        4) CLEANUP_DAYS=14 ;;
-        5) 
-            read -p "Enter custom retention days: " CUSTOM_DAYS
-            CLEANUP_DAYS=${CUSTOM_DAYS:-3}
-            ;;
-        *) CLEANUP_DAYS=3 ;;
-    esac
-fi
+echo "Backup Retention:"
+echo "  1) Keep backups for 3 days"
+echo "  2) Keep backups for 7 days"
+echo "  3) Keep backups for 30 days (recommended)"
+echo "  4) Keep backups for 90 days"
+echo "  5) Custom retention period"
+read -p "Select retention period (1-5) [3]: " RETENTION_CHOICE
+RETENTION_CHOICE=${RETENTION_CHOICE:-3}
+
+case $RETENTION_CHOICE in
+    1) RETENTION_DAYS=3 ;;
+    2) RETENTION_DAYS=7 ;;
+    3) RETENTION_DAYS=30 ;;
+    4) RETENTION_DAYS=90 ;;
+    5)
+        read -p "Enter custom retention days: " CUSTOM_DAYS
+        RETENTION_DAYS=${CUSTOM_DAYS:-30}
+        ;;
+    *) RETENTION_DAYS=30 ;;
+esac
 
 # Ask for update interval
 echo ""
@@ -259,14 +251,14 @@ cat > /etc/systemd/system/ddns-updater.service << EOF
 Description=DDNS Updater - Nginx Allow List Manager
 Documentation=https://github.com/koenieee/ddns_local_server
 After=network-online.target
-Wants=network-online.target$([ "$CLEANUP_ENABLED" == "true" ] && echo " ddns-backup-cleanup.service" || echo "")
+Wants=network-online.target
 
 [Service]
 Type=oneshot
 User=root
 Group=root
-ExecStartPre=/bin/sh -c 'echo "DDNS Configuration: Host=\${DDNS_HOST}, Config=\${DDNS_CONFIG_MODE}(\${DDNS_CONFIG_DIR}\${DDNS_CONFIG_FILE}), Pattern=\${DDNS_PATTERN}, Interval=\${DDNS_INTERVAL}, Verbose=\${DDNS_VERBOSE}, Cleanup=\${DDNS_CLEANUP_ENABLED}"'
-ExecStart=$BINARY_PATH --host "$HOST" $CONFIG_ARGS --backup-dir "$BACKUP_DIR" $VERBOSE_FLAG $RELOAD_FLAG
+ExecStartPre=/bin/sh -c 'echo "DDNS Configuration: Host=\${DDNS_HOST}, Config=\${DDNS_CONFIG_MODE}(\${DDNS_CONFIG_DIR}\${DDNS_CONFIG_FILE}), Pattern=\${DDNS_PATTERN}, Interval=\${DDNS_INTERVAL}, Verbose=\${DDNS_VERBOSE}, Retention=\${DDNS_RETENTION_DAYS} days"'
+ExecStart=$BINARY_PATH --host "$HOST" $CONFIG_ARGS --backup-dir "$BACKUP_DIR" --backup-retention-days $RETENTION_DAYS $VERBOSE_FLAG $RELOAD_FLAG
 Environment=PATH=/usr/local/bin:/usr/bin:/bin
 Environment=DDNS_HOST=$HOST
 Environment=DDNS_CONFIG_MODE=$CONFIG_MODE
@@ -276,8 +268,7 @@ Environment=DDNS_BACKUP_DIR=$BACKUP_DIR
 Environment=DDNS_INTERVAL=$INTERVAL
 Environment=DDNS_VERBOSE=$([ -n "$VERBOSE_FLAG" ] && echo "true" || echo "false")
 Environment=DDNS_AUTO_RELOAD=$([ -z "$RELOAD_FLAG" ] && echo "true" || echo "false")
-Environment=DDNS_CLEANUP_ENABLED=$CLEANUP_ENABLED
-$([ "$CLEANUP_ENABLED" == "true" ] && echo "Environment=DDNS_CLEANUP_DAYS=$CLEANUP_DAYS" || echo "")
+Environment=DDNS_RETENTION_DAYS=$RETENTION_DAYS
 WorkingDirectory=$(dirname "$BINARY_PATH")
 StandardOutput=journal
 StandardError=journal
@@ -339,99 +330,14 @@ EOF
 chmod 644 /etc/systemd/system/ddns-updater.service
 chmod 644 /etc/systemd/system/ddns-updater.timer
 
-# Install backup cleanup system
-if [[ "$CLEANUP_ENABLED" == "true" ]]; then
-    print_status "Installing backup cleanup system..."
-    
-    # Install cleanup script if in development mode
-    if [ "$DEVELOPMENT_MODE" = true ]; then
-        # Copy cleanup script from local directory
-        cp ddns-backup-cleanup.sh /usr/bin/ddns-backup-cleanup
-        chmod +x /usr/bin/ddns-backup-cleanup
-        CLEANUP_SCRIPT_PATH="/usr/bin/ddns-backup-cleanup"
-    elif [ -f "/usr/bin/ddns-backup-cleanup" ]; then
-        # Use installed cleanup script
-        CLEANUP_SCRIPT_PATH="/usr/bin/ddns-backup-cleanup"
-        print_status "Using installed cleanup script: $CLEANUP_SCRIPT_PATH"
-    else
-        print_error "Cleanup script not found. Please install the package or run from development directory."
-        exit 1
-    fi
-    
-    # Create cleanup service file
-    cat > /etc/systemd/system/ddns-backup-cleanup.service << EOF
-[Unit]
-Description=DDNS Updater - Backup Cleanup Service
-Documentation=https://github.com/koenieee/ddns_local_server
-After=network.target
-PartOf=ddns-updater.service
-Requisite=ddns-updater.service
-
-[Service]
-Type=oneshot
-User=root
-Group=root
-
-# Clean up backup files older than $CLEANUP_DAYS days
-ExecStartPre=/bin/sh -c 'echo "DDNS Cleanup Configuration: Directory=\${DDNS_CLEANUP_BACKUP_DIR}, Retention=\${DDNS_CLEANUP_RETENTION_DAYS} days, Verbose=\${DDNS_CLEANUP_VERBOSE}"'
-ExecStart=/usr/bin/ddns-backup-cleanup --backup-dir "$BACKUP_DIR" --days $CLEANUP_DAYS --verbose
-Environment=DDNS_CLEANUP_BACKUP_DIR=$BACKUP_DIR
-Environment=DDNS_CLEANUP_RETENTION_DAYS=$CLEANUP_DAYS
-Environment=DDNS_CLEANUP_VERBOSE=true
-
-# Security settings
-NoNewPrivileges=true
-ProtectSystem=strict
-ProtectKernelTunables=true
-ProtectKernelModules=true
-ProtectControlGroups=true
-RestrictRealtime=true
-MemoryDenyWriteExecute=true
-LockPersonality=true
-RestrictSUIDSGID=true
-
-# Allow access to backup directories
-ReadWritePaths=$BACKUP_DIR
-
-# Logging
-StandardOutput=journal
-StandardError=journal
-SyslogIdentifier=ddns-backup-cleanup
-EOF
-
-    # Create cleanup timer file
-    cat > /etc/systemd/system/ddns-backup-cleanup.timer << EOF
-[Unit]
-Description=DDNS Updater - Backup Cleanup Timer
-Requires=ddns-backup-cleanup.service
-PartOf=ddns-updater.timer
-After=ddns-updater.timer
-
-[Timer]
-# Run daily at 3:00 AM
-OnCalendar=daily
-Persistent=true
-RandomizedDelaySec=1800
-
-[Install]
-WantedBy=timers.target
-EOF
-
-    # Set permissions for cleanup files
-    chmod 644 /etc/systemd/system/ddns-backup-cleanup.service
-    chmod 644 /etc/systemd/system/ddns-backup-cleanup.timer
-    
-    print_status "Backup cleanup configured to run daily, keeping files for $CLEANUP_DAYS days"
-fi
-
 # Create a target file for grouping all DDNS updater services
 print_status "Creating DDNS updater service group target..."
 cat > /etc/systemd/system/ddns-updater.target << EOF
 [Unit]
 Description=DDNS Updater Service Group
 Documentation=https://github.com/koenieee/ddns_local_server
-Wants=ddns-updater.timer$([ "$CLEANUP_ENABLED" == "true" ] && echo " ddns-backup-cleanup.timer" || echo "")
-After=ddns-updater.timer$([ "$CLEANUP_ENABLED" == "true" ] && echo " ddns-backup-cleanup.timer" || echo "")
+Wants=ddns-updater.timer
+After=ddns-updater.timer
 
 [Install]
 WantedBy=multi-user.target
@@ -449,13 +355,6 @@ systemctl enable ddns-updater.target
 systemctl enable ddns-updater.timer
 systemctl start ddns-updater.timer
 
-# Enable cleanup timer if configured
-if [[ "$CLEANUP_ENABLED" == "true" ]]; then
-    print_status "Enabling backup cleanup timer..."
-    systemctl enable ddns-backup-cleanup.timer
-    systemctl start ddns-backup-cleanup.timer
-fi
-
 print_status "Starting DDNS updater service group..."
 systemctl start ddns-updater.target
 
@@ -470,11 +369,7 @@ echo "  Config Dir:   $CONFIG_DIR"
 echo "  Pattern:      $PATTERN"
 fi
 echo "  Backup Dir:   $BACKUP_DIR"
-if [[ "$CLEANUP_ENABLED" == "true" ]]; then
-echo "  Backup Cleanup: Enabled ($CLEANUP_DAYS days retention)"
-else
-echo "  Backup Cleanup: Disabled"
-fi
+echo "  Backup Retention: $RETENTION_DAYS days"
 echo "  Interval:     $INTERVAL"
 echo "  Verbose:      $([ -n "$VERBOSE_FLAG" ] && echo "Yes" || echo "No")"
 echo "  Auto Reload:  $([ -z "$RELOAD_FLAG" ] && echo "Yes" || echo "No")"
@@ -484,11 +379,6 @@ echo "  Service Group:   /etc/systemd/system/ddns-updater.target"
 echo "  Service File:    /etc/systemd/system/ddns-updater.service"
 echo "  Timer File:      /etc/systemd/system/ddns-updater.timer"
 echo "  Binary:          $BINARY_PATH"
-if [[ "$CLEANUP_ENABLED" == "true" ]]; then
-echo "  Cleanup Service: /etc/systemd/system/ddns-backup-cleanup.service"
-echo "  Cleanup Timer:   /etc/systemd/system/ddns-backup-cleanup.timer"
-echo "  Cleanup Script:  /usr/bin/ddns-backup-cleanup"
-fi
 echo ""
 echo "=== Management Commands ==="
 echo "  Show configuration:    /usr/share/ddns-updater/show-config.sh"
@@ -499,14 +389,7 @@ echo "  Stop service group:    systemctl stop ddns-updater.target"
 echo "  Start service group:   systemctl start ddns-updater.target"
 echo "  Disable service group: systemctl disable ddns-updater.target"
 echo "  Manual run:            systemctl start ddns-updater.service"
-if [[ "$CLEANUP_ENABLED" == "true" ]]; then
-echo ""
-echo "=== Backup Cleanup Commands ==="
-echo "  Check cleanup status:  systemctl status ddns-backup-cleanup.timer"
-echo "  View cleanup logs:     journalctl -u ddns-backup-cleanup.service -f"
-echo "  Manual cleanup:        systemctl start ddns-backup-cleanup.service"
-echo "  Test cleanup (dry-run): /usr/bin/ddns-backup-cleanup --backup-dir $BACKUP_DIR --days $CLEANUP_DAYS --dry-run --verbose"
-fi
+echo "  Prune backups now:     $BINARY_PATH backups prune --host \"$HOST\" $CONFIG_ARGS --backup-dir \"$BACKUP_DIR\" --backup-retention-days $RETENTION_DAYS"
 echo ""
 echo "The service will run every $INTERVAL and check for IP changes on $HOST."
 echo ""
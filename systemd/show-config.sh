@@ -79,10 +79,6 @@ print_header
 print_service_config "ddns-updater.service"
 print_timer_config "ddns-updater.timer"
 
-# Check backup cleanup service
-print_service_config "ddns-backup-cleanup.service"
-print_timer_config "ddns-backup-cleanup.timer"
-
 # Check target
 echo "=== ddns-updater.target ==="
 if systemctl is-loaded "ddns-updater.target" &>/dev/null; then
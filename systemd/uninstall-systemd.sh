@@ -53,7 +53,7 @@ if systemctl is-active --quiet ddns-updater.service; then
     systemctl stop ddns-updater.service
 fi
 
-# Handle backup cleanup services
+# Handle the backup cleanup units installed by earlier versions
 if systemctl list-unit-files ddns-backup-cleanup.timer &>/dev/null; then
     print_status "Found backup cleanup service, removing..."
     
@@ -96,6 +96,7 @@ print_status "Removing systemd service files..."
 rm -f /etc/systemd/system/ddns-updater.target
 rm -f /etc/systemd/system/ddns-updater.service
 rm -f /etc/systemd/system/ddns-updater.timer
+# Left by earlier versions
 rm -f /etc/systemd/system/ddns-backup-cleanup.service
 rm -f /etc/systemd/system/ddns-backup-cleanup.timer
 
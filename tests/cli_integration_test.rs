@@ -1,4 +1,5 @@
-use ddns_updater::cli::Args;
+use ddns_updater::cli::{AddressSourceKind, Args, ResolverKind, StorageKind};
+use ddns_updater::infrastructure::DEFAULT_DOH_URL;
 use std::path::PathBuf;
 use tempfile::tempdir;
 
@@ -25,10 +26,27 @@ server {
         host: "example.com".to_string(),
         nginx_config: Some(config_file.clone()),
         config_dir: None,
+        config_file: None,
         pattern: "*.conf".to_string(),
         backup_dir: Some(temp_dir.path().join("backups")),
+        storage: StorageKind::Json,
+        max_backups: 10,
+        backup_retention_days: 30,
         no_reload: true,
+        dry_run: false,
+        rewrite_prefix: false,
+        address_source: AddressSourceKind::Dns,
+        interface: None,
+        gateway: None,
+        gateway_protocols: vec![],
+        resolver: ResolverKind::System,
+        nameservers: vec![],
+        authoritative: false,
+        doh_url: DEFAULT_DOH_URL.to_string(),
+        ip_sources: vec![],
+        ip_quorum: None,
         verbose: true,
+        command: None,
     };
 
     // Test that CLI arguments flow through correctly
@@ -69,10 +87,27 @@ server {
         host: "test.local".to_string(),
         nginx_config: None,
         config_dir: Some(temp_dir.path().to_path_buf()),
+        config_file: None,
         pattern: "*.nginx".to_string(),
         backup_dir: None,
+        storage: StorageKind::Json,
+        max_backups: 10,
+        backup_retention_days: 30,
         no_reload: false,
+        dry_run: false,
+        rewrite_prefix: false,
+        address_source: AddressSourceKind::Dns,
+        interface: None,
+        gateway: None,
+        gateway_protocols: vec![],
+        resolver: ResolverKind::System,
+        nameservers: vec![],
+        authoritative: false,
+        doh_url: DEFAULT_DOH_URL.to_string(),
+        ip_sources: vec![],
+        ip_quorum: None,
         verbose: false,
+        command: None,
     };
 
     // Test CLI argument flow for directory-based configuration
@@ -97,10 +132,27 @@ fn test_cli_args_all_flags_integration() {
         host: "comprehensive.example.com".to_string(),
         nginx_config: Some(config_file.clone()),
         config_dir: None,
+        config_file: None,
         pattern: "*.conf".to_string(),
         backup_dir: Some(temp_dir.path().join("custom_backups")),
+        storage: StorageKind::Json,
+        max_backups: 10,
+        backup_retention_days: 30,
         no_reload: true,
+        dry_run: false,
+        rewrite_prefix: false,
+        address_source: AddressSourceKind::Dns,
+        interface: None,
+        gateway: None,
+        gateway_protocols: vec![],
+        resolver: ResolverKind::System,
+        nameservers: vec![],
+        authoritative: false,
+        doh_url: DEFAULT_DOH_URL.to_string(),
+        ip_sources: vec![],
+        ip_quorum: None,
         verbose: true,
+        command: None,
     };
 
     // Validate complete CLI argument chain
@@ -125,10 +177,27 @@ fn test_cli_args_error_handling_flow() {
         host: "error.test".to_string(),
         nginx_config: Some(PathBuf::from("/nonexistent/file.conf")),
         config_dir: None,
+        config_file: None,
         pattern: "*.conf".to_string(),
         backup_dir: None,
+        storage: StorageKind::Json,
+        max_backups: 10,
+        backup_retention_days: 30,
         no_reload: true,
+        dry_run: false,
+        rewrite_prefix: false,
+        address_source: AddressSourceKind::Dns,
+        interface: None,
+        gateway: None,
+        gateway_protocols: vec![],
+        resolver: ResolverKind::System,
+        nameservers: vec![],
+        authoritative: false,
+        doh_url: DEFAULT_DOH_URL.to_string(),
+        ip_sources: vec![],
+        ip_quorum: None,
         verbose: false,
+        command: None,
     };
 
     // Verify args are created correctly even with invalid paths
@@ -144,10 +213,27 @@ fn test_cli_args_error_handling_flow() {
         host: "error.test".to_string(),
         nginx_config: None,
         config_dir: Some(PathBuf::from("/nonexistent/directory")),
+        config_file: None,
         pattern: "*.conf".to_string(),
         backup_dir: None,
+        storage: StorageKind::Json,
+        max_backups: 10,
+        backup_retention_days: 30,
         no_reload: true,
+        dry_run: false,
+        rewrite_prefix: false,
+        address_source: AddressSourceKind::Dns,
+        interface: None,
+        gateway: None,
+        gateway_protocols: vec![],
+        resolver: ResolverKind::System,
+        nameservers: vec![],
+        authoritative: false,
+        doh_url: DEFAULT_DOH_URL.to_string(),
+        ip_sources: vec![],
+        ip_quorum: None,
         verbose: false,
+        command: None,
     };
 
     assert_eq!(args_dir.host, "error.test");
@@ -173,10 +259,27 @@ fn test_cli_args_pattern_and_hostname_combinations() {
             host: hostname.to_string(),
             nginx_config: None,
             config_dir: Some(PathBuf::from("/etc/nginx")),
+            config_file: None,
             pattern: pattern.to_string(),
             backup_dir: None,
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload: false,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose: false,
+            command: None,
         };
 
         // Test that CLI argument combinations work correctly
@@ -202,10 +305,27 @@ fn test_cli_args_flag_combinations() {
             host: "flags.test".to_string(),
             nginx_config: Some(PathBuf::from("test.conf")),
             config_dir: None,
+            config_file: None,
             pattern: "*.conf".to_string(),
             backup_dir: Some(PathBuf::from("/custom/backup")),
+            storage: StorageKind::Json,
+            max_backups: 10,
+            backup_retention_days: 30,
             no_reload,
+            dry_run: false,
+            rewrite_prefix: false,
+            address_source: AddressSourceKind::Dns,
+            interface: None,
+            gateway: None,
+            gateway_protocols: vec![],
+            resolver: ResolverKind::System,
+            nameservers: vec![],
+            authoritative: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+            ip_sources: vec![],
+            ip_quorum: None,
             verbose,
+            command: None,
         };
 
         // Verify flag combinations flow through correctly